@@ -54,6 +54,9 @@ pub const CONFIG_OPTION_SEMICOLON_SEPARATORS : c_int                    = 0x02;
 pub const CONFIG_OPTION_COLON_ASSIGNMENT_FOR_GROUPS : c_int             = 0x04;
 pub const CONFIG_OPTION_COLON_ASSIGNMENT_FOR_NON_GROUPS : c_int         = 0x08;
 pub const CONFIG_OPTION_OPEN_BRACE_ON_SEPARATE_LINE : c_int             = 0x10;
+pub const CONFIG_OPTION_ALLOW_SCIENTIFIC_NOTATION : c_int               = 0x20;
+pub const CONFIG_OPTION_FSYNC : c_int                                   = 0x40;
+pub const CONFIG_OPTION_ALLOW_OVERRIDES : c_int                         = 0x80;
 
 pub const CONFIG_TRUE : c_int                                           = 1;
 pub const CONFIG_FALSE : c_int                                          = 0;
@@ -100,19 +103,29 @@ pub struct config_list_t {
 pub struct config_t {
     pub root : *mut config_setting_t,
     pub destructor : Option<extern "C" fn(*mut c_void) -> ()>,
+    pub options : c_int,
     pub tab_width : c_ushort,
+    pub float_precision : c_ushort,
     pub default_format : c_short,
     pub include_dir : *const c_schar,
+    pub include_fn : Option<config_include_fn_t>,
     pub error_text : *const c_schar,
     pub error_file : *const c_schar,
     pub error_line : c_int,
     pub error_type : config_error_t,
-    pub filenames : *mut *mut c_schar,
-    pub num_filenames : c_uint,
+    pub filenames : *mut *const c_schar,
+    pub hook : *mut c_void,
 }
 
 pub type destructor_callback = extern "C" fn(ptr : *mut c_void) -> ();
 
+pub type config_include_fn_t = extern "C" fn(config : *mut config_t,
+    include_dir : *const c_schar, path : *const c_schar, error :
+    *mut *const c_schar) -> *mut *const c_schar;
+
+pub type config_fatal_error_fn_t = extern "C" fn(message : *const c_schar)
+    -> ();
+
 #[link(name = "config")]
 extern "C" {
     pub fn config_read (config : *mut config_t, stream : *mut FILE) -> c_int;
@@ -121,9 +134,18 @@ extern "C" {
     pub fn config_set_options (config : *mut config_t, options : c_int);
     pub fn config_get_options (config : *const config_t) -> c_int;
 
+    pub fn config_set_option (config : *mut config_t, option : c_int, flag :
+        c_int);
+    pub fn config_get_option (config : *const config_t, option : c_int)
+        -> c_int;
+
     pub fn config_set_auto_convert (config : *mut config_t, flag : c_int);
     pub fn config_get_auto_convert (config : *const config_t) -> c_int;
 
+    pub fn config_set_float_precision (config : *mut config_t, digits :
+        c_ushort);
+    pub fn config_get_float_precision (config : *const config_t) -> c_ushort;
+
     pub fn config_read_string (config : *mut config_t, str : *const c_schar)
         -> c_int;
 
@@ -136,9 +158,20 @@ extern "C" {
         destructor_callback);
     pub fn config_set_include_dir (config : *mut config_t, include_dir :
         *const c_schar);
+    pub fn config_set_include_func (config : *mut config_t, func :
+        Option<config_include_fn_t>);
+    pub fn config_default_include_func (config : *mut config_t, include_dir :
+        *const c_schar, path : *const c_schar, error : *mut *const c_schar)
+        -> *mut *const c_schar;
+
+    pub fn config_set_fatal_error_func (func :
+        Option<config_fatal_error_fn_t>);
+
+    pub fn config_set_hook (config : *mut config_t, hook : *mut c_void);
 
     pub fn config_init (config : *mut config_t);
     pub fn config_destroy (config : *mut config_t);
+    pub fn config_clear (config : *mut config_t);
 
     pub fn config_setting_get_int (setting : *const config_setting_t) -> c_int;
     pub fn config_setting_get_int64 (setting : *const config_setting_t)
@@ -238,6 +271,15 @@ pub fn config_get_include_dir (config : *const config_t) -> *const c_schar {
     unsafe { (*config).include_dir }
 }
 
+pub fn config_get_hook (config : *const config_t) -> *mut c_void {
+    unsafe { (*config).hook }
+}
+
+pub fn config_setting_get_hook (setting : *const config_setting_t)
+    -> *mut c_void {
+    unsafe { (*setting).hook }
+}
+
 pub fn config_setting_type (setting : *const config_setting_t) -> c_int {
     unsafe { (*setting).setting_type as c_int }
 }
@@ -0,0 +1,474 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+//! Derive macro generating `librustconfig::typed::LibConfig` and 
+//! `librustconfig::typed::ConfigValue` implementations, see 
+//! `librustconfig::typed::LibConfig` for supported attributes.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Expr, Fields, LitStr, Path};
+
+/// Derive `LibConfig` and `ConfigValue` for struct or enum.
+#[proc_macro_derive(LibConfig, attributes(libconfig))]
+pub fn derive_lib_config(input : proc_macro::TokenStream) 
+    -> proc_macro::TokenStream {
+    
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => { tokens.into() },
+        Err(error) => { error.to_compile_error().into() }
+    }
+}
+
+/// Options of field set by `#[libconfig(...)]` attributes.
+#[derive(Default)]
+struct FieldOptions {
+    rename : Option<String>,
+    default : Option<Option<Path>>,
+    flatten : bool,
+    skip : bool,
+    min : Option<Expr>,
+    max : Option<Expr>,
+    hex : bool,
+    collection : Option<&'static str>
+}
+
+/// Field of struct or struct variant.
+struct Field {
+    ident : syn::Ident,
+    name : String,
+    options : FieldOptions
+}
+
+// Generate trait implementations.
+fn expand(input : &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = 
+        input.generics.split_for_impl();
+    
+    let (from_config, to_config, read_value, write_value) = {
+        match &input.data {
+            Data::Struct(data) => { expand_struct(&data.fields)? },
+            Data::Enum(data) => { expand_enum(data)? },
+            Data::Union(_) => {
+                return Err(syn::Error::new(input.span(), 
+                    "LibConfig can't be derived for unions"));
+            }
+        }
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::librustconfig::typed::LibConfig 
+            for #ident #type_generics #where_clause {
+            
+            fn from_config(reader : &::librustconfig::config::OptionReader) 
+                -> ::librustconfig::config::Result<Self> {
+                #from_config
+            }
+
+            fn to_config(&self, 
+                writer : &::librustconfig::config::OptionWriter) 
+                -> ::librustconfig::config::Result<()> {
+                #to_config
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::librustconfig::typed::ConfigValue 
+            for #ident #type_generics #where_clause {
+            
+            fn read_value(reader : &::librustconfig::config::OptionReader) 
+                -> ::librustconfig::config::Result<Self> {
+                #read_value
+            }
+
+            fn write_value(&self, 
+                writer : &::librustconfig::config::OptionWriter, 
+                name : &str, format : ::librustconfig::typed::Format) 
+                -> ::librustconfig::config::Result<()> {
+                #write_value
+            }
+        }
+    })
+}
+
+// Generate method bodies of struct with named fields.
+fn expand_struct(fields : &Fields) 
+    -> syn::Result<(TokenStream, TokenStream, TokenStream, TokenStream)> {
+    
+    let fields = named_fields(fields)?;
+    let read = read_fields(&fields, &quote! { Self });
+    let writes = fields.iter().map(|field| {
+        let ident = &field.ident;
+        write_field(field, &quote! { &self.#ident })
+    });
+
+    Ok((
+        quote! {
+            ::librustconfig::typed::expect_group(reader)?;
+            #read
+        },
+        quote! {
+            #(#writes)*
+            Ok(())
+        },
+        quote! {
+            <Self as ::librustconfig::typed::LibConfig>::from_config(reader)
+        },
+        quote! {
+            let _ = format;
+            let group = ::librustconfig::typed::create_group(writer, name)?;
+            ::librustconfig::typed::LibConfig::to_config(self, &group)
+        }
+    ))
+}
+
+// Generate method bodies of enum.
+fn expand_enum(data : &syn::DataEnum) 
+    -> syn::Result<(TokenStream, TokenStream, TokenStream, TokenStream)> {
+    
+    let mut names = Vec::new();
+    let mut units = Vec::new();
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut values = Vec::new();
+
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let options = field_options(&variant.attrs)?;
+        let name = options.rename.clone()
+            .unwrap_or_else(|| { ident.to_string() });
+        names.push(name.clone());
+
+        match &variant.fields {
+            Fields::Unit => {
+                units.push((name.clone(), ident));
+                reads.push(quote! { #name => { Ok(Self::#ident) }, });
+                writes.push(quote! {
+                    Self::#ident => {
+                        ::librustconfig::typed::retain_variant(writer, #name);
+                        ::librustconfig::typed::create_group(writer, #name)?;
+                        Ok(())
+                    },
+                });
+                values.push(quote! {
+                    Self::#ident => {
+                        ::librustconfig::typed::ConfigValue::write_value(
+                            &::std::string::String::from(#name), writer, 
+                            name, format)
+                    },
+                });
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field = &fields.unnamed[0];
+                let format = format(&field_options(&field.attrs)?);
+                reads.push(quote! {
+                    #name => {
+                        Ok(Self::#ident(
+                            ::librustconfig::typed::ConfigValue::read_value(
+                                &value)?))
+                    },
+                });
+                writes.push(quote! {
+                    Self::#ident(value) => {
+                        ::librustconfig::typed::retain_variant(writer, #name);
+                        ::librustconfig::typed::ConfigValue::write_value(
+                            value, writer, #name, #format)
+                    },
+                });
+            },
+            Fields::Named(_) => {
+                let fields = named_fields(&variant.fields)?;
+                let read = read_fields(&fields, &quote! { Self::#ident });
+                let idents : Vec<_> = fields.iter()
+                    .map(|field| { &field.ident }).collect();
+                let bindings : Vec<_> = idents.iter()
+                    .map(|ident| { format_ident!("field_{}", ident) })
+                    .collect();
+                let field_writes = fields.iter().zip(&bindings)
+                    .map(|(field, binding)| { 
+                        write_field(field, &quote! { #binding }) 
+                    });
+                
+                reads.push(quote! {
+                    #name => {
+                        let reader = &value;
+                        ::librustconfig::typed::expect_group(reader)?;
+                        #read
+                    },
+                });
+                writes.push(quote! {
+                    Self::#ident { #(#idents : #bindings),* } => {
+                        ::librustconfig::typed::retain_variant(writer, #name);
+                        let writer = &::librustconfig::typed::create_group(
+                            writer, #name)?;
+                        #(#field_writes)*
+                        Ok(())
+                    },
+                });
+            },
+            _ => {
+                return Err(syn::Error::new(variant.span(), 
+                    "LibConfig supports unit, newtype and struct variants"));
+            }
+        }
+    }
+
+    let unit_reads = units.iter().map(|(name, ident)| {
+        quote! { #name => { Ok(Self::#ident) }, }
+    });
+    let unit_names = units.iter().map(|(name, _)| { name });
+    let variant_values = {
+        if values.len() == data.variants.len() {
+            quote! { #(#values)* }
+        } else {
+            quote! {
+                #(#values)*
+                _ => {
+                    let _ = format;
+                    let group = ::librustconfig::typed::create_group(
+                        writer, name)?;
+                    ::librustconfig::typed::LibConfig::to_config(self, &group)
+                }
+            }
+        }
+    };
+
+    Ok((
+        quote! {
+            let (variant, value) = 
+                ::librustconfig::typed::read_variant(reader)?;
+            let _ = &value;
+            match variant.as_str() {
+                #(#reads)*
+                _ => {
+                    Err(::librustconfig::typed::unknown_variant(reader, 
+                        &variant, &[#(#names),*]))
+                }
+            }
+        },
+        quote! {
+            match self {
+                #(#writes)*
+            }
+        },
+        quote! {
+            if reader.is_section() == Some(true) {
+                return <Self as ::librustconfig::typed::LibConfig>
+                    ::from_config(reader);
+            }
+            
+            let variant : ::std::string::String = 
+                ::librustconfig::typed::ConfigValue::read_value(reader)?;
+            match variant.as_str() {
+                #(#unit_reads)*
+                _ => {
+                    Err(::librustconfig::typed::unknown_variant(reader, 
+                        &variant, &[#(#unit_names),*]))
+                }
+            }
+        },
+        quote! {
+            match self {
+                #variant_values
+            }
+        }
+    ))
+}
+
+// Collect named fields with their options.
+fn named_fields(fields : &Fields) -> syn::Result<Vec<Field>> {
+    match fields {
+        Fields::Named(fields) => {
+            fields.named.iter().map(|field| {
+                let ident = field.ident.clone().unwrap();
+                let options = field_options(&field.attrs)?;
+                let name = options.rename.clone()
+                    .unwrap_or_else(|| { ident.to_string() });
+                Ok(Field { ident, name, options })
+            }).collect()
+        },
+        _ => {
+            Err(syn::Error::new(fields.span(), 
+                "LibConfig supports structs with named fields"))
+        }
+    }
+}
+
+// Parse `#[libconfig(...)]` attributes.
+fn field_options(attrs : &[syn::Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in attrs.iter().filter(|attr| { 
+        attr.path().is_ident("libconfig") 
+    }) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?
+                    .value());
+            } else if meta.path.is_ident("default") {
+                options.default = {
+                    if meta.input.peek(syn::Token![=]) {
+                        Some(Some(meta.value()?.parse::<LitStr>()?
+                            .parse::<Path>()?))
+                    } else {
+                        Some(None)
+                    }
+                };
+            } else if meta.path.is_ident("flatten") {
+                options.flatten = true;
+            } else if meta.path.is_ident("skip") {
+                options.skip = true;
+            } else if meta.path.is_ident("hex") {
+                options.hex = true;
+            } else if meta.path.is_ident("array") {
+                options.collection = Some("Array");
+            } else if meta.path.is_ident("list") {
+                options.collection = Some("List");
+            } else if meta.path.is_ident("range") {
+                meta.parse_nested_meta(|bound| {
+                    if bound.path.is_ident("min") {
+                        options.min = Some(bound.value()?.parse()?);
+                    } else if bound.path.is_ident("max") {
+                        options.max = Some(bound.value()?.parse()?);
+                    } else {
+                        return Err(bound.error("expected `min` or `max`"));
+                    }
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error("unknown libconfig attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+// Generate construction of struct or variant from fields of group `reader`.
+fn read_fields(fields : &[Field], path : &TokenStream) -> TokenStream {
+    let values = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let name = &field.name;
+        let options = &field.options;
+
+        let value = {
+            if options.skip {
+                quote! { ::core::default::Default::default() }
+            } else if options.flatten {
+                quote! { 
+                    ::librustconfig::typed::LibConfig::from_config(reader)? 
+                }
+            } else {
+                match &options.default {
+                    Some(Some(default)) => {
+                        quote! { 
+                            ::librustconfig::typed::read_member_or(reader, 
+                                #name, #default)? 
+                        }
+                    },
+                    Some(None) => {
+                        quote! {
+                            ::librustconfig::typed::read_member_or(reader, 
+                                #name, ::core::default::Default::default)?
+                        }
+                    },
+                    None => {
+                        quote! {
+                            ::librustconfig::typed::read_member(reader, 
+                                #name)?
+                        }
+                    }
+                }
+            }
+        };
+
+        if (options.min.is_some() || options.max.is_some()) && 
+            !options.skip && !options.flatten {
+            let min = bound(&options.min);
+            let max = bound(&options.max);
+            quote! {
+                #ident : {
+                    let value = #value;
+                    ::librustconfig::typed::check_range(reader, #name, 
+                        &value, #min, #max)?;
+                    value
+                }
+            }
+        } else {
+            quote! { #ident : #value }
+        }
+    });
+
+    quote! { Ok(#path { #(#values),* }) }
+}
+
+// Generate write of field value to group `writer`.
+fn write_field(field : &Field, value : &TokenStream) -> TokenStream {
+    let name = &field.name;
+    let options = &field.options;
+    
+    if options.skip {
+        quote! {}
+    } else if options.flatten {
+        quote! { 
+            ::librustconfig::typed::LibConfig::to_config(#value, writer)?; 
+        }
+    } else {
+        let format = format(options);
+        quote! {
+            ::librustconfig::typed::ConfigValue::write_value(#value, writer, 
+                #name, #format)?;
+        }
+    }
+}
+
+// Generate format of written field.
+fn format(options : &FieldOptions) -> TokenStream {
+    let hex = options.hex;
+    let collection = format_ident!("{}", options.collection.unwrap_or("Auto"));
+    quote! {
+        ::librustconfig::typed::Format {
+            hex : #hex,
+            collection : ::librustconfig::typed::CollectionFormat::#collection
+        }
+    }
+}
+
+// Generate optional range bound.
+fn bound(value : &Option<Expr>) -> TokenStream {
+    match value {
+        Some(value) => { quote! { Some(#value) } },
+        None => { quote! { None } }
+    }
+}
@@ -0,0 +1,263 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{Config, Errors, Result};
+use crate::merge::{self, MergePolicy, Merger};
+
+/// Setting overrides given on command line as `path=value`, value is written
+/// in libconfig syntax: `server.port=9000`, `name="app"`,
+/// `features=["a", "b"]`, `limits={ cpu = 2; }`.
+pub struct CliOverrides {
+    overrides : Vec<CliOverride>
+}
+
+/// Parsed command line override.
+struct CliOverride {
+    argument : String,
+    names : Vec<String>,
+    value : Config
+}
+
+impl CliOverrides {
+
+    /// Constructor.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// let overrides = CliOverrides::new();
+    /// ```
+    pub fn new() -> CliOverrides {
+        CliOverrides {
+            overrides : Vec::new()
+        }
+    }
+
+    /// Extract overrides from command line arguments `--set path=value`,
+    /// `--set=path=value`, `-o path=value` and `-opath=value`. Return 
+    /// overrides and remaining arguments.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// let args = vec!["app", "--set", "server.port=9000", "-v"];
+    /// let (overrides, args) = CliOverrides::from_args(args).unwrap();
+    /// assert_eq!(overrides.len(), 1);
+    /// assert_eq!(args, vec!["app", "-v"]);
+    /// ```
+    pub fn from_args<I, S>(args : I) -> Result<(CliOverrides, Vec<String>)>
+        where I: IntoIterator<Item = S>, S: Into<String> {
+        
+        let mut overrides = CliOverrides::new();
+        let mut rest = Vec::new();
+        
+        let mut args = args.into_iter().map(|arg| { arg.into() });
+        while let Some(arg) = args.next() {
+            if arg == "--set" || arg == "-o" {
+                match args.next() {
+                    Some(value) => { 
+                        overrides.add_argument(&format!("{} {}", arg, value),
+                            &value)?; 
+                    },
+                    None => {
+                        return Err(Errors::OverrideError(format!(
+                            "{}: missing path=value", arg)));
+                    }
+                }
+            } else if let Some(value) = arg.strip_prefix("--set=")
+                .or_else(|| { 
+                    arg.strip_prefix("-o").filter(|value| { 
+                        value.contains('=') 
+                    })
+                }) {
+                overrides.add_argument(&arg, value)?;
+            } else {
+                rest.push(arg);
+            }
+        }
+        Ok((overrides, rest))
+    }
+
+    /// Parse override `path=value`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// let mut overrides = CliOverrides::new();
+    /// overrides.add("features=[\"a\", \"b\"]").unwrap();
+    /// assert!(overrides.add("features=[\"a\",").is_err());
+    /// ```
+    pub fn add(&mut self, argument : &str) -> Result<()> {
+        self.add_argument(argument, argument)
+    }
+
+    /// Return count of overrides.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// let mut overrides = CliOverrides::new();
+    /// overrides.add("debug=true").unwrap();
+    /// assert_eq!(overrides.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    /// Return true if there are no overrides.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// assert!(CliOverrides::new().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Write overrides to config in order they were given. Missing settings 
+    /// and groups on the path are created, settings are replaced by value, 
+    /// groups are merged with group value. Overrides are tried on a copy of 
+    /// config first, config is left unchanged if any override can't be 
+    /// applied.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::cli::CliOverrides;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { port = 80; };").unwrap();
+    /// 
+    /// let mut overrides = CliOverrides::new();
+    /// overrides.add("server.port=9000").unwrap();
+    /// overrides.apply(&mut cfg).unwrap();
+    /// assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(9000));
+    /// ```
+    pub fn apply(&self, config : &mut Config) -> Result<()> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        
+        let staged = config.empty_copy();
+        let target = staged.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |_, _| {})
+            .merge_group(target, root);
+        self.write(target)?;
+        
+        self.write(root)
+    }
+
+    // Write overrides to group.
+    fn write(&self, root : merge::Setting) -> Result<()> {
+        for item in &self.overrides {
+            let source = item.value()?;
+            let setting = merge::upsert(root, &item.names, 
+                merge::setting_type(source)).map_err(|message| {
+                    Errors::OverrideError(format!("{}: {}", item.argument, 
+                        message))
+                })?;
+            
+            Merger::new(MergePolicy::default(), &mut |_, _| {})
+                .assign(setting, source);
+        }
+        Ok(())
+    }
+
+    // Parse argument value path=value.
+    fn add_argument(&mut self, argument : &str, value : &str) -> Result<()> {
+        let error = |message : String| -> Errors {
+            Errors::OverrideError(format!("{}: {}", argument, message))
+        };
+
+        let (path, value) = value.split_once('=').ok_or_else(|| {
+            error(String::from("expected path=value"))
+        })?;
+        let path = path.trim();
+        let names : Vec<String> = path.split('.').map(String::from).collect();
+        if let Some(name) = names.iter().find(|name| { 
+            !merge::valid_name(name) 
+        }) {
+            return Err(error(format!("invalid setting name {:?} in {:?}", 
+                name, path)));
+        }
+
+        let mut config = Config::new();
+        if config.load_from_string(format!("value = {}\n;", value)).is_err() {
+            let reason = config.error_text()
+                .unwrap_or_else(|| { String::from("syntax error") });
+            let hint = {
+                if value.trim().chars().all(|c| { 
+                    c.is_alphanumeric() || c == '_' || c == '-' 
+                }) {
+                    " (strings must be quoted)"
+                } else {
+                    ""
+                }
+            };
+            return Err(error(format!("invalid value {:?}: {}{}", value, 
+                reason, hint)));
+        }
+        
+        let root = config.root_setting().ok_or_else(|| { 
+            error(String::from("invalid value")) 
+        })?;
+        if merge::length(root) != 1 {
+            return Err(error(format!("invalid value {:?}: unexpected {:?}", 
+                value, merge::setting_path(merge::element(root, 1)))));
+        }
+
+        self.overrides.push(CliOverride {
+            argument : argument.to_string(),
+            names,
+            value : config
+        });
+        Ok(())
+    }
+
+}
+
+impl Default for CliOverrides {
+    fn default() -> CliOverrides {
+        CliOverrides::new()
+    }
+}
+
+impl CliOverride {
+
+    // Return parsed value setting.
+    fn value(&self) -> Result<merge::Setting> {
+        self.value.root_setting().map(|root| { merge::element(root, 0) })
+            .ok_or(Errors::ElementNotExists)
+    }
+}
@@ -29,18 +29,85 @@
 /******************************************************************************/
 
 use libconfig_sys as raw;
+use crate::diff::{self, Change};
+use crate::document::{self, Comments, Document};
+use crate::include::{self, IncludeCache, IncludeChecker, IncludeSource, 
+    IncludeSources};
+use crate::merge::{self, CollectionMerge, MergePolicy, Merger, ScalarMerge};
+use crate::interpolate::Interpolator;
+use crate::secret::{self, DefaultSecretResolver, Secret, SecretResolver};
+use crate::stack::Origin;
+use crate::usage::{self, UnusedSetting};
+use crate::vfs::{self, ConfigFs, DiskFs};
 
 use std::{mem::MaybeUninit, path};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
+use std::fmt;
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Mutex;
 
 /// Configuration file.
 pub struct Config {
-    config : raw::config_t,
+    config : Box<raw::config_t>,
+    context : Box<ConfigContext>,
     root_element : Option<*mut raw::config_setting_t>
 }
 
+/// Include resolver callback.
+type IncludeResolver = dyn Fn(&path::Path, &str) -> Result<Vec<IncludeSource>>;
+
+/// Rust side state of the config, reachable from libconfig callbacks through
+/// the config hook.
+struct ConfigContext {
+    fs : Box<dyn ConfigFs>,
+    main_file : Option<String>,
+    include_resolver : Option<Box<IncludeResolver>>,
+    include_sources : IncludeSources,
+    include_cache : IncludeCache,
+    include_contents : HashMap<path::PathBuf, String>,
+    include_depth : usize,
+    include_error : Option<CString>,
+    save_backup : bool,
+    export_secrets : bool,
+    secret_resolver : Box<dyn SecretResolver>,
+    source_files : Vec<path::PathBuf>,
+    include_dirs : Vec<path::PathBuf>,
+    main_text : String,
+    documents : RefCell<HashMap<Option<String>, Option<Document>>>
+}
+
+/// Rust side state of the setting kept in its libconfig hook, released by 
+/// the config destructor when setting is destroyed.
+#[derive(Default)]
+pub(crate) struct SettingData {
+    user : RefCell<Option<Box<dyn Any>>>,
+    secret : Cell<bool>,
+    defaulted : Cell<bool>,
+    accessed : Cell<bool>,
+    comments : RefCell<Option<Comments>>,
+    pub(crate) origin : RefCell<Option<Origin>>
+}
+
+/// Config options flags.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConfigOption {
+    AutoConvert,
+    SemicolonSeparators,
+    ColonAssignmentForGroups,
+    ColonAssignmentForNonGroups,
+    OpenBraceOnSeparateLine,
+    AllowScientificNotation,
+    Fsync,
+    AllowOverrides
+}
+
 /// Option value type.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OptionType {
     IntegerType,
     Int64Type,
@@ -52,7 +119,7 @@ pub enum OptionType {
 /// Writer for configuration option.
 #[derive(Clone, Copy)]
 pub struct OptionWriter {
-    element : Option<*mut raw::config_setting_t>
+    pub(crate) element : Option<*mut raw::config_setting_t>
 }
 
 /// Writer for collection (array, list) option.
@@ -63,14 +130,16 @@ pub struct CollectionWriter {
 
 /// Reader for configuration option.
 pub struct OptionReader {
-    element : Option<*mut raw::config_setting_t>
+    pub(crate) element : Option<*mut raw::config_setting_t>,
+    tracked : bool
 }
 
 /// Reader for collection (array, list) option.
 pub struct CollectionReaderIterator {
     element : Option<*mut raw::config_setting_t>,
     pos : i32,
-    size : i32
+    size : i32,
+    tracked : bool
 }
 
 /// Config errors codes.
@@ -80,11 +149,26 @@ pub enum Errors {
     FileNotExists,
     SaveError,
     ElementNotExists,
-    DeleteError
+    DeleteError,
+    IncludeError(String),
+    MergeConflict(String, String),
+    OverrideError(String),
+    ReferenceError(String),
+    SecretError(String),
+    SchemaError(String),
+    ValueError(String),
+    PatchError(String),
+    DocumentError(String),
+    QueryError(String),
+    JsonError(String),
+    TomlError(String),
+    YamlError(String),
+    ReadError(String),
+    RollbackError(String)
 }
 
 /// Config result type.
-type Result<T> = std::result::Result<T, Errors>;
+pub type Result<T> = std::result::Result<T, Errors>;
 
 impl Config {
     
@@ -98,13 +182,36 @@ impl Config {
     /// let cfg = Config::new();
     /// ```
     pub fn new() -> Config {
-        let mut c = MaybeUninit::<raw::config_t>::uninit();
-        let cfg = unsafe {
-            raw::config_init(c.as_mut_ptr());
-            c.assume_init()
+        let mut cfg = Box::new(MaybeUninit::<raw::config_t>::uninit());
+        let mut cfg = unsafe {
+            raw::config_init(cfg.as_mut_ptr());
+            Box::from_raw(Box::into_raw(cfg) as *mut raw::config_t)
         };
+
+        let mut context = Box::new(ConfigContext {
+            fs : Box::new(DiskFs),
+            main_file : None,
+            include_resolver : None,
+            include_sources : IncludeSources::default(),
+            include_cache : HashMap::new(),
+            include_contents : HashMap::new(),
+            include_depth : include::DEFAULT_INCLUDE_DEPTH,
+            include_error : None,
+            save_backup : false,
+            export_secrets : false,
+            secret_resolver : Box::new(DefaultSecretResolver),
+            source_files : Vec::new(),
+            include_dirs : Vec::new(),
+            main_text : String::new(),
+            documents : RefCell::new(HashMap::new())
+        });
+        unsafe {
+            raw::config_set_hook(&mut *cfg, 
+                &mut *context as *mut ConfigContext as *mut c_void);
+            raw::config_set_destructor(&mut *cfg, setting_destructor);
+        }
         
-        let option = raw::config_root_setting(&cfg);
+        let option = raw::config_root_setting(&*cfg);
         let element = {    
             if option.is_null() {
                 None
@@ -115,11 +222,17 @@ impl Config {
     
         Config {
             config : cfg,
+            context,
             root_element : element
         }
     }
     
-    /// Load config file from file and parse it.
+    /// Load config file from file and parse it. Included files are checked 
+    /// before parsing, include cycles and includes nested deeper than 
+    /// include depth limit are reported as `Errors::IncludeError` naming the
+    /// include chain. libconfig parses the same contents of included files 
+    /// that were checked. File which can't be read or isn't valid UTF-8 is 
+    /// reported as `Errors::ReadError`.
     /// 
     /// # Example
     /// ```
@@ -132,22 +245,23 @@ impl Config {
     /// }
     /// ```
     pub fn load_from_file(&mut self, file_name : &path::Path) -> Result<()> {
-        if file_name.exists() {
-            unsafe {
-                let result = raw::config_read_file(&mut self.config, 
-                    CString::new(file_name.as_os_str().to_str().unwrap())
-                        .unwrap().as_ptr()
-                );
-                
-                if result == raw::CONFIG_TRUE {
-                    self.root_element = 
-                        Some(raw::config_root_setting(&self.config));
-                    Ok(())
-                } else {
-                    self.root_element = None;
-                    Err(Errors::ParseError)
+        if self.context.fs.exists(file_name) {
+            let contents = {
+                match self.context.fs.read(file_name) {
+                    Ok(contents) => { contents },
+                    Err(error) => { 
+                        return Err(Errors::ReadError(format!("{}: {}", 
+                            file_name.display(), error))); 
+                    }
                 }
-            }
+            };
+            let name = file_name.display().to_string();
+            let identity = self.context.fs.canonicalize(file_name);
+
+            self.context.source_files = vec![file_name.to_path_buf()];
+            self.check_includes(&name, &identity.display().to_string(), 
+                &contents)?;
+            self.read_string(contents, Some(name))
         } else {
             Err(Errors::FileNotExists)
         }
@@ -167,13 +281,30 @@ impl Config {
     pub fn load_from_string<S>(&mut self, config_string : S) -> Result<()>
         where S: Into<String> {
           
+        let config_string = config_string.into();
+
+        self.context.source_files.clear();
+        self.check_includes("<string>", "<string>", &config_string)?;
+        self.read_string(config_string, None)
+    }
+
+    // Parse configuration contents, file name is reported as source file of 
+    // the settings read from contents.
+    fn read_string(&mut self, contents : String, file_name : Option<String>) 
+        -> Result<()> {
+        
+        self.context.main_file = file_name;
+        self.context.main_text = contents.clone();
+        self.context.documents.borrow_mut().clear();
         let result = unsafe { 
-            raw::config_read_string(&mut self.config, 
-                CString::new(config_string.into()).unwrap().as_ptr())
+            raw::config_read_string(&mut *self.config, 
+                CString::new(contents).unwrap().as_ptr())
         };
+        self.context.include_sources.release_files();
+        self.context.include_cache.clear();
         
         if result == raw::CONFIG_TRUE {
-            let option = raw::config_root_setting(&self.config);
+            let option = raw::config_root_setting(&*self.config);
             
             if option.is_null() {
                 self.root_element = None;
@@ -188,7 +319,9 @@ impl Config {
         }
     }
    
-   /// Save current config to file.
+   /// Save current config to file. File is replaced atomically: config is
+   /// written to temporary file in the same directory, flushed to storage and
+   /// renamed over the file, so it never holds partially written config.
    /// 
    /// # Example
    /// ```
@@ -203,82 +336,1128 @@ impl Config {
    /// fs::remove_file(Path::new("test.cfg"));
    /// ```
     pub fn save_to_file(&mut self, file_name : &path::Path) -> Result<()> {
-        let result = unsafe { raw::config_write_file(&mut self.config, 
-            CString::new(file_name.as_os_str().to_str().unwrap())
-                .unwrap().as_ptr())
+        let contents = self.save_to_string()?;
+        
+        match vfs::replace_file(&*self.context.fs, file_name, &contents, 
+            self.context.save_backup) {
+            Ok(_) => { Ok(()) },
+            Err(_) => { Err(Errors::SaveError) }
+        }
+    }
+
+    /// Write current config to string.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// cfg.root().write_int32("value", 1);
+    /// assert_eq!(cfg.save_to_string().unwrap(), "value = 1;\n");
+    /// ```
+    pub fn save_to_string(&self) -> Result<String> {
+        let mut contents = Vec::new();
+
+        unsafe {
+            let file = libc::tmpfile();
+            if file.is_null() {
+                return Err(Errors::SaveError)
+            }
+
+            raw::config_write(&*self.config, file);
+            libc::rewind(file);
+
+            let mut buffer = [0u8; 4096];
+            loop {
+                let count = libc::fread(buffer.as_mut_ptr() as *mut c_void, 1,
+                    buffer.len(), file);
+                if count == 0 {
+                    break;
+                }
+                contents.extend_from_slice(&buffer[..count]);
+            }
+
+            let failed = libc::ferror(file) != 0;
+            libc::fclose(file);
+            if failed {
+                return Err(Errors::SaveError)
+            }
+        }
+
+        let contents = String::from_utf8(contents)
+            .map_err(|_| Errors::SaveError)?;
+        self.write_comments(contents)
+    }
+
+    // Insert comments written by writers into config text.
+    fn write_comments(&self, contents : String) -> Result<String> {
+        let root = match self.root_element {
+            Some(root) => { root },
+            None => { return Ok(contents); }
+        };
+
+        let mut settings = Vec::new();
+        merge::collect(root, &mut settings);
+        let comments : Vec<(String, Comments)> = settings.into_iter()
+            .filter_map(|setting| {
+                let comments = setting_state(setting)?.comments.borrow()
+                    .clone()?;
+                Some((merge::setting_path(setting), comments))
+            }).collect();
+        if comments.is_empty() {
+            return Ok(contents);
+        }
+
+        let mut document = Document::parse(&contents)
+            .map_err(|_| Errors::SaveError)?;
+        for (path, comments) in comments {
+            document.set_comments(&path, &comments)
+                .map_err(|_| Errors::SaveError)?;
+        }
+        Ok(document.to_string())
+    }
+
+    /// Set filesystem used to read configuration files and includes and to
+    /// save configuration. Config uses `DiskFs` by default.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::vfs::MemoryFs;
+    /// use std::path::Path;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "@include \"defaults.cfg\"\nport = 8080;");
+    /// fs.insert("defaults.cfg", "host = \"localhost\";");
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_fs(fs.clone());
+    /// if cfg.load_from_file(Path::new("app.cfg")).is_ok() {
+    ///     let host = cfg.value("host").unwrap().as_string();
+    /// }
+    /// ```
+    pub fn set_fs<F>(&mut self, fs : F) where F: ConfigFs + 'static {
+        self.context.fs = Box::new(fs);
+    }
+    
+    /// Set current config include directory.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use std::path::Path;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.include_dir(Path::new("/config"));
+    /// ```
+    pub fn include_dir(&mut self, path : &path::Path) -> () {
+        unsafe {
+            raw::config_set_include_dir(&mut *self.config, 
+                CString::new(path.as_os_str().to_str().unwrap())
+                    .unwrap().as_ptr())
+        }
+    }
+
+    /// Set limit of nested includes. libconfig never nests includes deeper
+    /// than 10 files.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_include_depth(3);
+    /// ```
+    pub fn set_include_depth(&mut self, depth : usize) {
+        self.context.include_depth = depth;
+    }
+
+    /// Keep previous version of file as `<file>.bak` when config is saved.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::vfs::MemoryFs;
+    /// use std::path::Path;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "value = 1;\n");
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_fs(fs.clone());
+    /// cfg.set_save_backup(true);
+    /// cfg.root().write_int32("value", 2);
+    /// cfg.save_to_file(Path::new("app.cfg")).unwrap();
+    /// assert_eq!(fs.get("app.cfg.bak").unwrap(), "value = 1;\n");
+    /// ```
+    pub fn set_save_backup(&mut self, backup : bool) {
+        self.context.save_backup = backup;
+    }
+
+    // Walk includes of configuration contents before libconfig parses them.
+    fn check_includes(&mut self, name : &str, identity : &str, 
+        contents : &str) -> Result<()> {
+        
+        self.context.include_sources.reset();
+        self.context.include_dirs.clear();
+
+        let include_dir = self.include_dir_path();
+        let context = &self.context;
+        let resolve = |path : &str| { 
+            context.resolve_include(&include_dir, path) 
+        };
+
+        let (cache, include_contents) = IncludeChecker::new(&*context.fs, 
+            &resolve, context.include_depth).check(name, identity, contents)?;
+        
+        let mut paths : Vec<&String> = cache.keys().collect();
+        paths.sort();
+        for path in paths {
+            for source in &cache[path] {
+                if let IncludeSource::File(file) = source {
+                    if !self.context.source_files.contains(file) {
+                        self.context.source_files.push(file.clone());
+                    }
+                }
+            }
+            if let Some(dir) = include::include_dir(&*self.context.fs, 
+                &include_dir, path) {
+                self.context.include_dirs.push(dir);
+            }
+        }
+        // Includes are resolved from checked sources, directives missed by 
+        // checker are resolved through config filesystem too.
+        unsafe {
+            raw::config_set_include_func(&mut *self.config, 
+                Some(include_func_callback as raw::config_include_fn_t));
+        }
+        self.context.include_cache = cache;
+        self.context.include_contents = include_contents;
+        Ok(())
+    }
+
+    /// Return main file and files included by it, config loaded from string
+    /// returns included files only. In-memory include sources are not 
+    /// listed.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::vfs::MemoryFs;
+    /// use std::path::{Path, PathBuf};
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("/etc/app/app.cfg", "@include \"local.cfg\"");
+    /// fs.insert("/etc/app/local.cfg", "port = 80;");
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_fs(fs);
+    /// cfg.include_dir(Path::new("/etc/app"));
+    /// cfg.load_from_file(Path::new("/etc/app/app.cfg")).unwrap();
+    /// assert_eq!(cfg.source_files(), vec![PathBuf::from("/etc/app/app.cfg"),
+    ///     PathBuf::from("/etc/app/local.cfg")]);
+    /// ```
+    pub fn source_files(&self) -> Vec<path::PathBuf> {
+        self.context.source_files.clone()
+    }
+
+    // Return contents source file was parsed from.
+    pub(crate) fn source_text(&self, file : &path::Path) -> Option<&str> {
+        if self.context.main_file.as_deref() == file.to_str() {
+            Some(self.context.main_text.as_str())
+        } else {
+            self.context.include_contents.get(file).map(String::as_str)
+        }
+    }
+
+    // Return directories included by wildcard and directory includes.
+    pub(crate) fn include_dirs(&self) -> Vec<path::PathBuf> {
+        self.context.include_dirs.clone()
+    }
+
+    // Return file system config is loaded from.
+    pub(crate) fn fs(&self) -> &dyn ConfigFs {
+        &*self.context.fs
+    }
+
+    // Return file and line of last parse error. Errors of main file and 
+    // in-memory includes are reported by their names.
+    pub(crate) fn error_location(&self) -> (Option<String>, Option<u32>) {
+        let file = raw::config_error_file(&*self.config);
+        let file = {
+            if file.is_null() {
+                self.context.main_file.clone()
+            } else {
+                let file = unsafe { CStr::from_ptr(file) }.to_string_lossy()
+                    .into_owned();
+                Some(self.context.include_sources.name(path::Path::new(&file))
+                    .map(String::from).unwrap_or(file))
+            }
+        };
+        
+        match raw::config_error_line(&*self.config) {
+            0 => { (file, None) },
+            line => { (file, Some(line as u32)) }
+        }
+    }
+
+    // Return current include directory.
+    fn include_dir_path(&self) -> path::PathBuf {
+        let dir = raw::config_get_include_dir(&*self.config);
+        if dir.is_null() {
+            path::PathBuf::new()
+        } else {
+            path::PathBuf::from(unsafe { CStr::from_ptr(dir) }
+                .to_string_lossy().into_owned())
+        }
+    }
+        
+    /// Read value from path.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// match cfg.value("root.value") {
+    ///     Some(val) => { /* ... */ },
+    ///     None => { /* ... */ }
+    /// }
+    /// ```
+    pub fn value<S>(&self, path : S) -> Option<OptionReader>
+        where S: Into<String> {
+        OptionReader::tracked(self.root_element).value(path)
+    }
+    
+    /// Create new group section.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// match cfg.create_section("root") {
+    ///     Some(s) => { /* ... */ },
+    ///     None => { /* ... */ }
+    /// }
+    /// ```
+    pub fn create_section<S>(&self, path : S) -> Option<OptionWriter>
+        where S: Into<String> {
+        OptionWriter::new(self.root_element).create_section(path)
+    }
+
+    /// Get the root section writer
+    ///
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    ///
+    /// let cfg = Config::new();
+    /// let root_writer = cfg.root();
+    /// root_writer.write_string("interface", "eno1");
+    /// ```
+    pub fn root(&self) -> OptionWriter {
+        return OptionWriter::new(self.root_element);
+    }
+
+    /// Get reader of root config group.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("interface = \"eno1\";").unwrap();
+    /// let root = cfg.root_reader().unwrap();
+    /// assert_eq!(root.value("interface").unwrap().as_string(), 
+    ///     Some(String::from("eno1")));
+    /// ```
+    pub fn root_reader(&self) -> Option<OptionReader> {
+        self.root_element.map(|root| { OptionReader::tracked(Some(root)) })
+    }
+
+    /// Return settings present in config which were never read by `value`,
+    /// `root_reader` and readers returned by them. Members of unused groups 
+    /// are not reported separately, collections are checked for unused 
+    /// members of group elements which were read.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { port = 80; }; sever = { port = 1; };")
+    ///     .unwrap();
+    /// let port = cfg.value("server.port").unwrap().as_int32();
+    /// 
+    /// let unused = cfg.unused_settings();
+    /// assert_eq!(unused.len(), 1);
+    /// assert_eq!(unused[0].path(), "sever");
+    /// assert_eq!(unused[0].line(), Some(1));
+    /// ```
+    pub fn unused_settings(&self) -> Vec<UnusedSetting> {
+        match self.root_element {
+            Some(root) => {
+                usage::unused(root, &|setting| {
+                    setting_state(setting).is_some_and(|state| { 
+                        state.accessed.get() 
+                    })
+                })
+            },
+            None => { Vec::new() }
+        }
+    }
+
+    /// Forget settings read so far, `unused_settings` reports settings read 
+    /// after reset only.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 8080;").unwrap();
+    /// cfg.value("port");
+    /// cfg.reset_access_tracking();
+    /// assert_eq!(cfg.unused_settings()[0].path(), "port");
+    /// ```
+    pub fn reset_access_tracking(&mut self) {
+        if let Some(root) = self.root_element {
+            let mut settings = Vec::new();
+            merge::collect(root, &mut settings);
+            for setting in settings {
+                if let Some(state) = setting_state(setting) {
+                    state.accessed.set(false);
+                }
+            }
+        }
+    }
+
+    /// Merge settings of other config into this config. Groups are merged 
+    /// recursively, scalars and collections are merged by policy. Settings of
+    /// different types are reported as conflict with both paths and config is
+    /// left unchanged, unless policy is `ScalarMerge::Fill`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::merge::MergePolicy;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { host = \"localhost\"; };").unwrap();
+    /// let mut other = Config::new();
+    /// other.load_from_string("server = { port = 8080; };").unwrap();
+    /// 
+    /// cfg.merge_from(&other, MergePolicy::default()).unwrap();
+    /// assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    /// ```
+    pub fn merge_from(&mut self, other : &Config, policy : MergePolicy) 
+        -> Result<()> {
+        
+        let (target, source) = {
+            match (self.root_element, other.root_element) {
+                (Some(target), Some(source)) => { (target, source) },
+                _ => { return Err(Errors::ElementNotExists); }
+            }
+        };
+
+        if let Some((existing, conflict)) = merge::find_conflict(target, 
+            source, policy) {
+            return Err(Errors::MergeConflict(merge::setting_location(existing),
+                merge::setting_location(conflict)));
+        }
+
+        Merger::new(policy, &mut |_, _| {}).merge_group(target, source);
+        Ok(())
+    }
+
+    /// Insert settings of defaults template missing in config and return 
+    /// paths of inserted settings. Existing settings are kept even if their
+    /// type differs from template, inserted settings are reported by 
+    /// `OptionReader::is_defaulted`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut defaults = Config::new();
+    /// defaults.load_from_string("port = 8080; host = \"localhost\";")
+    ///     .unwrap();
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 9090;").unwrap();
+    /// assert_eq!(cfg.apply_defaults(&defaults).unwrap(), vec!["host"]);
+    /// assert!(cfg.value("host").unwrap().is_defaulted());
+    /// assert!(!cfg.value("port").unwrap().is_defaulted());
+    /// ```
+    pub fn apply_defaults(&mut self, template : &Config) 
+        -> Result<Vec<String>> {
+        
+        let (target, source) = {
+            match (self.root_element, template.root_element) {
+                (Some(target), Some(source)) => { (target, source) },
+                _ => { return Err(Errors::ElementNotExists); }
+            }
+        };
+
+        let mut existing = Vec::new();
+        merge::collect(target, &mut existing);
+        let existing : HashSet<usize> = existing.into_iter()
+            .map(|setting| { setting as usize }).collect();
+        
+        let mut inserted = Vec::new();
+        Merger::new(MergePolicy::new(ScalarMerge::Fill, CollectionMerge::Keep),
+            &mut |setting, _| {
+                if !existing.contains(&(setting as usize)) {
+                    inserted.push(setting);
+                }
+            }).merge_group(target, source);
+
+        for setting in &inserted {
+            setting_data(*setting).defaulted.set(true);
+        }
+        Ok(inserted.iter().filter(|setting| { 
+            !setting_state(raw::config_setting_parent(**setting))
+                .is_some_and(|state| { state.defaulted.get() })
+        }).map(|setting| { merge::setting_path(*setting) }).collect())
+    }
+
+    /// Expand `${path}` references to other settings and `${env:NAME}` 
+    /// references to environment variables in string values. See 
+    /// `Interpolator` for details.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(r#"
+    ///     base = "/var/app";
+    ///     log_dir = "${base}/log";"#).unwrap();
+    /// cfg.interpolate().unwrap();
+    /// assert_eq!(cfg.value("log_dir").unwrap().as_string().unwrap(), 
+    ///     "/var/app/log");
+    /// ```
+    pub fn interpolate(&mut self) -> Result<()> {
+        Interpolator::new().apply(self)
+    }
+
+    /// Set resolver of `secret:<provider>:<name>` values read by 
+    /// `OptionReader::as_secret`, default resolver supports `file` and `env`
+    /// providers.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// use librustconfig::secret::Secret;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_secret_resolver(|provider : &str, name : &str| {
+    ///     match (provider, name) {
+    ///         ("vault", "db") => { Ok(Secret::new(String::from("password"))) },
+    ///         _ => { Err(Errors::SecretError(String::from("unknown secret"))) }
+    ///     }
+    /// });
+    /// cfg.load_from_string("password = \"secret:vault:db\";").unwrap();
+    /// assert_eq!(cfg.value("password").unwrap().as_secret().unwrap()
+    ///     .expose(), "password");
+    /// ```
+    pub fn set_secret_resolver<R>(&mut self, resolver : R) 
+        where R: SecretResolver + 'static {
+        self.context.secret_resolver = Box::new(resolver);
+    }
+
+    /// Write secret values in clear text when config is converted to JSON, 
+    /// TOML or YAML. Values referring to secrets and values read by 
+    /// `OptionReader::as_secret` are redacted by default.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::json::JsonMode;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("password = \"secret:env:DB_PASS\";").unwrap();
+    /// assert_eq!(cfg.save_to_json(JsonMode::Plain).unwrap(), 
+    ///     "{\n  \"password\": \"[REDACTED]\"\n}");
+    /// 
+    /// cfg.set_export_secrets(true);
+    /// assert_eq!(cfg.save_to_json(JsonMode::Plain).unwrap(), 
+    ///     "{\n  \"password\": \"secret:env:DB_PASS\"\n}");
+    /// ```
+    pub fn set_export_secrets(&mut self, export : bool) {
+        self.context.export_secrets = export;
+    }
+
+    // Return true if secret values are redacted by exporters.
+    pub(crate) fn redacts_exports(&self) -> bool {
+        !self.context.export_secrets
+    }
+
+    /// Write config to string with secret values redacted. Values referring 
+    /// to secrets and values read by `OptionReader::as_secret` are replaced 
+    /// by `[REDACTED]`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("password = \"secret:env:DB_PASS\";").unwrap();
+    /// assert_eq!(cfg.render_redacted().unwrap(), 
+    ///     "password = \"[REDACTED]\";\n");
+    /// ```
+    pub fn render_redacted(&self) -> Result<String> {
+        let root = self.root_element.ok_or(Errors::ElementNotExists)?;
+        let copy = self.empty_copy();
+        let target = copy.root_element.ok_or(Errors::ElementNotExists)?;
+
+        let mut redacted = Vec::new();
+        Merger::new(MergePolicy::default(), &mut |setting, source| {
+            if is_secret(source) {
+                redacted.push(setting);
+            }
+        }).merge_group(target, root);
+
+        let text = CString::new(secret::REDACTED).unwrap_or_default();
+        for setting in redacted {
+            unsafe { raw::config_setting_set_string(setting, text.as_ptr()); }
+        }
+        copy.save_to_string()
+    }
+
+    /// Return changes turning this config into other config. Group members 
+    /// are matched by name, list elements by index, arrays and scalars are 
+    /// compared as whole values.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("server = { port = 80; debug = true; };")
+    ///     .unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("server = { port = 8080; host = \"local\"; };")
+    ///     .unwrap();
+    /// 
+    /// let changes : Vec<String> = old.diff(&new).iter()
+    ///     .map(|change| { change.to_string() }).collect();
+    /// assert_eq!(changes, vec!["~ server.port = 80 -> 8080", 
+    ///     "- server.debug = true", "+ server.host = \"local\""]);
+    /// ```
+    pub fn diff(&self, other : &Config) -> Vec<Change> {
+        diff::changes(self.root_setting(), other.root_setting())
+    }
+
+    // Return description of the last parse error.
+    pub(crate) fn error_text(&self) -> Option<String> {
+        let text = raw::config_error_text(&*self.config);
+        if text.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(text) }.to_string_lossy().into_owned())
+    }
+
+    // Create empty config with the same options and float precision.
+    pub(crate) fn empty_copy(&self) -> Config {
+        let mut copy = Config::new();
+        unsafe {
+            raw::config_set_options(&mut *copy.config, 
+                raw::config_get_options(&*self.config));
+        }
+        copy.set_float_precision(self.float_precision());
+        copy
+    }
+
+    // Return root setting.
+    pub(crate) fn root_setting(&self) -> Option<*mut raw::config_setting_t> {
+        self.root_element
+    }
+
+    /// Enable or disable config option.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, ConfigOption};
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_option(ConfigOption::AllowOverrides, true);
+    /// ```
+    pub fn set_option(&mut self, option : ConfigOption, flag : bool) {
+        let flag = {
+            match flag {
+                true => { raw::CONFIG_TRUE },
+                false => { raw::CONFIG_FALSE }
+            }
+        };
+
+        unsafe {
+            raw::config_set_option(&mut *self.config, option.as_raw(), flag);
+        }
+    }
+
+    /// Return true if config option is enabled.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, ConfigOption};
+    /// 
+    /// let cfg = Config::new();
+    /// if cfg.option(ConfigOption::Fsync) {
+    ///     /* ... */
+    /// }
+    /// ```
+    pub fn option(&self, option : ConfigOption) -> bool {
+        let result = unsafe {
+            raw::config_get_option(&*self.config, option.as_raw())
+        };
+        result == raw::CONFIG_TRUE
+    }
+
+    /// Set number of decimal digits used when float values are written.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_float_precision(3);
+    /// ```
+    pub fn set_float_precision(&mut self, digits : u16) {
+        unsafe {
+            raw::config_set_float_precision(&mut *self.config, digits);
+        }
+    }
+
+    /// Return number of decimal digits used when float values are written.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// let digits = cfg.float_precision();
+    /// ```
+    pub fn float_precision(&self) -> u16 {
+        unsafe { raw::config_get_float_precision(&*self.config) }
+    }
+
+    /// Remove all settings from config.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.root().write_int32("value", 1);
+    /// cfg.clear();
+    /// assert!(cfg.value("value").is_none());
+    /// ```
+    pub fn clear(&mut self) {
+        unsafe {
+            raw::config_clear(&mut *self.config);
+        }
+        self.context.include_sources.reset();
+        self.context.include_contents.clear();
+        self.context.source_files.clear();
+        self.context.include_dirs.clear();
+        self.context.main_text.clear();
+        self.context.documents.borrow_mut().clear();
+
+        let option = raw::config_root_setting(&*self.config);
+        self.root_element = {
+            if option.is_null() {
+                None
+            } else {
+                Some(option)
+            }
+        };
+    }
+
+    /// Set function used to resolve `@include` directives. Function receives
+    /// current include directory and include path and returns list of files 
+    /// to include in that place.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use std::path::Path;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_include_func(|dir : &Path, path : &str| {
+    ///     Ok(vec![dir.join("override").join(path)])
+    /// });
+    /// ```
+    pub fn set_include_func<F>(&mut self, func : F)
+        where F: Fn(&path::Path, &str) -> 
+            std::result::Result<Vec<path::PathBuf>, String> + 'static {
+        
+        self.set_include_resolver(move |dir : &path::Path, path : &str| {
+            match func(dir, path) {
+                Ok(files) => { 
+                    Ok(files.into_iter().map(IncludeSource::File).collect()) 
+                },
+                Err(message) => { Err(Errors::IncludeError(message)) }
+            }
+        });
+    }
+
+    /// Set resolver for `@include` directives. Resolver receives current 
+    /// include directory and include path and returns list of sources to 
+    /// include in that place. Sources can be files on disk or configuration
+    /// contents held in memory.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// use librustconfig::include::IncludeSource;
+    /// use std::path::Path;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.set_include_resolver(|_dir : &Path, path : &str| {
+    ///     match path {
+    ///         "defaults" => {
+    ///             Ok(vec![IncludeSource::Memory {
+    ///                 name : String::from("defaults"),
+    ///                 contents : String::from("port = 8080;")
+    ///             }])
+    ///         },
+    ///         _ => { Err(Errors::IncludeError(format!("unknown {}", path))) }
+    ///     }
+    /// });
+    /// 
+    /// if cfg.load_from_string("@include \"defaults\"\n").is_ok() {
+    ///     let port = cfg.value("port").unwrap().as_int32();
+    /// }
+    /// ```
+    pub fn set_include_resolver<F>(&mut self, resolver : F)
+        where F: Fn(&path::Path, &str) -> Result<Vec<IncludeSource>> 
+            + 'static {
+        
+        self.context.include_resolver = Some(Box::new(resolver));
+    }
+
+    /// Set function called when libconfig meets unrecoverable error. Process
+    /// is aborted after function returns.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// Config::set_fatal_error_func(|message : &str| {
+    ///     eprintln!("libconfig: {}", message);
+    /// });
+    /// ```
+    pub fn set_fatal_error_func(func : fn(&str)) {
+        *FATAL_ERROR_FUNC.lock().unwrap() = Some(func);
+        unsafe {
+            raw::config_set_fatal_error_func(Some(fatal_error_func_callback));
+        }
+    }
+}
+
+impl ConfigContext {
+
+    // Resolve include path with include resolver or, if resolver is not set,
+    // by include::resolve_path.
+    fn resolve_include(&self, include_dir : &path::Path, path : &str) 
+        -> Result<Vec<IncludeSource>> {
+        
+        match &self.include_resolver {
+            Some(resolver) => { resolver(include_dir, path) },
+            None => {
+                include::resolve_path(&*self.fs, include_dir, path)
+                    .map(|files| {
+                    files.into_iter().map(IncludeSource::File).collect()
+                }).map_err(|error| {
+                    Errors::IncludeError(format!("{}: {}", path, error))
+                })
+            }
+        }
+    }
+
+    // Return Rust side state of the config element belongs to.
+    fn of<'a>(element : *const raw::config_setting_t) 
+        -> Option<&'a ConfigContext> {
+        
+        let config = unsafe { (*element).config };
+        if config.is_null() {
+            return None
+        }
+
+        let context = raw::config_get_hook(config) as *const ConfigContext;
+        if context.is_null() {
+            None
+        } else {
+            Some(unsafe { &*context })
+        }
+    }
+}
+
+impl ConfigOption {
+
+    // Return libconfig option flag.
+    fn as_raw(self) -> i32 {
+        match self {
+            ConfigOption::AutoConvert => { 
+                raw::CONFIG_OPTION_AUTOCONVERT 
+            },
+            ConfigOption::SemicolonSeparators => {
+                raw::CONFIG_OPTION_SEMICOLON_SEPARATORS
+            },
+            ConfigOption::ColonAssignmentForGroups => {
+                raw::CONFIG_OPTION_COLON_ASSIGNMENT_FOR_GROUPS
+            },
+            ConfigOption::ColonAssignmentForNonGroups => {
+                raw::CONFIG_OPTION_COLON_ASSIGNMENT_FOR_NON_GROUPS
+            },
+            ConfigOption::OpenBraceOnSeparateLine => {
+                raw::CONFIG_OPTION_OPEN_BRACE_ON_SEPARATE_LINE
+            },
+            ConfigOption::AllowScientificNotation => {
+                raw::CONFIG_OPTION_ALLOW_SCIENTIFIC_NOTATION
+            },
+            ConfigOption::Fsync => { 
+                raw::CONFIG_OPTION_FSYNC 
+            },
+            ConfigOption::AllowOverrides => { 
+                raw::CONFIG_OPTION_ALLOW_OVERRIDES 
+            }
+        }
+    }
+}
+
+/// Function registered by Config::set_fatal_error_func.
+static FATAL_ERROR_FUNC : Mutex<Option<fn(&str)>> = Mutex::new(None);
+
+// Copy string to memory allocated by malloc, libconfig releases it by free.
+unsafe fn malloc_string(value : &CStr) -> *const c_char {
+    let bytes = value.to_bytes_with_nul();
+    let result = libc::malloc(bytes.len()) as *mut c_char;
+
+    if !result.is_null() {
+        ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, result,
+            bytes.len());
+    }
+    result
+}
+
+// libconfig include function, dispatches call to the resolver stored in config
+// context.
+extern "C" fn include_func_callback(config : *mut raw::config_t, 
+    include_dir : *const c_char, path : *const c_char, 
+    error : *mut *const c_char) -> *mut *const c_char {
+    
+    let context = raw::config_get_hook(config) as *mut ConfigContext;
+    if context.is_null() {
+        return unsafe { 
+            raw::config_default_include_func(config, include_dir, path, error)
         };
-        
-        if result == raw::CONFIG_TRUE {
-            Ok(())
+    }
+    let context = unsafe { &mut *context };
+
+    let dir = {
+        if include_dir.is_null() {
+            path::PathBuf::new()
         } else {
-            Err(Errors::SaveError)
+            path::PathBuf::from(unsafe { CStr::from_ptr(include_dir) }
+                .to_string_lossy().into_owned())
+        }
+    };
+    let include_path = unsafe { CStr::from_ptr(path) }.to_string_lossy()
+        .into_owned();
+
+    let result = {
+        match context.include_cache.get(&include_path) {
+            Some(sources) => { Ok(sources.clone()) },
+            None => {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    context.resolve_include(&dir, &include_path)
+                })).unwrap_or_else(|_| {
+                    Err(Errors::IncludeError(
+                        String::from("include resolver panicked")))
+                })
+            }
+        }
+    };
+
+    let result = result.and_then(|sources| {
+        sources.into_iter().map(|source| {
+            context.include_sources.materialize(&*context.fs, source, 
+                &context.include_contents)
+                .map_err(|error| { Errors::IncludeError(error.to_string()) })
+        }).collect::<Result<Vec<path::PathBuf>>>()
+    });
+
+    match result {
+        Ok(files) => {
+            unsafe {
+                let list = libc::calloc(files.len() + 1, 
+                    std::mem::size_of::<*const c_char>()) as *mut *const c_char;
+                if list.is_null() {
+                    return ptr::null_mut();
+                }
+
+                for (index, file) in files.iter().enumerate() {
+                    let file = CString::new(file.to_string_lossy().as_bytes())
+                        .unwrap_or_default();
+                    *list.add(index) = malloc_string(&file);
+                }
+                list
+            }
+        },
+        Err(include_error) => {
+            let message = {
+                match include_error {
+                    Errors::IncludeError(message) => { message },
+                    include_error => { format!("{:?}", include_error) }
+                }
+            };
+            context.include_error = Some(CString::new(message)
+                .unwrap_or_default());
+            unsafe { 
+                *error = context.include_error.as_ref().unwrap().as_ptr(); 
+            }
+            ptr::null_mut()
         }
     }
+}
+
+// Return Rust side state of the setting, state is created on first use. 
+// Setting must belong to Config, whose destructor releases the state.
+pub(crate) fn setting_data<'a>(element : *mut raw::config_setting_t) 
+    -> &'a SettingData {
     
-    /// Set current config include directory.
-    /// 
-    /// # Example
-    /// ```
-    /// use librustconfig::config::Config;
-    /// use std::path::Path;
-    /// 
-    /// let mut cfg = Config::new();
-    /// cfg.include_dir(Path::new("/config"));
-    /// ```
-    pub fn include_dir(&mut self, path : &path::Path) -> () {
+    let mut hook = raw::config_setting_get_hook(element);
+    if hook.is_null() {
+        hook = Box::into_raw(Box::<SettingData>::default()) as *mut c_void;
+        unsafe { raw::config_setting_set_hook(element, hook); }
+    }
+    unsafe { &*(hook as *const SettingData) }
+}
+
+// Return Rust side state of the setting, None if state was never created.
+pub(crate) fn setting_state<'a>(element : *mut raw::config_setting_t) 
+    -> Option<&'a SettingData> {
+    
+    let hook = raw::config_setting_get_hook(element);
+    if hook.is_null() {
+        None
+    } else {
+        Some(unsafe { &*(hook as *const SettingData) })
+    }
+}
+
+// Return true if setting was read by `OptionReader::as_secret` or its value
+// refers to secret.
+pub(crate) fn is_secret(element : *mut raw::config_setting_t) -> bool {
+    setting_state(element).is_some_and(|state| { state.secret.get() }) || 
+        OptionReader::new(Some(element)).as_string().is_some_and(|value| {
+            value.starts_with(secret::SECRET_PREFIX)
+        })
+}
+
+// Mark setting as secret.
+pub(crate) fn mark_secret(element : *mut raw::config_setting_t) {
+    setting_data(element).secret.set(true);
+}
+
+// Move Rust side state and source location of setting and its members to 
+// target setting of the same shape, state of target is released. Used when
+// setting is rebuilt by copying it, null target is ignored. Source file name
+// is owned by config of source setting, so rebuilt setting has to end up in
+// that config.
+pub(crate) fn move_setting_state(source : *mut raw::config_setting_t, 
+    target : *mut raw::config_setting_t) {
+    
+    if target.is_null() {
+        return;
+    }
+
+    unsafe {
+        if (*source).line != 0 {
+            (*target).line = (*source).line;
+            (*target).file = (*source).file;
+        }
+    }
+
+    let hook = raw::config_setting_get_hook(source);
+    if !hook.is_null() {
+        setting_destructor(raw::config_setting_get_hook(target));
         unsafe {
-            raw::config_set_include_dir(&mut self.config, 
-                CString::new(path.as_os_str().to_str().unwrap())
-                    .unwrap().as_ptr())
+            raw::config_setting_set_hook(source, ptr::null_mut());
+            raw::config_setting_set_hook(target, hook);
         }
     }
-        
-    /// Read value from path.
-    /// 
-    /// # Example
-    /// ```
-    /// use librustconfig::config::Config;
-    /// 
-    /// let cfg = Config::new();
-    /// match cfg.value("root.value") {
-    ///     Some(val) => { /* ... */ },
-    ///     None => { /* ... */ }
-    /// }
-    /// ```
-    pub fn value<S>(&self, path : S) -> Option<OptionReader>
-        where S: Into<String> {
-        OptionReader::new(self.root_element).value(path)
+
+    for index in 0..merge::length(source).min(merge::length(target)) {
+        move_setting_state(merge::element(source, index), 
+            merge::element(target, index));
     }
+}
+
+// Merge Rust side state of source setting into target setting: flags are 
+// combined, comments, attached value and origin set on source replace those
+// of target.
+pub(crate) fn merge_setting_state(source : *mut raw::config_setting_t, 
+    target : *mut raw::config_setting_t) {
     
-    /// Create new group section.
-    /// 
-    /// # Example
-    /// ```
-    /// use librustconfig::config::Config;
-    /// 
-    /// let cfg = Config::new();
-    /// match cfg.create_section("root") {
-    ///     Some(s) => { /* ... */ },
-    ///     None => { /* ... */ }
-    /// }
-    /// ```
-    pub fn create_section<S>(&self, path : S) -> Option<OptionWriter>
-        where S: Into<String> {
-        OptionWriter::new(self.root_element).create_section(path)
+    let state = match setting_state(source) {
+        Some(state) => { state },
+        None => { return; }
+    };
+    let data = setting_data(target);
+    data.secret.set(data.secret.get() || state.secret.get());
+    data.defaulted.set(data.defaulted.get() || state.defaulted.get());
+    data.accessed.set(data.accessed.get() || state.accessed.get());
+    if let Some(comments) = state.comments.take() {
+        data.comments.replace(Some(comments));
+    }
+    if let Some(user) = state.user.take() {
+        data.user.replace(Some(user));
     }
+    if let Some(origin) = state.origin.take() {
+        data.origin.replace(Some(origin));
+    }
+}
 
-    /// Get the root section writer
-    ///
-    /// # Example
-    /// ```
-    /// use librustconfig::config::Config;
-    ///
-    /// let cfg = Config::new();
-    /// let root_writer = cfg.root();
-    /// root_writer.write_string("interface", "eno1");
-    /// ```
-    pub fn root(&self) -> OptionWriter {
-        return OptionWriter::new(self.root_element);
+// libconfig destructor of setting hooks, releases state of destroyed setting.
+extern "C" fn setting_destructor(hook : *mut c_void) {
+    if !hook.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            drop(unsafe { Box::from_raw(hook as *mut SettingData) });
+        }));
+    }
+}
+
+// libconfig fatal error function, dispatches call to the function registered
+// by Config::set_fatal_error_func.
+pub(crate) extern "C" fn fatal_error_func_callback(message : *const c_char) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    
+    if let Ok(func) = FATAL_ERROR_FUNC.lock() {
+        if let Some(func) = *func {
+            let _ = panic::catch_unwind(|| { func(&message) });
+        }
+    }
+}
+
+/// Config is shown with secret values redacted.
+impl fmt::Debug for Config {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Config is shown in libconfig syntax with secret values redacted, see 
+/// `Config::render_redacted`.
+impl fmt::Display for Config {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.render_redacted() {
+            Ok(text) => { f.write_str(&text) },
+            Err(_) => { f.write_str("Config") }
+        }
     }
 }
 
@@ -287,7 +1466,7 @@ impl Config {
 impl Drop for Config {
     fn drop (&mut self) {
         unsafe { 
-            raw::config_destroy(&mut self.config); 
+            raw::config_destroy(&mut *self.config); 
         }
     }
 }
@@ -295,7 +1474,8 @@ impl Drop for Config {
 impl OptionWriter {
     
     // Constructor.
-    fn new(elem : Option<*mut raw::config_setting_t>) -> OptionWriter {
+    pub(crate) fn new(elem : Option<*mut raw::config_setting_t>) 
+        -> OptionWriter {
         OptionWriter {
             element : elem
         }
@@ -679,6 +1859,98 @@ impl OptionWriter {
             }
         }
     }
+
+    /// Add comment line above group member, comment is written when config 
+    /// is saved. Return None if comment contains `*/`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// cfg.root().write_int32("port", 8080).unwrap()
+    ///     .write_comment("port", "Listening port").unwrap()
+    ///     .write_trailing_comment("port", "default");
+    /// assert_eq!(cfg.save_to_string().unwrap(), 
+    ///     "# Listening port\nport = 8080; # default\n");
+    /// ```
+    pub fn write_comment<S>(&self, name : S, comment : S) 
+        -> Option<OptionWriter> where S: Into<String> {
+        
+        let comment = comment.into();
+        if !document::valid_comment(&comment, false) {
+            return None;
+        }
+
+        let (element, _) = self.member_context(name.into())?;
+        setting_data(element).comments.borrow_mut().get_or_insert_with(
+            Comments::default).leading.extend(comment.lines()
+            .map(String::from));
+        Some(*self)
+    }
+
+    /// Set comment following group member on the same line, comment is 
+    /// written when config is saved. Return None if comment contains line 
+    /// breaks or `*/`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// cfg.root().write_bool("debug", false).unwrap()
+    ///     .write_trailing_comment("debug", "verbose logging");
+    /// assert_eq!(cfg.value("debug").unwrap().comments().trailing(), 
+    ///     Some("verbose logging"));
+    /// ```
+    pub fn write_trailing_comment<S>(&self, name : S, comment : S) 
+        -> Option<OptionWriter> where S: Into<String> {
+        
+        let comment = comment.into();
+        if !document::valid_comment(&comment, true) {
+            return None;
+        }
+
+        let (element, _) = self.member_context(name.into())?;
+        setting_data(element).comments.borrow_mut().get_or_insert_with(
+            Comments::default).trailing = Some(comment);
+        Some(*self)
+    }
+
+    /// Attach value to group member. Value replaces value attached before and
+    /// is dropped when member is removed.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// cfg.root().write_int32("port", 8080).unwrap()
+    ///     .write_hook("port", String::from("listener"));
+    /// assert_eq!(cfg.value("port").unwrap().hook::<String>(), 
+    ///     Some(String::from("listener")));
+    /// ```
+    pub fn write_hook<S, T>(&self, name : S, value : T) 
+        -> Option<OptionWriter> where S: Into<String>, T: Any {
+        
+        let (element, _) = self.member_context(name.into())?;
+        *setting_data(element).user.borrow_mut() = Some(Box::new(value));
+        Some(*self)
+    }
+
+    // Return group member and context of its config.
+    fn member_context<'a>(&self, name : String) 
+        -> Option<(*mut raw::config_setting_t, &'a ConfigContext)> {
+        
+        let name = CString::new(name).ok()?;
+        let element = unsafe { 
+            raw::config_setting_get_member(self.element?, name.as_ptr()) 
+        };
+        if element.is_null() {
+            return None;
+        }
+        ConfigContext::of(element).map(|context| { (element, context) })
+    }
 }
 
 impl CollectionWriter {
@@ -822,9 +2094,38 @@ impl CollectionWriter {
 impl OptionReader {
     
     // Constructor
-    fn new(elem : Option<*mut raw::config_setting_t>) -> OptionReader {
+    pub(crate) fn new(elem : Option<*mut raw::config_setting_t>) 
+        -> OptionReader {
         OptionReader {
-            element : elem
+            element : elem,
+            tracked : false
+        }
+    }
+
+    // Constructor of reader recording settings it reads.
+    fn tracked(elem : Option<*mut raw::config_setting_t>) -> OptionReader {
+        OptionReader {
+            element : elem,
+            tracked : true
+        }
+    }
+
+    // Create reader of related setting, record access to it and its parents
+    // if this reader is tracked.
+    pub(crate) fn child(&self, elem : *mut raw::config_setting_t) 
+        -> OptionReader {
+        
+        if self.tracked {
+            let mut setting = elem;
+            while !setting.is_null() && !setting_data(setting).accessed
+                .replace(true) {
+                setting = raw::config_setting_parent(setting);
+            }
+        }
+
+        OptionReader {
+            element : Some(elem),
+            tracked : self.tracked
         }
     }
 
@@ -962,10 +2263,143 @@ impl OptionReader {
         if result.is_null() {
             None
         } else {
-            Some(OptionReader::new(Some(result)))
+            Some(self.child(result))
         }
     }
     
+    /// Return name of file option was read from. Options created by writers
+    /// and options parsed from string have no source file.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use std::path::Path;
+    /// 
+    /// let mut cfg = Config::new();
+    /// if cfg.load_from_file(Path::new("test.cfg")).is_ok() {
+    ///     match cfg.value("group.value").unwrap().source_file() {
+    ///         Some(file) => { /* ... */ },
+    ///         None => { /* ... */ }
+    ///     }
+    /// }
+    /// ```
+    pub fn source_file(&self) -> Option<String> {
+        let element = self.element?;
+        
+        let file = raw::config_setting_source_file(element);
+        if file.is_null() {
+            return ConfigContext::of(element).and_then(|context| {
+                context.main_file.clone()
+            });
+        }
+
+        let file = unsafe { CStr::from_ptr(file) }.to_string_lossy()
+            .into_owned();
+        let name = ConfigContext::of(element).and_then(|context| {
+            context.include_sources.name(path::Path::new(&file))
+        });
+
+        match name {
+            Some(name) => { Some(name.to_string()) },
+            None => { Some(file) }
+        }
+    }
+
+    /// Return line number option was read from. Options created by writers 
+    /// have no source line.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// if cfg.load_from_string("value = 1;").is_ok() {
+    ///     assert_eq!(cfg.value("value").unwrap().source_line(), Some(1));
+    /// }
+    /// ```
+    pub fn source_line(&self) -> Option<u32> {
+        let element = self.element?;
+
+        match raw::config_setting_source_line(element) {
+            0 => { None },
+            line => { Some(line) }
+        }
+    }
+
+    /// Return comments of group member: comment lines directly above it and
+    /// comment following it on the same line. Comments are read from the text
+    /// the setting was parsed from, comments written by writers are returned
+    /// for created settings.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "# Maximal number of workers\nworkers = 4; // per core").unwrap();
+    /// let comments = cfg.value("workers").unwrap().comments();
+    /// assert_eq!(comments.leading(), &["Maximal number of workers"]);
+    /// assert_eq!(comments.trailing(), Some("per core"));
+    /// ```
+    pub fn comments(&self) -> Comments {
+        let element = match self.element {
+            Some(element) => { element },
+            None => { return Comments::default(); }
+        };
+        let context = match ConfigContext::of(element) {
+            Some(context) => { context },
+            None => { return Comments::default(); }
+        };
+        if let Some(comments) = setting_state(element).and_then(|state| {
+            state.comments.borrow().clone()
+        }) {
+            return comments;
+        }
+
+        // Path of setting in its source, included settings are addressed 
+        // from the group include directive is in.
+        let file = self.source_file();
+        let mut keys = Vec::new();
+        let mut current = element;
+        while !raw::config_setting_parent(current).is_null() {
+            let name = merge::setting_name(current);
+            if name.is_empty() {
+                keys.push(format!("[{}]", unsafe { 
+                    raw::config_setting_index(current) 
+                }));
+            } else {
+                keys.push(name);
+            }
+
+            current = raw::config_setting_parent(current);
+            if OptionReader::new(Some(current)).source_file() != file {
+                break;
+            }
+        }
+        keys.reverse();
+
+        // Comments are read from text libconfig parsed, included sources are
+        // addressed by file libconfig read them from.
+        let source = raw::config_setting_source_file(element);
+        let source = (!source.is_null()).then(|| {
+            unsafe { CStr::from_ptr(source) }.to_string_lossy().into_owned()
+        });
+        let mut documents = context.documents.borrow_mut();
+        let document = documents.entry(source.clone()).or_insert_with(|| {
+            let text = match &source {
+                Some(source) => {
+                    context.include_sources.text(path::Path::new(source))
+                },
+                None => { Some(context.main_text.as_str()) }
+            };
+            text.and_then(|text| { Document::parse(text).ok() })
+        });
+        document.as_ref()
+            .and_then(|document| { document.comments(&keys.join(".")) })
+            .unwrap_or_default()
+    }
+
     /// Return option value type.
     /// 
     /// # Example
@@ -1042,16 +2476,16 @@ impl OptionReader {
         if option.is_null() {
             None          
         } else {
-            Some(OptionReader::new(Some(option)))
+            Some(self.child(option))
         }  
     }
     
     pub fn as_array(&self) -> CollectionReaderIterator {
-        CollectionReaderIterator::new(self.element)
+        CollectionReaderIterator::new(self)
     }
 
     pub fn as_list(&self) -> CollectionReaderIterator {
-        CollectionReaderIterator::new(self.element)
+        CollectionReaderIterator::new(self)
     }
 
     /// Present option value as i32.
@@ -1328,6 +2762,87 @@ impl OptionReader {
         }
     }
     
+    /// Return true if option was inserted by `Config::apply_defaults`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut defaults = Config::new();
+    /// defaults.load_from_string("port = 8080;").unwrap();
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.apply_defaults(&defaults).unwrap();
+    /// assert!(cfg.value("port").unwrap().is_defaulted());
+    /// ```
+    pub fn is_defaulted(&self) -> bool {
+        match self.element {
+            Some(element) => {
+                setting_state(element).is_some_and(|state| { 
+                    state.defaulted.get() 
+                })
+            },
+            None => { false }
+        }
+    }
+
+    /// Return value attached to option by `OptionWriter::write_hook`, None if
+    /// there is no value of the type.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let cfg = Config::new();
+    /// cfg.root().write_bool("debug", true).unwrap().write_hook("debug", 42);
+    /// assert_eq!(cfg.value("debug").unwrap().hook::<i32>(), Some(42));
+    /// assert_eq!(cfg.value("debug").unwrap().hook::<u8>(), None);
+    /// ```
+    pub fn hook<T>(&self) -> Option<T> where T: Any + Clone {
+        let state = setting_state(self.element?)?;
+        let user = state.user.borrow();
+        user.as_ref()?.downcast_ref::<T>().cloned()
+    }
+
+    /// Present option value as secret. Values `secret:<provider>:<name>` are
+    /// resolved by resolver set by `Config::set_secret_resolver`, other 
+    /// values are secrets themselves. Value is redacted when config is 
+    /// rendered by `Config::render_redacted`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("password = \"secret:env:PATH\";").unwrap();
+    /// match cfg.value("password").unwrap().as_secret() {
+    ///     Ok(password) => { assert_eq!(password.to_string(), "[REDACTED]"); },
+    ///     Err(error) => { /* ... */ }
+    /// }
+    /// ```
+    pub fn as_secret(&self) -> Result<Secret> {
+        let element = self.element.ok_or(Errors::ElementNotExists)?;
+        let value = Secret::new(self.as_string().ok_or_else(|| {
+            Errors::SecretError(String::from("secret value is not a string"))
+        })?);
+
+        mark_secret(element);
+        let context = ConfigContext::of(element);
+
+        match secret::parse_reference(value.expose()) {
+            Some(reference) => {
+                let (provider, name) = reference?;
+                match context {
+                    Some(context) => { 
+                        context.secret_resolver.resolve(provider, name) 
+                    },
+                    None => { DefaultSecretResolver.resolve(provider, name) }
+                }
+            },
+            None => { Ok(value) }
+        }
+    }
+
     /// Present option value as string, return def if value not exists.
     /// 
     /// # Example
@@ -1360,9 +2875,8 @@ impl OptionReader {
 impl CollectionReaderIterator {
 
     // Constructor.
-    fn new(elem : Option<*mut raw::config_setting_t>) 
-        -> CollectionReaderIterator {
-        
+    fn new(reader : &OptionReader) -> CollectionReaderIterator {
+        let elem = reader.element;
         let collection_size = {
             match elem {
                 Some(val) => { 
@@ -1375,7 +2889,8 @@ impl CollectionReaderIterator {
         CollectionReaderIterator {
             element : elem,
             pos : 0,
-            size : collection_size
+            size : collection_size,
+            tracked : reader.tracked
         }
     }
 
@@ -1398,7 +2913,8 @@ impl Iterator for CollectionReaderIterator {
         }
 
         self.pos += 1;
-        Some(OptionReader::new(Some(result)))
+        Some(OptionReader { element : self.element, tracked : self.tracked }
+            .child(result))
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,474 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config;
+use crate::merge::{self, Setting};
+use crate::schema;
+use crate::secret;
+
+use std::ffi::CStr;
+use std::fmt;
+
+/// Kind of setting change.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified
+}
+
+/// Change of setting between two configs. Values are shown in libconfig 
+/// syntax, secret values are redacted when change is displayed.
+#[derive(PartialEq, Clone)]
+pub struct Change {
+    kind : ChangeKind,
+    path : String,
+    old : Option<Value>,
+    new : Option<Value>
+}
+
+/// Value of changed setting.
+#[derive(PartialEq, Clone)]
+struct Value {
+    text : String,
+    shown : String,
+    kind : &'static str
+}
+
+impl Change {
+
+    // Constructor.
+    fn new(kind : ChangeKind, path : String, old : Option<Setting>, 
+        new : Option<Setting>) -> Change {
+        
+        // Both values are redacted if either of them is secret.
+        let secret = old.is_some_and(config::is_secret) || 
+            new.is_some_and(config::is_secret);
+        let value = |setting : Setting| -> Value {
+            Value {
+                text : render_value(setting),
+                shown : if secret { 
+                    quote(secret::REDACTED) 
+                } else { 
+                    render_redacted_value(setting) 
+                },
+                kind : schema::type_name(merge::setting_type(setting))
+            }
+        };
+        Change { kind, path, old : old.map(value), new : new.map(value) }
+    }
+
+    /// Return kind of change.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::diff::ChangeKind;
+    /// 
+    /// let old = Config::new();
+    /// let mut new = Config::new();
+    /// new.load_from_string("port = 80;").unwrap();
+    /// assert_eq!(old.diff(&new)[0].kind(), ChangeKind::Added);
+    /// ```
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    /// Return path of changed setting, collection elements are addressed by
+    /// index.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("servers = ( { port = 80; } );").unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("servers = ( { port = 81; } );").unwrap();
+    /// assert_eq!(old.diff(&new)[0].path(), "servers.[0].port");
+    /// ```
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return value before change, None for added settings. Secret values 
+    /// are returned as is.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("host = \"localhost\";").unwrap();
+    /// let new = Config::new();
+    /// assert_eq!(old.diff(&new)[0].old_value(), Some("\"localhost\""));
+    /// ```
+    pub fn old_value(&self) -> Option<&str> {
+        self.old.as_ref().map(|value| { value.text.as_str() })
+    }
+
+    /// Return value after change, None for removed settings. Secret values 
+    /// are returned as is.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let old = Config::new();
+    /// let mut new = Config::new();
+    /// new.load_from_string("hosts = [ \"a\", \"b\" ];").unwrap();
+    /// assert_eq!(old.diff(&new)[0].new_value(), Some("[ \"a\", \"b\" ]"));
+    /// ```
+    pub fn new_value(&self) -> Option<&str> {
+        self.new.as_ref().map(|value| { value.text.as_str() })
+    }
+
+    /// Return type of setting before change.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("port = 80;").unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("port = 80L;").unwrap();
+    /// assert_eq!(old.diff(&new)[0].old_type(), Some("integer"));
+    /// ```
+    pub fn old_type(&self) -> Option<&str> {
+        self.old.as_ref().map(|value| { value.kind })
+    }
+
+    /// Return type of setting after change.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("port = 80;").unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("port = 80L;").unwrap();
+    /// assert_eq!(old.diff(&new)[0].new_type(), Some("64bit integer"));
+    /// ```
+    pub fn new_type(&self) -> Option<&str> {
+        self.new.as_ref().map(|value| { value.kind })
+    }
+
+    /// Return true if modified setting changed its type.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("port = 80;").unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("port = \"80\";").unwrap();
+    /// assert!(old.diff(&new)[0].is_type_change());
+    /// ```
+    pub fn is_type_change(&self) -> bool {
+        match (self.old_type(), self.new_type()) {
+            (Some(old), Some(new)) => { old != new },
+            _ => { false }
+        }
+    }
+}
+
+/// Change is shown as `+ path = value`, `- path = value` or 
+/// `~ path = old -> new`, type changes are shown after values. Secret values
+/// are redacted.
+impl fmt::Display for Change {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => {
+                write!(f, "~ {} = {} -> {}", self.path, old.shown, new.shown)?;
+                if old.kind != new.kind {
+                    write!(f, " ({} -> {})", old.kind, new.kind)?;
+                }
+                Ok(())
+            },
+            (Some(old), None) => { 
+                write!(f, "- {} = {}", self.path, old.shown) 
+            },
+            (None, Some(new)) => { 
+                write!(f, "+ {} = {}", self.path, new.shown) 
+            },
+            (None, None) => { write!(f, "  {}", self.path) }
+        }
+    }
+}
+
+/// Change is shown with secret values redacted.
+impl fmt::Debug for Change {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = |value : &Option<Value>| {
+            value.as_ref().map(|value| { (value.shown.clone(), value.kind) })
+        };
+        f.debug_struct("Change")
+            .field("kind", &self.kind)
+            .field("path", &self.path)
+            .field("old", &shown(&self.old))
+            .field("new", &shown(&self.new))
+            .finish()
+    }
+}
+
+/// Render changes in unified diff format. Each change is a hunk headed by 
+/// changed path, type changes are noted in hunk header. Secret values are 
+/// redacted.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::diff;
+/// 
+/// let mut old = Config::new();
+/// old.load_from_string("port = 80; debug = true;").unwrap();
+/// let mut new = Config::new();
+/// new.load_from_string("port = \"http\"; host = \"localhost\";").unwrap();
+/// 
+/// assert_eq!(diff::unified(&old.diff(&new), "old.cfg", "new.cfg"),
+///     "--- old.cfg\n+++ new.cfg\n\
+///     @@ port: integer -> string @@\n-port = 80;\n+port = \"http\";\n\
+///     @@ debug @@\n-debug = true;\n\
+///     @@ host @@\n+host = \"localhost\";\n");
+/// ```
+pub fn unified(changes : &[Change], old_name : &str, new_name : &str) 
+    -> String {
+    
+    let mut result = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for change in changes {
+        if change.is_type_change() {
+            result.push_str(&format!("@@ {}: {} -> {} @@\n", change.path, 
+                change.old_type().unwrap(), change.new_type().unwrap()));
+        } else {
+            result.push_str(&format!("@@ {} @@\n", change.path));
+        }
+        
+        if let Some(old) = &change.old {
+            result.push_str(&format!("-{} = {};\n", change.path, old.shown));
+        }
+        if let Some(new) = &change.new {
+            result.push_str(&format!("+{} = {};\n", change.path, new.shown));
+        }
+    }
+    result
+}
+
+// Collect changes between settings.
+pub(crate) fn diff(old : Setting, new : Setting, changes : &mut Vec<Change>) {
+    let kind = merge::setting_type(old);
+    if kind != merge::setting_type(new) {
+        changes.push(Change::new(ChangeKind::Modified, merge::setting_path(new),
+            Some(old), Some(new)));
+        return;
+    }
+
+    match kind {
+        raw::CONFIG_TYPE_GROUP => {
+            for index in 0..merge::length(old) {
+                let member = merge::element(old, index);
+                let other = unsafe { 
+                    raw::config_setting_get_member(new, 
+                        raw::config_setting_name(member))
+                };
+                if other.is_null() {
+                    changes.push(Change::new(ChangeKind::Removed, 
+                        merge::setting_path(member), Some(member), None));
+                } else {
+                    diff(member, other, changes);
+                }
+            }
+            for index in 0..merge::length(new) {
+                let member = merge::element(new, index);
+                let other = unsafe { 
+                    raw::config_setting_get_member(old, 
+                        raw::config_setting_name(member))
+                };
+                if other.is_null() {
+                    changes.push(Change::new(ChangeKind::Added, 
+                        merge::setting_path(member), None, Some(member)));
+                }
+            }
+        },
+        raw::CONFIG_TYPE_LIST => {
+            let (old_length, new_length) = (merge::length(old), 
+                merge::length(new));
+            for index in 0..old_length.min(new_length) {
+                diff(merge::element(old, index), merge::element(new, index), 
+                    changes);
+            }
+            for index in new_length..old_length {
+                let element = merge::element(old, index);
+                changes.push(Change::new(ChangeKind::Removed, 
+                    merge::setting_path(element), Some(element), None));
+            }
+            for index in old_length..new_length {
+                let element = merge::element(new, index);
+                changes.push(Change::new(ChangeKind::Added, 
+                    merge::setting_path(element), None, Some(element)));
+            }
+        },
+        _ => {
+            if !merge::equal(old, new) {
+                changes.push(Change::new(ChangeKind::Modified, 
+                    merge::setting_path(new), Some(old), Some(new)));
+            }
+        }
+    }
+}
+
+// Render setting value in libconfig syntax.
+pub(crate) fn render_value(setting : Setting) -> String {
+    render(setting, false)
+}
+
+// Render setting value in libconfig syntax with secret values redacted.
+pub(crate) fn render_redacted_value(setting : Setting) -> String {
+    render(setting, true)
+}
+
+// Render setting value in libconfig syntax, secret values are replaced if 
+// redact is set.
+fn render(setting : Setting, redact : bool) -> String {
+    if redact && config::is_secret(setting) {
+        return quote(secret::REDACTED);
+    }
+
+    unsafe {
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_INT => {
+                let value = raw::config_setting_get_int(setting);
+                if raw::config_setting_get_format(setting) == 
+                    raw::CONFIG_FORMAT_HEX as i16 {
+                    format!("0x{:X}", value)
+                } else {
+                    value.to_string()
+                }
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                let value = raw::config_setting_get_int64(setting);
+                if raw::config_setting_get_format(setting) == 
+                    raw::CONFIG_FORMAT_HEX as i16 {
+                    format!("0x{:X}L", value)
+                } else {
+                    format!("{}L", value)
+                }
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                format!("{:?}", raw::config_setting_get_float(setting))
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                (raw::config_setting_get_bool(setting) != 0).to_string()
+            },
+            raw::CONFIG_TYPE_STRING => {
+                let value = raw::config_setting_get_string(setting);
+                if value.is_null() {
+                    String::from("\"\"")
+                } else {
+                    quote(&CStr::from_ptr(value).to_string_lossy())
+                }
+            },
+            raw::CONFIG_TYPE_GROUP => {
+                let members : Vec<String> = (0..merge::length(setting))
+                    .map(|index| {
+                        let member = merge::element(setting, index);
+                        format!("{} = {}; ", merge::setting_name(member), 
+                            render(member, redact))
+                    }).collect();
+                format!("{{ {}}}", members.concat())
+            },
+            kind => {
+                let elements : Vec<String> = (0..merge::length(setting))
+                    .map(|index| { 
+                        render(merge::element(setting, index), redact) 
+                    }).collect();
+                let (open, close) = {
+                    if kind == raw::CONFIG_TYPE_ARRAY { ("[", "]") } 
+                    else { ("(", ")") }
+                };
+                if elements.is_empty() {
+                    format!("{}{}", open, close)
+                } else {
+                    format!("{} {} {}", open, elements.join(", "), close)
+                }
+            }
+        }
+    }
+}
+
+// Quote string in libconfig syntax.
+pub(crate) fn quote(value : &str) -> String {
+    let mut result = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => { result.push_str("\\\""); },
+            '\\' => { result.push_str("\\\\"); },
+            '\n' => { result.push_str("\\n"); },
+            '\r' => { result.push_str("\\r"); },
+            '\t' => { result.push_str("\\t"); },
+            '\x0c' => { result.push_str("\\f"); },
+            character if character.is_control() => {
+                result.push_str(&format!("\\x{:02X}", character as u32));
+            },
+            character => { result.push(character); }
+        }
+    }
+    result.push('"');
+    result
+}
+
+// Return changes turning old root group into new root group.
+pub(crate) fn changes(old : Option<Setting>, new : Option<Setting>) 
+    -> Vec<Change> {
+    
+    let mut changes = Vec::new();
+    match (old, new) {
+        (Some(old), Some(new)) => { diff(old, new, &mut changes); },
+        (Some(old), None) => {
+            for index in 0..merge::length(old) {
+                let member = merge::element(old, index);
+                changes.push(Change::new(ChangeKind::Removed, 
+                    merge::setting_path(member), Some(member), None));
+            }
+        },
+        (None, Some(new)) => {
+            for index in 0..merge::length(new) {
+                let member = merge::element(new, index);
+                changes.push(Change::new(ChangeKind::Added, 
+                    merge::setting_path(member), None, Some(member)));
+            }
+        },
+        (None, None) => {}
+    }
+    changes
+}
@@ -0,0 +1,858 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{Config, Errors, Result};
+use crate::patch::{self, Key};
+use crate::vfs::{self, ConfigFs, DiskFs};
+use regex::Regex;
+
+use std::fmt;
+use std::path::Path;
+
+/// Lossless document model of libconfig text. Document keeps comments, 
+/// blank lines, settings order, string concatenation and number formats. 
+/// Edits change only text of edited settings, the rest of document is 
+/// written back byte for byte.
+#[derive(Debug, Clone)]
+pub struct Document {
+    text : String,
+    root : Node,
+    comments : Vec<(usize, usize)>
+}
+
+/// Comments of group member: comment lines directly above it and comment 
+/// following it on the same line. Comment markers are stripped.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Comments {
+    pub(crate) leading : Vec<String>,
+    pub(crate) trailing : Option<String>
+}
+
+impl Comments {
+
+    /// Constructor.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Comments;
+    /// 
+    /// let comments = Comments::new(vec![String::from("Listening port")], 
+    ///     Some(String::from("default")));
+    /// ```
+    pub fn new(leading : Vec<String>, trailing : Option<String>) -> Comments {
+        Comments { leading, trailing }
+    }
+
+    /// Return comments above setting, one item per comment.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse("# Listening\n// port\nport = 80;").unwrap();
+    /// assert_eq!(doc.comments("port").unwrap().leading(), 
+    ///     &["Listening", "port"]);
+    /// ```
+    pub fn leading(&self) -> &[String] {
+        &self.leading
+    }
+
+    /// Return comment following setting on the same line.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse("port = 80; /* default */").unwrap();
+    /// assert_eq!(doc.comments("port").unwrap().trailing(), Some("default"));
+    /// ```
+    pub fn trailing(&self) -> Option<&str> {
+        self.trailing.as_deref()
+    }
+
+    /// Return true if there are no comments.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse("# Port\n\nport = 80;").unwrap();
+    /// assert!(doc.comments("port").unwrap().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_none()
+    }
+}
+
+// Syntax tree node, spans are byte offsets of value text.
+#[derive(Debug, Clone)]
+struct Node {
+    start : usize,
+    end : usize,
+    kind : NodeKind
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Scalar,
+    Group(Vec<Member>),
+    Array(Vec<Node>),
+    List(Vec<Node>)
+}
+
+// Group member, span covers name, value and terminator.
+#[derive(Debug, Clone)]
+struct Member {
+    name : String,
+    start : usize,
+    end : usize,
+    value : Node
+}
+
+// Recursive descent parser of libconfig syntax.
+struct Parser<'a> {
+    text : &'a str,
+    pos : usize,
+    comments : Vec<(usize, usize)>,
+    name : Regex,
+    scalar : Regex
+}
+
+impl<'a> Parser<'a> {
+
+    // Constructor.
+    fn new(text : &'a str) -> Parser<'a> {
+        Parser {
+            text,
+            pos : 0,
+            comments : Vec::new(),
+            name : Regex::new("^[A-Za-z*][-A-Za-z0-9_*]*$").unwrap(),
+            scalar : Regex::new(concat!("^(?:(?i:true|false)|",
+                "[-+]?[0-9]+(?:LL?)?|",
+                "0[xX][0-9A-Fa-f]+(?:LL?)?|",
+                "0[bB][01]+(?:LL?)?|",
+                "0[oOqQ][0-7]+(?:LL?)?|",
+                "[-+]?(?:[0-9]+\\.[0-9]*|\\.[0-9]+)(?:[eE][-+]?[0-9]+)?|",
+                "[-+]?[0-9]+[eE][-+]?[0-9]+)$")).unwrap()
+        }
+    }
+
+    // Return parse error at current position.
+    fn error(&self, message : &str) -> Errors {
+        Errors::DocumentError(format!("line {}: {}", 
+            self.text[..self.pos].matches('\n').count() + 1, message))
+    }
+
+    // Return current byte.
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    // Skip whitespace and comments, comments are recorded.
+    fn skip(&mut self) -> Result<()> {
+        loop {
+            let rest = &self.text[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            let start = self.pos;
+            if trimmed.starts_with('#') || trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => { self.pos += end + 4; },
+                    None => { return Err(self.error("unterminated comment")); }
+                }
+            } else {
+                return Ok(());
+            }
+
+            if self.comments.last().is_none_or(|last| { last.0 < start }) {
+                self.comments.push((start, self.pos));
+            }
+        }
+    }
+
+    // Parse group members until closing brace or end of text.
+    fn members(&mut self, group : bool) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        loop {
+            self.skip()?;
+            match self.peek() {
+                None if group => { return Err(self.error("missing '}'")); },
+                None => { return Ok(members); },
+                Some(b'}') if group => { return Ok(members); },
+                Some(b'@') => { self.include()?; },
+                _ => { members.push(self.member()?); }
+            }
+        }
+    }
+
+    // Parse include directive.
+    fn include(&mut self) -> Result<()> {
+        if !self.text[self.pos..].starts_with("@include") {
+            return Err(self.error("unknown directive"));
+        }
+        self.pos += "@include".len();
+        self.skip()?;
+        if self.peek() != Some(b'"') {
+            return Err(self.error("include file name expected"));
+        }
+        self.string()
+    }
+
+    // Parse group member.
+    fn member(&mut self) -> Result<Member> {
+        let start = self.pos;
+        let length = self.text[start..].find(|character : char| {
+            !(character.is_ascii_alphanumeric() || "-_*".contains(character))
+        }).unwrap_or(self.text.len() - start);
+        let name = &self.text[start..start + length];
+        if !self.name.is_match(name) {
+            return Err(self.error("setting name expected"));
+        }
+        self.pos += length;
+
+        self.skip()?;
+        match self.peek() {
+            Some(b'=') | Some(b':') => { self.pos += 1; },
+            _ => { return Err(self.error("'=' or ':' expected")); }
+        }
+        self.skip()?;
+        let value = self.value()?;
+
+        let end = self.pos;
+        self.skip()?;
+        match self.peek() {
+            Some(b';') | Some(b',') => { self.pos += 1; },
+            _ => { self.pos = end; }
+        }
+        Ok(Member { name : String::from(name), start, end : self.pos, value })
+    }
+
+    // Parse value.
+    fn value(&mut self) -> Result<Node> {
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let members = self.members(true)?;
+                self.pos += 1;
+                NodeKind::Group(members)
+            },
+            Some(b'[') => { NodeKind::Array(self.elements(b']')?) },
+            Some(b'(') => { NodeKind::List(self.elements(b')')?) },
+            Some(b'"') => {
+                self.string()?;
+                loop {
+                    let end = self.pos;
+                    self.skip()?;
+                    if self.peek() == Some(b'"') {
+                        self.string()?;
+                    } else {
+                        self.pos = end;
+                        break;
+                    }
+                }
+                NodeKind::Scalar
+            },
+            _ => {
+                let length = self.text[start..].find(|character : char| {
+                    !(character.is_ascii_alphanumeric() || 
+                        "+-.".contains(character))
+                }).unwrap_or(self.text.len() - start);
+                if !self.scalar.is_match(&self.text[start..start + length]) {
+                    return Err(self.error("value expected"));
+                }
+                self.pos += length;
+                NodeKind::Scalar
+            }
+        };
+        Ok(Node { start, end : self.pos, kind })
+    }
+
+    // Parse collection elements up to closing bracket.
+    fn elements(&mut self, close : u8) -> Result<Vec<Node>> {
+        let mut elements = Vec::new();
+        self.pos += 1;
+        loop {
+            self.skip()?;
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                return Ok(elements);
+            }
+            elements.push(self.value()?);
+            
+            self.skip()?;
+            match self.peek() {
+                Some(b',') => { self.pos += 1; },
+                Some(character) if character == close => {},
+                _ => { 
+                    return Err(self.error(&format!("',' or '{}' expected", 
+                        close as char)));
+                }
+            }
+        }
+    }
+
+    // Parse string literal.
+    fn string(&mut self) -> Result<()> {
+        let mut escaped = false;
+        for (index, character) in self.text[self.pos + 1..].char_indices() {
+            match character {
+                '\\' if !escaped => { escaped = true; },
+                '"' if !escaped => {
+                    self.pos += index + 2;
+                    return Ok(());
+                },
+                _ => { escaped = false; }
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+}
+
+impl Document {
+
+    /// Parse document from libconfig text.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let text = "# Server settings\nport = 0x1F90; // hex\n";
+    /// let doc = Document::parse(text).unwrap();
+    /// assert_eq!(doc.to_string(), text);
+    /// ```
+    pub fn parse(text : &str) -> Result<Document> {
+        let mut parser = Parser::new(text);
+        let members = parser.members(false)?;
+        Ok(Document {
+            text : String::from(text),
+            root : Node { start : 0, end : text.len(), 
+                kind : NodeKind::Group(members) },
+            comments : parser.comments
+        })
+    }
+
+    /// Read document from file.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// use std::path::Path;
+    /// 
+    /// assert!(Document::load_from_file(Path::new("missing.cfg")).is_err());
+    /// ```
+    pub fn load_from_file(file_name : &Path) -> Result<Document> {
+        match DiskFs.read(file_name) {
+            Ok(text) => { Document::parse(&text) },
+            Err(_) => { Err(Errors::FileNotExists) }
+        }
+    }
+
+    /// Write document to file, file is replaced atomically.
+    /// 
+    /// # Example
+    /// ```no_run
+    /// use librustconfig::document::Document;
+    /// use std::path::Path;
+    /// 
+    /// let mut doc = Document::load_from_file(Path::new("app.cfg")).unwrap();
+    /// doc.set("port", "8080").unwrap();
+    /// doc.save_to_file(Path::new("app.cfg")).unwrap();
+    /// ```
+    pub fn save_to_file(&self, file_name : &Path) -> Result<()> {
+        vfs::replace_file(&DiskFs, file_name, &self.text, false)
+            .map_err(|_| { Errors::SaveError })
+    }
+
+    /// Create config from document.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse("name = \"lib\" \"config\"; # name").unwrap();
+    /// let cfg = doc.to_config().unwrap();
+    /// assert_eq!(cfg.value("name").unwrap().as_string(), 
+    ///     Some(String::from("libconfig")));
+    /// ```
+    pub fn to_config(&self) -> Result<Config> {
+        let mut config = Config::new();
+        config.load_from_string(&self.text)?;
+        Ok(config)
+    }
+
+    /// Return value text of setting as written in document. Paths are 
+    /// libconfig paths with collection elements addressed by index.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse("limits = { size = 0xFF; ids = [ 1, 2 ]; };")
+    ///     .unwrap();
+    /// assert_eq!(doc.get("limits.size"), Some("0xFF"));
+    /// assert_eq!(doc.get("limits.ids.[1]"), Some("2"));
+    /// assert_eq!(doc.get("limits.missing"), None);
+    /// ```
+    pub fn get(&self, path : &str) -> Option<&str> {
+        self.find(path).ok().map(|node| { &self.text[node.start..node.end] })
+    }
+
+    /// Replace value of existing setting, value is written in libconfig 
+    /// syntax.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let mut doc = Document::parse(
+    ///     "# Listening port\nport = 80; # default\nhost = \"local\";\n")
+    ///     .unwrap();
+    /// doc.set("port", "8080").unwrap();
+    /// assert_eq!(doc.to_string(), 
+    ///     "# Listening port\nport = 8080; # default\nhost = \"local\";\n");
+    /// ```
+    pub fn set(&mut self, path : &str, value : &str) -> Result<()> {
+        check_value(value)?;
+        let node = self.find(path)?;
+        let (start, end) = (node.start, node.end);
+        self.splice(start, end, value)
+    }
+
+    /// Insert setting. Path names new group member or collection index, 
+    /// index equal to collection length appends element. New members are 
+    /// indented like their siblings.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let mut doc = Document::parse(concat!(
+    ///     "server = {\n",
+    ///     "  # Host name\n",
+    ///     "  host = \"local\";\n",
+    ///     "};\n",
+    ///     "ports = [ 80, 443 ];\n")).unwrap();
+    /// doc.insert("server.port", "8080").unwrap();
+    /// doc.insert("ports.[0]", "8080").unwrap();
+    /// assert_eq!(doc.to_string(), concat!(
+    ///     "server = {\n",
+    ///     "  # Host name\n",
+    ///     "  host = \"local\";\n",
+    ///     "  port = 8080;\n",
+    ///     "};\n",
+    ///     "ports = [ 8080, 80, 443 ];\n"));
+    /// ```
+    pub fn insert(&mut self, path : &str, value : &str) -> Result<()> {
+        check_value(value)?;
+        let (parent, key) = self.locate(path)?;
+        let (position, text) = match (&parent.kind, key) {
+            (NodeKind::Group(members), Key::Name(name)) => {
+                if !Parser::new("").name.is_match(&name) {
+                    return Err(error(path, "invalid setting name"));
+                }
+                if members.iter().any(|member| { member.name == name }) {
+                    return Err(error(path, "setting already exists"));
+                }
+                self.member_insertion(parent, members, 
+                    &format!("{} = {};", name, value))
+            },
+            (NodeKind::Array(elements), Key::Index(index)) | 
+            (NodeKind::List(elements), Key::Index(index)) => {
+                let index = index as usize;
+                if index > elements.len() {
+                    return Err(error(path, "index out of range"));
+                }
+                self.element_insertion(parent, elements, index, value)
+            },
+            (_, Key::Name(_)) => { 
+                return Err(error(path, "parent is not a group")); 
+            },
+            (_, Key::Index(_)) => { 
+                return Err(error(path, "parent is not a collection")); 
+            }
+        };
+        self.splice(position, position, &text)
+    }
+
+    /// Remove setting. Comment lines directly above group member and comment
+    /// following it on the same line are removed with it.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let mut doc = Document::parse(
+    ///     "port = 80;\n\n# Debug mode\ndebug = true; # temporary\n\
+    ///     hosts = ( \"a\", \"b\" );\n").unwrap();
+    /// doc.remove("debug").unwrap();
+    /// doc.remove("hosts.[0]").unwrap();
+    /// assert_eq!(doc.to_string(), "port = 80;\n\nhosts = ( \"b\" );\n");
+    /// ```
+    pub fn remove(&mut self, path : &str) -> Result<()> {
+        let (parent, key) = self.locate(path)?;
+        let (start, end) = match (&parent.kind, key) {
+            (NodeKind::Group(members), Key::Name(name)) => {
+                let member = members.iter()
+                    .find(|member| { member.name == name })
+                    .ok_or_else(|| { error(path, "setting not exists") })?;
+                self.member_span(member)
+            },
+            (NodeKind::Array(elements), Key::Index(index)) | 
+            (NodeKind::List(elements), Key::Index(index)) 
+                if (index as usize) < elements.len() => {
+                
+                let index = index as usize;
+                if index + 1 < elements.len() {
+                    (elements[index].start, elements[index + 1].start)
+                } else if index > 0 {
+                    (elements[index - 1].end, elements[index].end)
+                } else {
+                    (elements[index].start, elements[index].end)
+                }
+            },
+            _ => { return Err(error(path, "setting not exists")); }
+        };
+        self.splice(start, end, "")
+    }
+
+    /// Return comments of setting, collection elements have no comments. 
+    /// Return None if setting not exists.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::Document;
+    /// 
+    /// let doc = Document::parse(
+    ///     "server = {\n  # Host name\n  host = \"local\"; # or address\n};")
+    ///     .unwrap();
+    /// let comments = doc.comments("server.host").unwrap();
+    /// assert_eq!(comments.leading(), &["Host name"]);
+    /// assert_eq!(comments.trailing(), Some("or address"));
+    /// assert!(doc.comments("server.port").is_none());
+    /// ```
+    pub fn comments(&self, path : &str) -> Option<Comments> {
+        self.find(path).ok()?;
+        let member = match self.member(path) {
+            Ok(member) => { member },
+            Err(_) => { return Some(Comments::default()); }
+        };
+
+        let text = |(start, end) : (usize, usize)| -> String {
+            comment_text(&self.text[start..end])
+        };
+        Some(Comments {
+            leading : self.leading_comments(member).into_iter().map(text)
+                .collect(),
+            trailing : self.trailing_comment(member).map(text)
+        })
+    }
+
+    /// Replace comments of group member. Leading comments are written as 
+    /// `#` lines above member, trailing comment follows it on the same line.
+    /// Comments can't contain `*/`, trailing comment can't contain line 
+    /// breaks.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::document::{Comments, Document};
+    /// 
+    /// let mut doc = Document::parse("# Old\nport = 80;\n").unwrap();
+    /// doc.set_comments("port", &Comments::new(
+    ///     vec![String::from("Listening port")], 
+    ///     Some(String::from("default")))).unwrap();
+    /// assert_eq!(doc.to_string(), "# Listening port\nport = 80; # default\n");
+    /// ```
+    pub fn set_comments(&mut self, path : &str, comments : &Comments) 
+        -> Result<()> {
+        
+        let member = self.member(path)?;
+        let valid = comments.leading.iter().all(|comment| { 
+            valid_comment(comment, false) 
+        }) && comments.trailing.iter().all(|comment| { 
+            valid_comment(comment, true) 
+        });
+        if !valid {
+            return Err(error(path, "invalid comment"));
+        }
+
+        let line = line_start(&self.text, member.start);
+        let inline = !self.text[line..member.start].trim().is_empty();
+        
+        let (start, end) = match self.trailing_comment(member) {
+            Some((_, end)) => { (member.end, end) },
+            None => { (member.end, member.end) }
+        };
+        let line_end = self.text[end..].find('\n')
+            .map_or(self.text.len(), |offset| { end + offset });
+        let trailing = match &comments.trailing {
+            Some(comment) if self.text[end..line_end].trim().is_empty() => {
+                format!(" # {}", comment)
+            },
+            Some(comment) => { format!(" /* {} */", comment) },
+            None => { String::new() }
+        };
+
+        let (leading_start, leading_end) = if inline {
+            (member.start, member.start)
+        } else {
+            let first = self.leading_comments(member).first()
+                .map_or(member.start, |(start, _)| { *start });
+            (line_start(&self.text, first), line)
+        };
+        let indent = if inline { "" } else { &self.text[line..member.start] };
+        let leading : String = comments.leading.iter()
+            .flat_map(|comment| { comment.lines() })
+            .map(|comment| {
+                if inline {
+                    format!("/* {} */ ", comment)
+                } else {
+                    format!("{}# {}\n", indent, comment)
+                }
+            }).collect();
+
+        self.splice(start, end, &trailing)?;
+        self.splice(leading_start, leading_end, &leading)
+    }
+
+    // Find group member by path.
+    fn member(&self, path : &str) -> Result<&Member> {
+        let (parent, key) = self.locate(path)?;
+        match (&parent.kind, key) {
+            (NodeKind::Group(members), Key::Name(name)) => {
+                members.iter().find(|member| { member.name == name })
+                    .ok_or_else(|| { error(path, "setting not exists") })
+            },
+            _ => { Err(error(path, "setting is not a group member")) }
+        }
+    }
+
+    // Find value node by path.
+    fn find(&self, path : &str) -> Result<&Node> {
+        patch::keys(path).map_err(|message| { error(path, &message) })?
+            .iter().try_fold(&self.root, |node, key| { child(node, key) })
+            .ok_or_else(|| { error(path, "setting not exists") })
+    }
+
+    // Find parent node of path and last path key.
+    fn locate(&self, path : &str) -> Result<(&Node, Key)> {
+        let mut keys = patch::keys(path)
+            .map_err(|message| { error(path, &message) })?;
+        let key = keys.pop().ok_or_else(|| { error(path, "empty path") })?;
+        let parent = keys.iter()
+            .try_fold(&self.root, |node, key| { child(node, key) })
+            .ok_or_else(|| { error(path, "parent setting not exists") })?;
+        Ok((parent, key))
+    }
+
+    // Return position and text inserting member into group.
+    fn member_insertion(&self, group : &Node, members : &[Member], 
+        member : &str) -> (usize, String) {
+        
+        match members.last() {
+            Some(last) => {
+                let position = self.trailing_comment(last)
+                    .map_or(last.end, |(_, end)| { end });
+                let line = line_start(&self.text, last.start);
+                let indent = &self.text[line..last.start];
+                let root = std::ptr::eq(group, &self.root);
+                if line <= group.start && !root {
+                    (position, format!(" {}", member))
+                } else if indent.trim().is_empty() {
+                    (position, format!("\n{}{}", indent, member))
+                } else {
+                    (position, format!("\n{}", member))
+                }
+            },
+            None if std::ptr::eq(group, &self.root) => {
+                if self.text.is_empty() || self.text.ends_with('\n') {
+                    (self.text.len(), format!("{}\n", member))
+                } else {
+                    (self.text.len(), format!("\n{}\n", member))
+                }
+            },
+            None => { (group.start + 1, format!(" {} ", member)) }
+        }
+    }
+
+    // Return position and text inserting element into collection.
+    fn element_insertion(&self, collection : &Node, elements : &[Node], 
+        index : usize, value : &str) -> (usize, String) {
+        
+        let separator = elements.windows(2).map(|pair| { 
+            &self.text[pair[0].end..pair[1].start] 
+        }).find(|separator| { 
+            separator.trim() == "," 
+        }).unwrap_or(", ");
+
+        if index < elements.len() {
+            (elements[index].start, format!("{}{}", value, separator))
+        } else if let Some(last) = elements.last() {
+            (last.end, format!("{}{}", separator, value))
+        } else {
+            (collection.start + 1, format!(" {} ", value))
+        }
+    }
+
+    // Return span removed with member. Whole lines are removed if member 
+    // occupies them.
+    fn member_span(&self, member : &Member) -> (usize, usize) {
+        let start = self.leading_comments(member).first()
+            .map_or(member.start, |(start, _)| { *start });
+        let end = self.trailing_comment(member)
+            .map_or(member.end, |(_, end)| { end });
+
+        let line = line_start(&self.text, start);
+        let line_end = self.text[end..].find('\n')
+            .map_or(self.text.len(), |offset| { end + offset + 1 });
+        if self.text[line..start].trim().is_empty() && 
+            self.text[end..line_end].trim().is_empty() {
+            (line, line_end)
+        } else {
+            let spaces = self.text[end..].len() - 
+                self.text[end..].trim_start_matches([' ', '\t']).len();
+            (member.start, end + spaces)
+        }
+    }
+
+    // Return comments on lines directly above member.
+    fn leading_comments(&self, member : &Member) -> Vec<(usize, usize)> {
+        let mut comments = Vec::new();
+        let mut position = line_start(&self.text, member.start);
+        if !self.text[position..member.start].trim().is_empty() {
+            return comments;
+        }
+
+        for comment in self.comments.iter().rev()
+            .skip_while(|comment| { comment.0 >= member.start }) {
+            
+            let gap = &self.text[comment.1..position];
+            let line = line_start(&self.text, comment.0);
+            if !gap.trim().is_empty() || gap.matches('\n').count() > 1 || 
+                !self.text[line..comment.0].trim().is_empty() {
+                break;
+            }
+            comments.push(*comment);
+            position = line;
+        }
+        comments.reverse();
+        comments
+    }
+
+    // Return comment following member on the same line.
+    fn trailing_comment(&self, member : &Member) -> Option<(usize, usize)> {
+        self.comments.iter().find(|comment| { 
+            comment.0 >= member.end && 
+                self.text[member.end..comment.0].trim_start_matches(
+                    [' ', '\t']).is_empty()
+        }).copied()
+    }
+
+    // Replace text span and parse changed document.
+    fn splice(&mut self, start : usize, end : usize, text : &str) 
+        -> Result<()> {
+        
+        let mut changed = self.text.clone();
+        changed.replace_range(start..end, text);
+        *self = Document::parse(&changed)?;
+        Ok(())
+    }
+}
+
+/// Document is shown as its text.
+impl fmt::Display for Document {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// Return child node by key.
+fn child<'a>(node : &'a Node, key : &Key) -> Option<&'a Node> {
+    match (&node.kind, key) {
+        (NodeKind::Group(members), Key::Name(name)) => {
+            members.iter().find(|member| { &member.name == name })
+                .map(|member| { &member.value })
+        },
+        (NodeKind::Array(elements), Key::Index(index)) | 
+        (NodeKind::List(elements), Key::Index(index)) => {
+            elements.get(*index as usize)
+        },
+        _ => { None }
+    }
+}
+
+// Check text is single libconfig value.
+fn check_value(value : &str) -> Result<()> {
+    let mut parser = Parser::new(value);
+    let error = |_| { 
+        Errors::DocumentError(format!("invalid value {}", value)) 
+    };
+    parser.skip().map_err(error)?;
+    parser.value().map_err(error)?;
+    parser.skip().map_err(error)?;
+    if parser.pos == value.len() && parser.comments.is_empty() { 
+        Ok(()) 
+    } else { 
+        Err(error(Errors::ParseError)) 
+    }
+}
+
+// Return comment text without comment markers.
+fn comment_text(comment : &str) -> String {
+    let text = if let Some(text) = comment.strip_prefix("/*") {
+        text.strip_suffix("*/").unwrap_or(text)
+    } else if let Some(text) = comment.strip_prefix("//") {
+        text
+    } else {
+        comment.strip_prefix('#').unwrap_or(comment)
+    };
+    String::from(text.trim())
+}
+
+// Return document error of path.
+fn error(path : &str, message : &str) -> Errors {
+    Errors::DocumentError(format!("{}: {}", path, message))
+}
+
+// Check that comment can be written without closing comment block or line.
+pub(crate) fn valid_comment(comment : &str, trailing : bool) -> bool {
+    if comment.contains("*/") {
+        return false;
+    }
+    !trailing || !comment.contains(|c| { c == '\n' || c == '\r' })
+}
+
+// Return position of line start.
+fn line_start(text : &str, position : usize) -> usize {
+    text[..position].rfind('\n').map_or(0, |index| { index + 1 })
+}
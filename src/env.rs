@@ -0,0 +1,631 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, Result};
+use crate::merge::{self, Merger, MergePolicy, Setting};
+use crate::secret;
+
+use std::convert::TryFrom;
+use std::env;
+use std::ffi::CString;
+
+/// Overlay of environment variables onto config settings. Variable 
+/// `APP__SERVER__PORT` overrides setting `server.port` for prefix `APP`.
+/// Path segments match existing settings case-insensitively, new settings
+/// are named in lower case.
+pub struct EnvOverlay {
+    prefix : String,
+    separator : String,
+    vars : Vec<(String, String)>
+}
+
+/// Setting overridden by environment variable.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnvOverride {
+    variable : String,
+    path : String,
+    value : String,
+    created : bool
+}
+
+/// Value parsed from environment variable.
+#[derive(Debug, PartialEq, Clone)]
+enum EnvValue {
+    Integer(i32),
+    Int64(i64),
+    Float(f64),
+    Boolean(bool),
+    Str(String),
+    Array(Vec<EnvValue>),
+    List(Vec<EnvValue>)
+}
+
+/// Override prepared for writing.
+struct PendingOverride {
+    names : Vec<String>,
+    value : EnvValue,
+    report : EnvOverride
+}
+
+impl EnvOverlay {
+
+    /// Constructor.
+    /// Create overlay of process environment variables starting with prefix.
+    /// Variables with non UTF-8 name or value are skipped.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let overlay = EnvOverlay::new("APP");
+    /// ```
+    pub fn new<S>(prefix : S) -> EnvOverlay where S: Into<String> {
+        EnvOverlay::from_vars(prefix, env::vars_os().filter_map(
+            |(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            }))
+    }
+
+    /// Constructor.
+    /// Create overlay of given variables starting with prefix.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__SERVER__PORT"), String::from("8080"))]);
+    /// ```
+    pub fn from_vars<S, I>(prefix : S, vars : I) -> EnvOverlay 
+        where S: Into<String>, I: IntoIterator<Item = (String, String)> {
+        
+        let mut vars : Vec<(String, String)> = vars.into_iter().collect();
+        vars.sort();
+        
+        EnvOverlay {
+            prefix : prefix.into(),
+            separator : String::from("__"),
+            vars
+        }
+    }
+
+    /// Set separator of prefix and path segments, default is `__`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut overlay = EnvOverlay::new("APP");
+    /// overlay.set_separator("_");
+    /// ```
+    pub fn set_separator<S>(&mut self, separator : S) where S: Into<String> {
+        self.separator = separator.into();
+    }
+
+    /// Override config settings by variables and return report of overridden
+    /// settings. Value is parsed according to type of existing setting, type
+    /// of new setting is inferred: integer, 64bit integer (with `L` suffix or
+    /// too big for integer), float, boolean or string. Array values are 
+    /// written as `[a, b]`, list values as `(a, b)`, existing arrays and lists
+    /// accept comma separated values too. Config is left unchanged if any 
+    /// variable can't be applied.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { port = 80; };").unwrap();
+    /// 
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__SERVER__PORT"), String::from("8080"))]);
+    /// let report = overlay.apply(&mut cfg).unwrap();
+    /// assert_eq!(report[0].path(), "server.port");
+    /// assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    /// ```
+    pub fn apply(&self, config : &mut Config) -> Result<Vec<EnvOverride>> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        let prefix = format!("{}{}", self.prefix, self.separator);
+        
+        let mut pending = Vec::new();
+        for (variable, value) in &self.vars {
+            if let Some(path) = variable.strip_prefix(&prefix) {
+                pending.push(self.prepare(root, variable, path, value)
+                    .map_err(|message| {
+                        Errors::OverrideError(format!("{}: {}", variable, 
+                            message))
+                    })?);
+            }
+        }
+
+        if let Some((parent, child)) = collision(&pending) {
+            return Err(Errors::OverrideError(format!("{}: {} is overridden \
+                by {}", child.report.variable, parent.report.path, 
+                parent.report.variable)));
+        }
+
+        let staged = config.empty_copy();
+        let target = staged.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |_, _| {})
+            .merge_group(target, root);
+        write(target, &pending)?;
+
+        write(root, &pending)?;
+        Ok(pending.into_iter().map(|item| { item.report }).collect())
+    }
+
+    // Resolve setting path of variable and parse its value.
+    fn prepare(&self, root : Setting, variable : &str, path : &str, 
+        value : &str) -> std::result::Result<PendingOverride, String> {
+        
+        let mut names = Vec::new();
+        let mut current = Some(root);
+        for segment in path.split(self.separator.as_str()) {
+            if segment.is_empty() {
+                return Err(String::from("empty setting name"));
+            }
+            if !merge::valid_name(segment) {
+                return Err(format!("invalid setting name {:?}", segment));
+            }
+
+            if let Some(setting) = current {
+                if merge::setting_type(setting) != raw::CONFIG_TYPE_GROUP {
+                    return Err(format!("{} is not a group", 
+                        merge::setting_path(setting)));
+                }
+            }
+
+            current = current.and_then(|group| { member(group, segment) });
+            names.push(match current {
+                Some(setting) => { merge::setting_name(setting) },
+                None => { segment.to_lowercase() }
+            });
+        }
+
+        let value = {
+            match current {
+                Some(setting) => { EnvValue::parse_as(setting, value)? },
+                None => { EnvValue::infer(value)? }
+            }
+        };
+        let secret = value.refers_secret() || 
+            current.is_some_and(config::is_secret);
+
+        Ok(PendingOverride {
+            report : EnvOverride {
+                variable : variable.to_string(),
+                path : names.join("."),
+                value : if secret { 
+                    String::from(secret::REDACTED) 
+                } else { 
+                    value.to_string() 
+                },
+                created : current.is_none()
+            },
+            names,
+            value
+        })
+    }
+}
+
+// Write overrides to group.
+fn write(root : Setting, pending : &[PendingOverride]) -> Result<()> {
+    for item in pending {
+        let setting = merge::upsert(root, &item.names, item.value.kind())
+            .map_err(|message| {
+                Errors::OverrideError(format!("{}: {}", item.report.variable,
+                    message))
+            })?;
+        item.value.write(setting);
+    }
+    Ok(())
+}
+
+// Find override which writes value into parent of other override.
+fn collision(pending : &[PendingOverride]) 
+    -> Option<(&PendingOverride, &PendingOverride)> {
+    
+    pending.iter().find_map(|parent| {
+        pending.iter().find(|child| {
+            child.names.len() > parent.names.len() &&
+                child.names.starts_with(&parent.names)
+        }).map(|child| { (parent, child) })
+    })
+}
+
+impl EnvOverride {
+
+    /// Return name of environment variable.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut cfg = Config::new();
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__DEBUG"), String::from("true"))]);
+    /// let report = overlay.apply(&mut cfg).unwrap();
+    /// assert_eq!(report[0].variable(), "APP__DEBUG");
+    /// ```
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    /// Return path of overridden setting.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut cfg = Config::new();
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__DEBUG"), String::from("true"))]);
+    /// let report = overlay.apply(&mut cfg).unwrap();
+    /// assert_eq!(report[0].path(), "debug");
+    /// ```
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return variable value. Values referring to secrets and values of 
+    /// settings read by `OptionReader::as_secret` are redacted.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut cfg = Config::new();
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__DEBUG"), String::from("true"))]);
+    /// let report = overlay.apply(&mut cfg).unwrap();
+    /// assert_eq!(report[0].value(), "true");
+    /// ```
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Return true if setting did not exist before override.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::env::EnvOverlay;
+    /// 
+    /// let mut cfg = Config::new();
+    /// let overlay = EnvOverlay::from_vars("APP", vec![
+    ///     (String::from("APP__DEBUG"), String::from("true"))]);
+    /// let report = overlay.apply(&mut cfg).unwrap();
+    /// assert!(report[0].created());
+    /// ```
+    pub fn created(&self) -> bool {
+        self.created
+    }
+}
+
+impl EnvValue {
+
+    // Parse value as type of existing setting.
+    fn parse_as(setting : Setting, value : &str) 
+        -> std::result::Result<EnvValue, String> {
+        
+        let kind = merge::setting_type(setting);
+        match kind {
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                let items = split_items(value, kind).unwrap_or_else(|| {
+                    split(value)
+                });
+                let items = {
+                    match merge::length(setting) {
+                        0 => { 
+                            items.iter().map(|item| { EnvValue::infer(item) })
+                                .collect::<std::result::Result<Vec<_>, _>>()?
+                        },
+                        _ => {
+                            let element = merge::element(setting, 0);
+                            items.iter().map(|item| { 
+                                EnvValue::parse_as(element, item) 
+                            }).collect::<std::result::Result<Vec<_>, _>>()?
+                        }
+                    }
+                };
+                EnvValue::collection(kind, items)
+            },
+            raw::CONFIG_TYPE_GROUP => {
+                Err(format!("{} is a group", merge::setting_path(setting)))
+            },
+            _ => {
+                EnvValue::parse_scalar(kind, value.trim()).ok_or_else(|| {
+                    format!("invalid {} value {:?} for {}", type_name(kind), 
+                        value, merge::setting_path(setting))
+                })
+            }
+        }
+    }
+
+    // Parse value inferring its type.
+    fn infer(value : &str) -> std::result::Result<EnvValue, String> {
+        let value = value.trim();
+        
+        for kind in [raw::CONFIG_TYPE_ARRAY, raw::CONFIG_TYPE_LIST] {
+            if let Some(items) = split_items(value, kind) {
+                let items = items.iter().map(|item| { EnvValue::infer(item) })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                return EnvValue::collection(kind, items);
+            }
+        }
+
+        let value = [raw::CONFIG_TYPE_BOOL, raw::CONFIG_TYPE_INT, 
+            raw::CONFIG_TYPE_INT64, raw::CONFIG_TYPE_FLOAT].iter()
+            .find_map(|kind| { EnvValue::parse_scalar(*kind, value) })
+            .unwrap_or_else(|| { EnvValue::Str(unquote(value).to_string()) });
+        Ok(value)
+    }
+
+    // Parse scalar value of type.
+    fn parse_scalar(kind : i16, value : &str) -> Option<EnvValue> {
+        match kind {
+            raw::CONFIG_TYPE_INT => {
+                parse_integer(value).and_then(|value| { 
+                    i32::try_from(value).ok() 
+                }).map(EnvValue::Integer)
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                let value = value.strip_suffix('L').unwrap_or(value);
+                parse_integer(value).map(EnvValue::Int64)
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                let numeric = value.chars().any(|c| { c.is_ascii_digit() }) &&
+                    value.chars().all(|c| { 
+                        c.is_ascii_digit() || "+-.eE".contains(c) 
+                    });
+                if !numeric {
+                    return None;
+                }
+                value.parse::<f64>().ok().map(EnvValue::Float)
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                match value.to_lowercase().as_str() {
+                    "true" => { Some(EnvValue::Boolean(true)) },
+                    "false" => { Some(EnvValue::Boolean(false)) },
+                    _ => { None }
+                }
+            },
+            raw::CONFIG_TYPE_STRING => {
+                Some(EnvValue::Str(unquote(value).to_string()))
+            },
+            _ => { None }
+        }
+    }
+
+    // Create collection, array elements must be scalars of the same type,
+    // integers are widened to 64bit integers when needed.
+    fn collection(kind : i16, items : Vec<EnvValue>) 
+        -> std::result::Result<EnvValue, String> {
+        
+        if kind == raw::CONFIG_TYPE_LIST {
+            return Ok(EnvValue::List(items));
+        }
+
+        let items = {
+            if items.iter().any(|item| { 
+                item.kind() == raw::CONFIG_TYPE_INT64 
+            }) {
+                items.into_iter().map(|item| {
+                    match item {
+                        EnvValue::Integer(value) => { 
+                            EnvValue::Int64(value as i64) 
+                        },
+                        item => { item }
+                    }
+                }).collect()
+            } else {
+                items
+            }
+        };
+
+        match items.first().map(|item| { item.kind() }) {
+            Some(kind) if items.iter().any(|item| { item.kind() != kind }) => {
+                Err(String::from("array elements must be of the same type"))
+            },
+            Some(raw::CONFIG_TYPE_ARRAY) | Some(raw::CONFIG_TYPE_LIST) => {
+                Err(String::from("array elements must be scalar values"))
+            },
+            _ => { Ok(EnvValue::Array(items)) }
+        }
+    }
+
+    // Return true if value or its element refers to secret.
+    fn refers_secret(&self) -> bool {
+        match self {
+            EnvValue::Str(value) => { 
+                value.starts_with(secret::SECRET_PREFIX) 
+            },
+            EnvValue::Array(items) | EnvValue::List(items) => {
+                items.iter().any(|item| { item.refers_secret() })
+            },
+            _ => { false }
+        }
+    }
+
+    // Return libconfig type of value.
+    fn kind(&self) -> i16 {
+        match self {
+            EnvValue::Integer(_) => { raw::CONFIG_TYPE_INT },
+            EnvValue::Int64(_) => { raw::CONFIG_TYPE_INT64 },
+            EnvValue::Float(_) => { raw::CONFIG_TYPE_FLOAT },
+            EnvValue::Boolean(_) => { raw::CONFIG_TYPE_BOOL },
+            EnvValue::Str(_) => { raw::CONFIG_TYPE_STRING },
+            EnvValue::Array(_) => { raw::CONFIG_TYPE_ARRAY },
+            EnvValue::List(_) => { raw::CONFIG_TYPE_LIST }
+        }
+    }
+
+    // Write value to setting of the same type.
+    fn write(&self, setting : Setting) {
+        unsafe {
+            match self {
+                EnvValue::Integer(value) => { 
+                    raw::config_setting_set_int(setting, *value); 
+                },
+                EnvValue::Int64(value) => { 
+                    raw::config_setting_set_int64(setting, *value); 
+                },
+                EnvValue::Float(value) => { 
+                    raw::config_setting_set_float(setting, *value); 
+                },
+                EnvValue::Boolean(value) => { 
+                    raw::config_setting_set_bool(setting, *value as i32); 
+                },
+                EnvValue::Str(value) => {
+                    let value = CString::new(value.as_str())
+                        .unwrap_or_default();
+                    raw::config_setting_set_string(setting, value.as_ptr());
+                },
+                EnvValue::Array(items) | EnvValue::List(items) => {
+                    let empty = CString::default();
+                    for item in items {
+                        let element = raw::config_setting_add(setting, 
+                            empty.as_ptr(), item.kind() as i32);
+                        if !element.is_null() {
+                            item.write(element);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for EnvValue {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |items : &Vec<EnvValue>| -> String {
+            items.iter().map(|item| { item.to_string() })
+                .collect::<Vec<String>>().join(", ")
+        };
+
+        match self {
+            EnvValue::Integer(value) => { write!(f, "{}", value) },
+            EnvValue::Int64(value) => { write!(f, "{}L", value) },
+            EnvValue::Float(value) => { write!(f, "{:?}", value) },
+            EnvValue::Boolean(value) => { write!(f, "{}", value) },
+            EnvValue::Str(value) => { write!(f, "{:?}", value) },
+            EnvValue::Array(items) => { write!(f, "[{}]", join(items)) },
+            EnvValue::List(items) => { write!(f, "({})", join(items)) }
+        }
+    }
+}
+
+// Find group member by name ignoring case.
+fn member(group : Setting, name : &str) -> Option<Setting> {
+    (0..merge::length(group)).map(|index| { merge::element(group, index) })
+        .find(|setting| { 
+            merge::setting_name(*setting).eq_ignore_ascii_case(name) 
+        })
+}
+
+// Return name of libconfig type used in error messages.
+fn type_name(kind : i16) -> &'static str {
+    match kind {
+        raw::CONFIG_TYPE_INT => { "integer" },
+        raw::CONFIG_TYPE_INT64 => { "64bit integer" },
+        raw::CONFIG_TYPE_FLOAT => { "float" },
+        raw::CONFIG_TYPE_BOOL => { "boolean" },
+        _ => { "string" }
+    }
+}
+
+// Parse decimal or hexadecimal integer.
+fn parse_integer(value : &str) -> Option<i64> {
+    let (negative, digits) = {
+        match value.strip_prefix('-') {
+            Some(digits) => { (true, digits) },
+            None => { (false, value.strip_prefix('+').unwrap_or(value)) }
+        }
+    };
+    
+    let result = {
+        match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            Some(hex) => { i64::from_str_radix(hex, 16).ok()? },
+            None if !digits.is_empty() && 
+                digits.chars().all(|c| { c.is_ascii_digit() }) => { 
+                digits.parse::<i64>().ok()? 
+            },
+            None => { return None; }
+        }
+    };
+    
+    Some(if negative { -result } else { result })
+}
+
+// Return items of value enclosed in brackets of collection kind.
+fn split_items(value : &str, kind : i16) -> Option<Vec<String>> {
+    let (open, close) = {
+        match kind {
+            raw::CONFIG_TYPE_ARRAY => { ('[', ']') },
+            _ => { ('(', ')') }
+        }
+    };
+
+    let value = value.trim();
+    let inner = value.strip_prefix(open)?.strip_suffix(close)?;
+    Some(split(inner))
+}
+
+// Split comma separated values, commas inside quotes are kept.
+fn split(value : &str) -> Vec<String> {
+    if value.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => { quoted = !quoted; item.push(c); },
+            ',' if !quoted => { items.push(item.trim().to_string()); 
+                item.clear(); },
+            _ => { item.push(c); }
+        }
+    }
+    items.push(item.trim().to_string());
+    items
+}
+
+// Remove quotes around value.
+fn unquote(value : &str) -> &str {
+    value.strip_prefix('"').and_then(|value| { value.strip_suffix('"') })
+        .unwrap_or(value)
+}
@@ -0,0 +1,638 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{Errors, Result};
+use crate::vfs::ConfigFs;
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default limit of nested includes. libconfig itself refuses to nest more 
+/// than 10 files deep.
+pub const DEFAULT_INCLUDE_DEPTH : usize = 10;
+
+/// Extension of files included when `@include` names a directory.
+pub const DIRECTORY_INCLUDE_EXTENSION : &str = "cfg";
+
+/// Source of configuration included by `@include` directive.
+#[derive(Debug, PartialEq, Clone)]
+pub enum IncludeSource {
+    /// Configuration file on disk.
+    File(PathBuf),
+
+    /// Configuration held in memory. Name is reported as source file of the
+    /// settings read from contents.
+    Memory {
+        name : String,
+        contents : String
+    }
+}
+
+/// Sources resolved for include paths.
+pub(crate) type IncludeCache = HashMap<String, Vec<IncludeSource>>;
+
+/// Counter used to create unique temporary directories.
+static TEMP_DIR_COUNTER : AtomicUsize = AtomicUsize::new(0);
+
+/// Included sources of one config. libconfig reads includes only from files, 
+/// so in-memory sources and files of non-local filesystems are passed to it 
+/// as anonymous memory files on Linux and as temporary files elsewhere while
+/// config is parsed. Their names and contents are remembered to report them
+/// as settings source files and to read comments of their settings.
+#[derive(Default)]
+pub(crate) struct IncludeSources {
+    temp_dir : Option<PathBuf>,
+    temp_files : Vec<PathBuf>,
+    memory_files : Vec<fs::File>,
+    names : Vec<(PathBuf, String, String)>
+}
+
+impl IncludeSources {
+
+    // Return file path libconfig should read for the source. Files are 
+    // passed with contents read when includes were checked.
+    pub(crate) fn materialize(&mut self, fs : &dyn ConfigFs, 
+        source : IncludeSource, checked : &HashMap<PathBuf, String>) 
+        -> io::Result<PathBuf> {
+        
+        match source {
+            IncludeSource::File(path) => { 
+                let contents = {
+                    match checked.get(&path) {
+                        Some(contents) => { contents.clone() },
+                        None => { fs.read(&path)? }
+                    }
+                };
+                self.store(path.display().to_string(), contents)
+            },
+            IncludeSource::Memory { name, contents } => {
+                self.store(name, contents)
+            }
+        }
+    }
+
+    // Store contents to memory file or, if it is not supported, to 
+    // temporary file readable by owner only.
+    fn store(&mut self, name : String, contents : String) 
+        -> io::Result<PathBuf> {
+        
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(path) = self.store_in_memory(&contents) {
+                self.names.push((path.clone(), name, contents));
+                return Ok(path);
+            }
+        }
+
+        let dir = self.temp_dir()?;
+        let path = dir.join(format!("include-{}.cfg", self.names.len()));
+        
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path)?;
+        self.temp_files.push(path.clone());
+        file.write_all(contents.as_bytes())?;
+        
+        self.names.push((path.clone(), name, contents));
+        Ok(path)
+    }
+
+    // Store contents to anonymous memory file, libconfig opens it by link in
+    // /proc/self/fd. Return None if memory files are not available.
+    #[cfg(target_os = "linux")]
+    fn store_in_memory(&mut self, contents : &str) -> Option<PathBuf> {
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe {
+            libc::memfd_create(b"librustconfig-include\0".as_ptr() 
+                as *const libc::c_char, libc::MFD_CLOEXEC)
+        };
+        if fd < 0 {
+            return None;
+        }
+
+        let mut file = unsafe { fs::File::from_raw_fd(fd) };
+        let path = PathBuf::from(format!("/proc/self/fd/{}", fd));
+        if file.write_all(contents.as_bytes()).is_err() || !path.exists() {
+            return None;
+        }
+        self.memory_files.push(file);
+        Some(path)
+    }
+
+    // Return name of source stored in file.
+    pub(crate) fn name(&self, file : &Path) -> Option<&str> {
+        self.names.iter().find(|(path, _, _)| path == file)
+            .map(|(_, name, _)| name.as_str())
+    }
+
+    // Return contents libconfig parsed from file.
+    pub(crate) fn text(&self, file : &Path) -> Option<&str> {
+        self.names.iter().find(|(path, _, _)| path == file)
+            .map(|(_, _, contents)| contents.as_str())
+    }
+
+    // Remove temporary files when config has been parsed. Names of sources 
+    // are kept while settings read from them are alive.
+    pub(crate) fn release_files(&mut self) {
+        for file in self.temp_files.drain(..) {
+            let _ = fs::remove_file(file);
+        }
+        self.memory_files.clear();
+    }
+
+    // Forget all sources, called before config is parsed again.
+    pub(crate) fn reset(&mut self) {
+        self.release_files();
+        self.names.clear();
+    }
+
+    // Create temporary directory accessible by owner only on first use. 
+    // Directory must not exist before, so existing directory or link placed 
+    // by other user is never used.
+    fn temp_dir(&mut self) -> io::Result<PathBuf> {
+        if let Some(dir) = &self.temp_dir {
+            return Ok(dir.clone());
+        }
+
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        loop {
+            let dir = std::env::temp_dir().join(format!(
+                "librustconfig-{}-{}-{}", process::id(), 
+                TEMP_DIR_COUNTER.fetch_add(1, Ordering::SeqCst), 
+                random_suffix()));
+            match builder.create(&dir) {
+                Ok(()) => {
+                    self.temp_dir = Some(dir.clone());
+                    return Ok(dir);
+                },
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    continue;
+                },
+                Err(error) => { return Err(error); }
+            }
+        }
+    }
+}
+
+impl Drop for IncludeSources {
+    fn drop(&mut self) {
+        self.release_files();
+        if let Some(dir) = &self.temp_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+// Return hard to guess suffix of temporary directory name.
+fn random_suffix() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_or(0, |time| { time.as_nanos() }));
+    hasher.finish()
+}
+
+impl IncludeSource {
+
+    // Name of the source used in include chains.
+    fn display_name(&self) -> String {
+        match self {
+            IncludeSource::File(path) => { path.display().to_string() },
+            IncludeSource::Memory { name, .. } => { name.clone() }
+        }
+    }
+
+    // Identity of the source used to detect include cycles.
+    fn identity(&self, fs : &dyn ConfigFs) -> String {
+        match self {
+            IncludeSource::File(path) => { 
+                fs.canonicalize(path).display().to_string()
+            },
+            IncludeSource::Memory { name, .. } => { 
+                format!("memory:{}", name) 
+            }
+        }
+    }
+}
+
+/// Resolve include path the way `Config` does when no include resolver is 
+/// set. Relative paths are resolved against include directory. Path 
+/// components may contain `*`, `?` and `[...]` wildcards, matched files are
+/// returned in sorted order. Path naming a directory includes all `*.cfg` 
+/// files from it in sorted order.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::include::resolve_path;
+/// use librustconfig::vfs::DiskFs;
+/// use std::path::Path;
+/// 
+/// let files = resolve_path(&DiskFs, Path::new("/etc/app"), "conf.d/*.cfg");
+/// ```
+pub fn resolve_path(fs : &dyn ConfigFs, include_dir : &Path, path : &str) 
+    -> io::Result<Vec<PathBuf>> {
+    
+    let path = Path::new(path);
+    let full_path = {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            include_dir.join(path)
+        }
+    };
+
+    let mut files = {
+        if has_wildcards(&full_path) {
+            expand_wildcards(fs, &full_path)?
+        } else {
+            vec![full_path]
+        }
+    };
+
+    let mut result = Vec::new();
+    for file in files.drain(..) {
+        if fs.is_dir(&file) {
+            let mut entries = Vec::new();
+            for entry in fs.list(&file)? {
+                if !fs.is_dir(&entry) && entry.extension().map(|extension| {
+                    extension == DIRECTORY_INCLUDE_EXTENSION
+                }).unwrap_or(false) && !is_hidden(&entry) {
+                    entries.push(entry);
+                }
+            }
+            entries.sort();
+            result.append(&mut entries);
+        } else {
+            result.push(file);
+        }
+    }
+    Ok(result)
+}
+
+// Return directory whose entries are included by wildcard or directory 
+// include path, None for plain file includes.
+pub(crate) fn include_dir(fs : &dyn ConfigFs, include_dir : &Path, 
+    path : &str) -> Option<PathBuf> {
+    
+    let path = include_dir.join(path);
+    if has_wildcards(&path) {
+        let mut dir = PathBuf::new();
+        for component in path.components() {
+            if has_wildcards(Path::new(component.as_os_str())) {
+                break;
+            }
+            dir.push(component.as_os_str());
+        }
+        Some(dir)
+    } else if fs.is_dir(&path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+// Return true if path contains wildcard characters.
+fn has_wildcards(path : &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+// Return true if file name starts with dot.
+fn is_hidden(path : &Path) -> bool {
+    path.file_name().map(|name| name.to_string_lossy().starts_with('.'))
+        .unwrap_or(false)
+}
+
+// Expand path with wildcards component by component.
+fn expand_wildcards(fs : &dyn ConfigFs, path : &Path) 
+    -> io::Result<Vec<PathBuf>> {
+    
+    let mut result = vec![PathBuf::new()];
+
+    for component in path.components() {
+        let pattern = {
+            match component {
+                Component::Normal(pattern) => { pattern.to_string_lossy() },
+                component => {
+                    for path in result.iter_mut() {
+                        path.push(component.as_os_str());
+                    }
+                    continue;
+                }
+            }
+        };
+
+        if !has_wildcards(Path::new(pattern.as_ref())) {
+            for path in result.iter_mut() {
+                path.push(pattern.as_ref());
+            }
+            continue;
+        }
+
+        let mut matched = Vec::new();
+        for dir in result.iter() {
+            let entries = {
+                let dir = {
+                    if dir.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        dir.as_path()
+                    }
+                };
+
+                match fs.list(dir) {
+                    Ok(entries) => { entries },
+                    Err(_) => { continue; }
+                }
+            };
+
+            for entry in entries {
+                let name = {
+                    match entry.file_name() {
+                        Some(name) => { name.to_string_lossy().into_owned() },
+                        None => { continue; }
+                    }
+                };
+                if name.starts_with('.') && !pattern.starts_with('.') {
+                    continue;
+                }
+
+                if wildcard_match(pattern.as_bytes(), name.as_bytes()) {
+                    matched.push(dir.join(name));
+                }
+            }
+        }
+        matched.sort();
+        result = matched;
+    }
+    Ok(result)
+}
+
+// Match name against pattern with `*`, `?` and `[...]` wildcards.
+fn wildcard_match(pattern : &[u8], name : &[u8]) -> bool {
+    match pattern.first() {
+        None => { name.is_empty() },
+        Some(b'*') => {
+            (0..=name.len()).any(|skip| {
+                wildcard_match(&pattern[1..], &name[skip..])
+            })
+        },
+        Some(b'?') => {
+            !name.is_empty() && wildcard_match(&pattern[1..], &name[1..])
+        },
+        Some(b'[') => {
+            let end = {
+                match pattern.iter().skip(1).position(|c| *c == b']') {
+                    Some(end) => { end + 1 },
+                    None => { 
+                        return name.first() == Some(&b'[') 
+                            && wildcard_match(&pattern[1..], &name[1..]) 
+                    }
+                }
+            };
+
+            match name.first() {
+                Some(c) => {
+                    let (negate, class) = {
+                        match pattern[1] {
+                            b'!' | b'^' => { (true, &pattern[2..end]) },
+                            _ => { (false, &pattern[1..end]) }
+                        }
+                    };
+                    class_match(class, *c) != negate 
+                        && wildcard_match(&pattern[end + 1..], &name[1..])
+                },
+                None => { false }
+            }
+        },
+        Some(c) => {
+            name.first() == Some(c) 
+                && wildcard_match(&pattern[1..], &name[1..])
+        }
+    }
+}
+
+// Match character against `[...]` class contents.
+fn class_match(class : &[u8], c : u8) -> bool {
+    let mut index = 0;
+    while index < class.len() {
+        if index + 2 < class.len() && class[index + 1] == b'-' {
+            if class[index] <= c && c <= class[index + 2] {
+                return true
+            }
+            index += 3;
+        } else {
+            if class[index] == c {
+                return true
+            }
+            index += 1;
+        }
+    }
+    false
+}
+
+/// Return paths of `@include` directives in configuration text. Strings and
+/// comments are skipped, so their contents are never taken for directives.
+pub(crate) fn include_directives(contents : &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    let mut line_start = true;
+
+    while let Some(character) = contents[pos..].chars().next() {
+        let rest = &contents[pos..];
+        if rest.starts_with('#') || rest.starts_with("//") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            pos += comment.find("*/").map_or(rest.len(), |end| { end + 4 });
+            line_start = false;
+        } else if rest.starts_with('"') {
+            pos += string_literal(rest).1;
+            line_start = false;
+        } else if line_start && rest.starts_with("@include") {
+            let directive = rest["@include".len()..]
+                .trim_start_matches([' ', '\t']);
+            pos = contents.len() - directive.len();
+            if directive.starts_with('"') {
+                let (path, length) = string_literal(directive);
+                result.push(path);
+                pos += length;
+            }
+            line_start = false;
+        } else {
+            if character == '\n' {
+                line_start = true;
+            } else if !character.is_whitespace() {
+                line_start = false;
+            }
+            pos += character.len_utf8();
+        }
+    }
+    result
+}
+
+// Return value of string literal text starts with and length of literal, 
+// unterminated literal spans rest of text.
+fn string_literal(text : &str) -> (String, usize) {
+    let mut value = String::new();
+    let mut escaped = false;
+    for (index, character) in text.char_indices().skip(1) {
+        match character {
+            '\\' if !escaped => { escaped = true; },
+            '"' if !escaped => { return (value, index + 1); },
+            character => {
+                value.push(character);
+                escaped = false;
+            }
+        }
+    }
+    (value, text.len())
+}
+
+/// Walks `@include` directives of configuration before libconfig parses it
+/// to enforce depth limit and detect cycles. Resolved sources are cached, so
+/// resolver is called once per include path.
+pub(crate) struct IncludeChecker<'a> {
+    fs : &'a dyn ConfigFs,
+    resolve : &'a dyn Fn(&str) -> Result<Vec<IncludeSource>>,
+    max_depth : usize,
+    chain : Vec<(String, String)>,
+    cache : IncludeCache,
+    contents : HashMap<PathBuf, String>
+}
+
+impl<'a> IncludeChecker<'a> {
+
+    // Constructor.
+    pub(crate) fn new(fs : &'a dyn ConfigFs, resolve : &'a dyn Fn(&str) -> 
+        Result<Vec<IncludeSource>>, max_depth : usize) -> IncludeChecker<'a> {
+        
+        IncludeChecker {
+            fs,
+            resolve,
+            max_depth,
+            chain : Vec::new(),
+            cache : HashMap::new(),
+            contents : HashMap::new()
+        }
+    }
+
+    // Check includes of configuration and return resolved sources and 
+    // contents of included files.
+    pub(crate) fn check(mut self, name : &str, identity : &str, 
+        contents : &str) -> Result<(IncludeCache, HashMap<PathBuf, String>)> {
+        
+        self.chain.push((name.to_string(), identity.to_string()));
+        self.check_contents(contents)?;
+        Ok((self.cache, self.contents))
+    }
+
+    // Recursively check directives of contents at the end of include chain.
+    fn check_contents(&mut self, contents : &str) -> Result<()> {
+        for path in include_directives(contents) {
+            let sources = {
+                match self.cache.get(&path) {
+                    Some(sources) => { sources.clone() },
+                    None => {
+                        let sources = (self.resolve)(&path).map_err(|error| {
+                            self.error(&path, &match error {
+                                Errors::IncludeError(message) => { message },
+                                error => { format!("{:?}", error) }
+                            })
+                        })?;
+                        self.cache.insert(path.clone(), sources.clone());
+                        sources
+                    }
+                }
+            };
+
+            for source in sources {
+                let identity = source.identity(self.fs);
+                if self.chain.iter().any(|(_, id)| *id == identity) {
+                    return Err(self.error(&path, &format!(
+                        "include cycle through {}", source.display_name())));
+                }
+
+                if self.chain.len() > self.max_depth {
+                    return Err(self.error(&path, &format!(
+                        "include depth limit {} exceeded", self.max_depth)));
+                }
+
+                let contents = self.read(&source).map_err(|error| {
+                    self.error(&path, &format!("{}: {}", 
+                        source.display_name(), error))
+                })?;
+
+                self.chain.push((source.display_name(), identity));
+                self.check_contents(&contents)?;
+                self.chain.pop();
+            }
+        }
+        Ok(())
+    }
+
+    // Return source contents, file is read once and its contents are kept.
+    fn read(&mut self, source : &IncludeSource) -> io::Result<String> {
+        match source {
+            IncludeSource::File(path) => {
+                if let Some(contents) = self.contents.get(path) {
+                    return Ok(contents.clone());
+                }
+                let contents = self.fs.read(path)?;
+                self.contents.insert(path.clone(), contents.clone());
+                Ok(contents)
+            },
+            IncludeSource::Memory { contents, .. } => { Ok(contents.clone()) }
+        }
+    }
+
+    // Make include error naming the include chain.
+    fn error(&self, path : &str, message : &str) -> Errors {
+        let chain = self.chain.iter().map(|(name, _)| name.as_str())
+            .collect::<Vec<&str>>().join(" -> ");
+        Errors::IncludeError(format!("@include \"{}\" in {}: {}", path, 
+            chain, message))
+    }
+}
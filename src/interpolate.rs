@@ -0,0 +1,463 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{Config, Errors, Result};
+use crate::merge::{self, MergePolicy, Merger, Setting};
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::{CStr, CString};
+
+/// Environment variable lookup function.
+type EnvLookup = dyn Fn(&str) -> Option<String>;
+
+/// Resolution pass expanding references in string values. `${path}` is 
+/// replaced by value of setting at path, `${env:NAME}` by value of 
+/// environment variable and `$${` by `${`. String consisting of single 
+/// reference takes type and value of referenced setting, so `port = "${base}"`
+/// becomes integer if `base` is integer and groups and collections can be 
+/// copied; collection elements keep their type and can't take groups or 
+/// collections. Reference cycles and unresolved references are reported with
+/// file and line of the string.
+pub struct Interpolator {
+    env : Box<EnvLookup>
+}
+
+/// Resolved string value.
+#[derive(Clone)]
+enum Resolved {
+    Text(String),
+    Setting(Setting)
+}
+
+/// Part of string value.
+enum Part<'a> {
+    Text(&'a str),
+    Reference(&'a str)
+}
+
+/// State of resolution pass.
+struct Resolution<'a> {
+    root : Setting,
+    env : &'a EnvLookup,
+    resolved : HashMap<usize, Resolved>,
+    failed : HashSet<usize>,
+    stack : Vec<Setting>,
+    errors : Vec<String>
+}
+
+impl Interpolator {
+
+    /// Constructor.
+    /// Create interpolator reading process environment variables.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::interpolate::Interpolator;
+    /// 
+    /// let interpolator = Interpolator::new();
+    /// ```
+    pub fn new() -> Interpolator {
+        Interpolator::with_env(|name| { env::var(name).ok() })
+    }
+
+    /// Constructor.
+    /// Create interpolator with environment variables lookup function.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::interpolate::Interpolator;
+    /// 
+    /// let interpolator = Interpolator::with_env(|name| {
+    ///     match name {
+    ///         "HOME" => { Some(String::from("/home/app")) },
+    ///         _ => { None }
+    ///     }
+    /// });
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("cache = \"${env:HOME}/.cache\";").unwrap();
+    /// interpolator.apply(&mut cfg).unwrap();
+    /// assert_eq!(cfg.value("cache").unwrap().as_string().unwrap(), 
+    ///     "/home/app/.cache");
+    /// ```
+    pub fn with_env<F>(env : F) -> Interpolator 
+        where F: Fn(&str) -> Option<String> + 'static {
+        Interpolator {
+            env : Box::new(env)
+        }
+    }
+
+    /// Expand references in string values of config. Config is left 
+    /// unchanged if any reference can't be resolved, error lists all of them.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, OptionType};
+    /// use librustconfig::interpolate::Interpolator;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(r#"
+    ///     default_port = 8080;
+    ///     port = "${default_port}";"#).unwrap();
+    /// Interpolator::new().apply(&mut cfg).unwrap();
+    /// assert_eq!(cfg.value("port").unwrap().value_type(), 
+    ///     Some(OptionType::IntegerType));
+    /// ```
+    pub fn apply(&self, config : &mut Config) -> Result<()> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        
+        let mut strings = Vec::new();
+        collect_strings(root, &mut strings);
+
+        let mut resolution = Resolution {
+            root,
+            env : &*self.env,
+            resolved : HashMap::new(),
+            failed : HashSet::new(),
+            stack : Vec::new(),
+            errors : Vec::new()
+        };
+        for setting in &strings {
+            resolution.resolve_string(*setting);
+        }
+        
+        let mut texts = Vec::new();
+        let mut settings = Vec::new();
+        for setting in strings {
+            match resolution.resolved.get(&(setting as usize)).cloned() {
+                Some(Resolved::Text(text)) => { texts.push((setting, text)); },
+                Some(Resolved::Setting(value)) if !is_collection(
+                    raw::config_setting_parent(setting)) => {
+                    settings.push((setting, value));
+                },
+                Some(Resolved::Setting(value)) => {
+                    match format_scalar(value) {
+                        Some(text) => { texts.push((setting, text)); },
+                        None => {
+                            resolution.errors.push(format!(
+                                "{}: can't substitute {} into collection \
+                                element", merge::setting_location(setting), 
+                                merge::setting_path(value)));
+                        }
+                    }
+                },
+                None => {}
+            }
+        }
+
+        if !resolution.errors.is_empty() {
+            return Err(Errors::ReferenceError(resolution.errors.join("\n")));
+        }
+
+        for (setting, text) in texts {
+            let text = CString::new(text).unwrap_or_default();
+            unsafe { raw::config_setting_set_string(setting, text.as_ptr()); }
+        }
+        write_settings(root, settings);
+        Ok(())
+    }
+}
+
+impl Default for Interpolator {
+    fn default() -> Interpolator {
+        Interpolator::new()
+    }
+}
+
+impl<'a> Resolution<'a> {
+
+    // Resolve references of string setting, return None if it can't be 
+    // resolved.
+    fn resolve_string(&mut self, setting : Setting) -> Option<Resolved> {
+        let key = setting as usize;
+        if self.failed.contains(&key) {
+            return None;
+        }
+        if let Some(value) = self.resolved.get(&key) {
+            return Some(value.clone());
+        }
+
+        if let Some(position) = self.stack.iter().position(|item| { 
+            *item == setting 
+        }) {
+            let cycle : Vec<String> = self.stack[position..].iter()
+                .chain(std::iter::once(&setting))
+                .map(|item| { merge::setting_path(*item) }).collect();
+            self.errors.push(format!("{}: reference cycle {}", 
+                merge::setting_location(setting), cycle.join(" -> ")));
+            for item in &self.stack[position..] {
+                self.failed.insert(*item as usize);
+            }
+            return None;
+        }
+
+        let text = unsafe { 
+            CStr::from_ptr(raw::config_setting_get_string(setting)) 
+        }.to_string_lossy().into_owned();
+        
+        let parts = {
+            match parse(&text) {
+                Ok(parts) => { parts },
+                Err(message) => {
+                    self.errors.push(format!("{}: {}", 
+                        merge::setting_location(setting), message));
+                    self.failed.insert(key);
+                    return None;
+                }
+            }
+        };
+
+        self.stack.push(setting);
+        let result = {
+            match parts.as_slice() {
+                [Part::Reference(name)] => {
+                    self.resolve_reference(setting, name, true)
+                },
+                _ => {
+                    let mut value = String::new();
+                    let mut complete = true;
+                    for part in &parts {
+                        match part {
+                            Part::Text(text) => { value.push_str(text); },
+                            Part::Reference(name) => {
+                                match self.resolve_reference(setting, name, 
+                                    false) {
+                                    Some(Resolved::Text(text)) => { 
+                                        value.push_str(&text); 
+                                    },
+                                    _ => { complete = false; }
+                                }
+                            }
+                        }
+                    }
+
+                    if complete { Some(Resolved::Text(value)) } else { None }
+                }
+            }
+        };
+        self.stack.pop();
+
+        match &result {
+            Some(value) => { self.resolved.insert(key, value.clone()); },
+            None => { self.failed.insert(key); }
+        }
+        result
+    }
+
+    // Resolve reference found in string setting. Return text or setting 
+    // whole value reference is replaced by.
+    fn resolve_reference(&mut self, setting : Setting, name : &str, 
+        whole : bool) -> Option<Resolved> {
+        
+        if let Some(variable) = name.strip_prefix("env:") {
+            return match (self.env)(variable) {
+                Some(value) => { Some(Resolved::Text(value)) },
+                None => {
+                    self.errors.push(format!(
+                        "{}: unresolved reference ${{{}}}", 
+                        merge::setting_location(setting), name));
+                    None
+                }
+            };
+        }
+
+        let path = CString::new(name).unwrap_or_default();
+        let target = unsafe { 
+            raw::config_setting_lookup(self.root, path.as_ptr()) 
+        };
+        if target.is_null() || target == self.root {
+            self.errors.push(format!("{}: unresolved reference ${{{}}}", 
+                merge::setting_location(setting), name));
+            return None;
+        }
+
+        let value = {
+            match merge::setting_type(target) {
+                raw::CONFIG_TYPE_STRING => {
+                    self.resolve_string(target)?
+                },
+                raw::CONFIG_TYPE_GROUP | raw::CONFIG_TYPE_ARRAY | 
+                raw::CONFIG_TYPE_LIST => {
+                    self.stack.push(target);
+                    let mut strings = Vec::new();
+                    collect_strings(target, &mut strings);
+                    let resolved = strings.into_iter().all(|item| { 
+                        self.resolve_string(item).is_some() 
+                    });
+                    self.stack.pop();
+
+                    if !resolved {
+                        return None;
+                    }
+                    Resolved::Setting(target)
+                },
+                _ => { Resolved::Setting(target) }
+            }
+        };
+
+        match value {
+            Resolved::Setting(value) if !whole => {
+                match format_scalar(value) {
+                    Some(text) => { Some(Resolved::Text(text)) },
+                    None => {
+                        self.errors.push(format!(
+                            "{}: can't insert {} into string", 
+                            merge::setting_location(setting), name));
+                        None
+                    }
+                }
+            },
+            value => { Some(value) }
+        }
+    }
+}
+
+// Split string value into text and references.
+fn parse(text : &str) -> std::result::Result<Vec<Part<'_>>, String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+
+    while let Some(position) = rest.find("${") {
+        if rest[..position].ends_with('$') {
+            parts.push(Part::Text(&rest[..position - 1]));
+            parts.push(Part::Text("${"));
+            rest = &rest[position + 2..];
+            continue;
+        }
+
+        if position > 0 {
+            parts.push(Part::Text(&rest[..position]));
+        }
+        let end = rest[position..].find('}').ok_or_else(|| {
+            format!("unterminated reference in {:?}", text)
+        })?;
+        let name = rest[position + 2..position + end].trim();
+        if name.is_empty() {
+            return Err(format!("empty reference in {:?}", text));
+        }
+
+        parts.push(Part::Reference(name));
+        rest = &rest[position + end + 1..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+// Collect string settings of setting tree.
+fn collect_strings(setting : Setting, strings : &mut Vec<Setting>) {
+    match merge::setting_type(setting) {
+        raw::CONFIG_TYPE_STRING => { strings.push(setting); },
+        raw::CONFIG_TYPE_GROUP | raw::CONFIG_TYPE_ARRAY | 
+        raw::CONFIG_TYPE_LIST => {
+            for index in 0..merge::length(setting) {
+                collect_strings(merge::element(setting, index), strings);
+            }
+        },
+        _ => {}
+    }
+}
+
+// Return true if setting is array or list.
+fn is_collection(setting : Setting) -> bool {
+    !setting.is_null() && matches!(merge::setting_type(setting), 
+        raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST)
+}
+
+// Format scalar setting value as string.
+fn format_scalar(setting : Setting) -> Option<String> {
+    unsafe {
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_INT => { 
+                Some(raw::config_setting_get_int(setting).to_string()) 
+            },
+            raw::CONFIG_TYPE_INT64 => { 
+                Some(raw::config_setting_get_int64(setting).to_string()) 
+            },
+            raw::CONFIG_TYPE_FLOAT => { 
+                Some(format!("{:?}", raw::config_setting_get_float(setting))) 
+            },
+            raw::CONFIG_TYPE_BOOL => { 
+                Some((raw::config_setting_get_bool(setting) != 0).to_string()) 
+            },
+            _ => { None }
+        }
+    }
+}
+
+// Replace string settings by copies of referenced settings in place, keeping
+// their position and state. Settings are addressed by path, as moving the
+// following siblings aside recreates them. Settings containing other 
+// replaced strings are copied after them.
+fn write_settings(root : Setting, settings : Vec<(Setting, Setting)>) {
+    let mut settings : Vec<(String, String)> = settings.into_iter()
+        .map(|(target, source)| { 
+            (merge::setting_path(target), merge::setting_path(source)) 
+        }).collect();
+
+    while !settings.is_empty() {
+        let index = settings.iter().position(|(_, source)| {
+            !settings.iter().any(|(target, _)| { within(target, source) })
+        }).unwrap_or(0);
+        
+        let (target, path) = settings.remove(index);
+        let (target, source) = (lookup(root, &target), lookup(root, &path));
+        if target.is_null() || source.is_null() {
+            continue;
+        }
+
+        let parent = raw::config_setting_parent(target);
+        let name = CString::new(merge::setting_name(target))
+            .unwrap_or_default();
+        let position = unsafe { raw::config_setting_index(target) } as u32;
+        let setting = merge::add_at(parent, position, &name, 
+            merge::setting_type(source), true);
+        if !setting.is_null() {
+            Merger::new(MergePolicy::default(), &mut |_, _| {})
+                .assign(setting, lookup(root, &path));
+        }
+    }
+}
+
+// Find setting by path, return null if it doesn't exist.
+fn lookup(root : Setting, path : &str) -> Setting {
+    let path = CString::new(path).unwrap_or_default();
+    unsafe { raw::config_setting_lookup(root, path.as_ptr()) }
+}
+
+// Return true if setting path is inside tree of ancestor path.
+fn within(path : &str, ancestor : &str) -> bool {
+    path.strip_prefix(ancestor).is_some_and(|rest| { rest.starts_with('.') })
+}
@@ -0,0 +1,572 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, OptionReader, Result};
+use crate::merge::{self, Merger, MergePolicy, Setting};
+use crate::schema;
+use crate::secret;
+
+use serde_json::{Map, Number, Value};
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+
+// Key of plain mode object holding 64bit integer.
+const INT64_KEY : &str = "$int64";
+
+/// JSON representation of config tree.
+/// 
+/// In `Plain` mode groups are JSON objects with members in config order, 
+/// arrays and lists are JSON arrays and scalars are JSON values. 64bit 
+/// integers are objects `{ "$int64": "9000000000" }` holding decimal 
+/// string, so they keep their type and precision in JavaScript. Plain mode 
+/// is lossy: hex format is dropped and arrays and lists are not 
+/// distinguished, on load JSON array of scalars of one type becomes array 
+/// and any other JSON array becomes list. Integer numbers not fitting 32bit 
+/// integer are loaded as 64bit integers.
+/// 
+/// In `Lossless` mode every setting is object tagged with its libconfig 
+/// type: `{ "type": "int", "value": 255, "format": "hex" }`. Types are 
+/// `int`, `int64`, `float`, `bool`, `string`, `group`, `array` and `list`, 
+/// `int64` value is decimal string, `group` value is object of tagged 
+/// members, `array` and `list` values are arrays of tagged elements. 
+/// Optional `format` is `hex` for integers written in hexadecimal. Saving 
+/// config loaded from lossless JSON produces the same text as saving 
+/// original config. Secret references `secret:<provider>:<name>` are kept in
+/// lossless mode, but values read by `OptionReader::as_secret` which are not
+/// references are redacted unless `Config::set_export_secrets` is set.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JsonMode {
+    Plain,
+    Lossless
+}
+
+impl Config {
+
+    /// Convert config tree to JSON value. Floats which are not finite can't 
+    /// be represented in JSON and are reported as `JsonError`. Secret values
+    /// are redacted unless `Config::set_export_secrets` is set, see 
+    /// `JsonMode` for lossless mode.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::json::JsonMode;
+    /// use serde_json::json;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 8080; size = 5000000000L;").unwrap();
+    /// assert_eq!(cfg.to_json_value(JsonMode::Plain).unwrap(), 
+    ///     json!({ "port": 8080, "size": { "$int64": "5000000000" } }));
+    /// ```
+    pub fn to_json_value(&self, mode : JsonMode) -> Result<Value> {
+        match self.root_setting() {
+            Some(root) => { to_value(root, "", mode, self.redacts_exports()) },
+            None => { Ok(Value::Object(Map::new())) }
+        }
+    }
+
+    /// Save config tree as pretty printed JSON string.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::json::JsonMode;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("mask = 0xFF;").unwrap();
+    /// assert_eq!(cfg.save_to_json(JsonMode::Lossless).unwrap(), concat!(
+    ///     "{\n",
+    ///     "  \"type\": \"group\",\n",
+    ///     "  \"value\": {\n",
+    ///     "    \"mask\": {\n",
+    ///     "      \"type\": \"int\",\n",
+    ///     "      \"value\": 255,\n",
+    ///     "      \"format\": \"hex\"\n",
+    ///     "    }\n",
+    ///     "  }\n",
+    ///     "}"));
+    /// ```
+    pub fn save_to_json(&self, mode : JsonMode) -> Result<String> {
+        let value = self.to_json_value(mode)?;
+        serde_json::to_string_pretty(&value).map_err(|error| {
+            Errors::JsonError(error.to_string())
+        })
+    }
+
+    /// Replace config tree with settings of JSON value. Root value has to be 
+    /// object, config is left unchanged if value can't be converted.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::json::JsonMode;
+    /// use serde_json::json;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_json_value(&json!({ 
+    ///     "name": "app", "ports": [ 80, 443 ], "mixed": [ 1, "a" ] 
+    /// }), JsonMode::Plain).unwrap();
+    /// assert_eq!(cfg.save_to_string().unwrap(), 
+    ///     "name = \"app\";\nports = [ 80, 443 ];\nmixed = ( 1, \"a\" );\n");
+    /// ```
+    pub fn load_from_json_value(&mut self, value : &Value, mode : JsonMode) 
+        -> Result<()> {
+        
+        let scratch = self.empty_copy();
+        let root = scratch.root_setting().ok_or(Errors::ElementNotExists)?;
+        match mode {
+            JsonMode::Plain => {
+                if !value.is_object() || int64_marker("", value)?.is_some() {
+                    return Err(error("", "expected object"));
+                }
+                add_members(root, "", value, add_plain)?;
+            },
+            JsonMode::Lossless => {
+                let (kind, members) = tagged("", value)?;
+                if kind != raw::CONFIG_TYPE_GROUP {
+                    return Err(error("", "expected group"));
+                }
+                add_members(root, "", members, add_tagged)?;
+            }
+        }
+
+        self.load_from_string("")?;
+        let target = self.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |_, _| {})
+            .merge_group(target, root);
+        Ok(())
+    }
+
+    /// Replace config tree with settings of JSON string.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// use librustconfig::json::JsonMode;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_json(r#"{
+    ///     "type": "group", 
+    ///     "value": { "ids": { "type": "list", "value": [
+    ///         { "type": "int64", "value": "1" }
+    ///     ] } }
+    /// }"#, JsonMode::Lossless).unwrap();
+    /// assert_eq!(cfg.save_to_string().unwrap(), "ids = ( 1L );\n");
+    /// 
+    /// assert_eq!(cfg.load_from_json(r#"{ "a": null }"#, JsonMode::Plain), 
+    ///     Err(Errors::JsonError(String::from(
+    ///         "a: null can't be represented in libconfig"))));
+    /// ```
+    pub fn load_from_json<S>(&mut self, json : S, mode : JsonMode) 
+        -> Result<()> where S: Into<String> {
+        
+        let value : Value = serde_json::from_str(&json.into())
+            .map_err(|error| { Errors::JsonError(error.to_string()) })?;
+        self.load_from_json_value(&value, mode)
+    }
+}
+
+// Convert setting to JSON value, secret values are replaced if redact is set.
+fn to_value(setting : Setting, path : &str, mode : JsonMode, redact : bool) 
+    -> Result<Value> {
+    
+    let kind = merge::setting_type(setting);
+    let value = unsafe {
+        match kind {
+            raw::CONFIG_TYPE_GROUP => {
+                let mut members = Map::new();
+                for index in 0..merge::length(setting) {
+                    let member = merge::element(setting, index);
+                    let name = merge::setting_name(member);
+                    let value = to_value(member, 
+                        &schema::join_path(path, &name), mode, redact)?;
+                    members.insert(name, value);
+                }
+                Value::Object(members)
+            },
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                let elements : Result<Vec<Value>> = (0..merge::length(setting))
+                    .map(|index| {
+                        to_value(merge::element(setting, index), 
+                            &element_path(path, index as usize), mode, redact)
+                    }).collect();
+                Value::Array(elements?)
+            },
+            raw::CONFIG_TYPE_INT => {
+                Value::from(raw::config_setting_get_int(setting))
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                let value = raw::config_setting_get_int64(setting).to_string();
+                match mode {
+                    JsonMode::Plain => {
+                        let mut marker = Map::new();
+                        marker.insert(String::from(INT64_KEY), 
+                            Value::String(value));
+                        Value::Object(marker)
+                    },
+                    JsonMode::Lossless => { Value::String(value) }
+                }
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                let value = raw::config_setting_get_float(setting);
+                Number::from_f64(value).map(Value::Number).ok_or_else(|| {
+                    error(path, &format!("float {} can't be represented in \
+                        JSON", value))
+                })?
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                Value::Bool(raw::config_setting_get_bool(setting) != 0)
+            },
+            _ if redact && config::is_secret(setting) && 
+                !(mode == JsonMode::Lossless && reference(setting)) => {
+                Value::from(secret::REDACTED)
+            },
+            _ => {
+                let value = raw::config_setting_get_string(setting);
+                if value.is_null() {
+                    Value::String(String::new())
+                } else {
+                    Value::String(CStr::from_ptr(value).to_string_lossy()
+                        .into_owned())
+                }
+            }
+        }
+    };
+
+    match mode {
+        JsonMode::Plain => { Ok(value) },
+        JsonMode::Lossless => {
+            let mut tagged = tag(kind, value);
+            if unsafe { raw::config_setting_get_format(setting) } == 
+                raw::CONFIG_FORMAT_HEX as i16 && (kind == raw::CONFIG_TYPE_INT 
+                || kind == raw::CONFIG_TYPE_INT64) {
+                tagged["format"] = Value::from("hex");
+            }
+            Ok(tagged)
+        }
+    }
+}
+
+// Return true if string setting refers to secret.
+fn reference(setting : Setting) -> bool {
+    OptionReader::new(Some(setting)).as_string().is_some_and(|value| {
+        value.starts_with(secret::SECRET_PREFIX)
+    })
+}
+
+// Return lossless mode object holding value of libconfig type, 64bit 
+// integer value has to be decimal string.
+pub(crate) fn tag(kind : i16, value : Value) -> Value {
+    let mut tagged = Map::new();
+    tagged.insert(String::from("type"), Value::from(type_tag(kind)));
+    tagged.insert(String::from("value"), value);
+    Value::Object(tagged)
+}
+
+// Add setting holding plain JSON value to parent.
+fn add_plain(parent : Setting, name : &CStr, path : &str, value : &Value) 
+    -> Result<()> {
+    
+    let kind = plain_type(path, value)?;
+    let setting = add(parent, name, path, kind)?;
+    match kind {
+        raw::CONFIG_TYPE_GROUP => { 
+            add_members(setting, path, value, add_plain) 
+        },
+        raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => { 
+            add_elements(setting, path, value, add_plain) 
+        },
+        _ => { set_scalar(setting, path, kind, value) }
+    }
+}
+
+// Add setting holding tagged JSON value to parent.
+fn add_tagged(parent : Setting, name : &CStr, path : &str, value : &Value) 
+    -> Result<()> {
+    
+    let (kind, inner) = tagged(path, value)?;
+    let setting = add(parent, name, path, kind)?;
+    match kind {
+        raw::CONFIG_TYPE_GROUP => { 
+            add_members(setting, path, inner, add_tagged)?; 
+        },
+        raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => { 
+            add_elements(setting, path, inner, add_tagged)?; 
+        },
+        _ => { set_scalar(setting, path, kind, inner)?; }
+    }
+
+    match value.get("format") {
+        None => { Ok(()) },
+        Some(Value::String(format)) if format == "hex" && matches!(kind, 
+            raw::CONFIG_TYPE_INT | raw::CONFIG_TYPE_INT64) => {
+            unsafe {
+                raw::config_setting_set_format(setting, 
+                    raw::CONFIG_FORMAT_HEX as i16);
+            }
+            Ok(())
+        },
+        Some(_) => { Err(error(path, "unsupported format")) }
+    }
+}
+
+// Add members of JSON object to group.
+fn add_members<F>(group : Setting, path : &str, value : &Value, add_value : F) 
+    -> Result<()> where F: Fn(Setting, &CStr, &str, &Value) -> Result<()> {
+    
+    let members = value.as_object().ok_or_else(|| { 
+        error(path, "expected object") 
+    })?;
+    for (name, member) in members {
+        let member_path = schema::join_path(path, name);
+        let name = CString::new(name.as_str()).map_err(|_| { 
+            error(&member_path, "invalid setting name")
+        })?;
+        add_value(group, &name, &member_path, member)?;
+    }
+    Ok(())
+}
+
+// Add elements of JSON array to collection.
+fn add_elements<F>(collection : Setting, path : &str, value : &Value, 
+    add_value : F) -> Result<()> 
+    where F: Fn(Setting, &CStr, &str, &Value) -> Result<()> {
+    
+    let elements = value.as_array().ok_or_else(|| { 
+        error(path, "expected array") 
+    })?;
+    let empty = CString::default();
+    for (index, element) in elements.iter().enumerate() {
+        add_value(collection, &empty, &element_path(path, index), element)?;
+    }
+    Ok(())
+}
+
+// Add setting of type to parent.
+fn add(parent : Setting, name : &CStr, path : &str, kind : i16) 
+    -> Result<Setting> {
+    
+    let setting = unsafe { 
+        raw::config_setting_add(parent, name.as_ptr(), kind as i32) 
+    };
+    if !setting.is_null() {
+        Ok(setting)
+    } else if merge::setting_type(parent) == raw::CONFIG_TYPE_ARRAY {
+        Err(error(path, "array elements must be scalars of one type"))
+    } else {
+        Err(error(path, "invalid setting name"))
+    }
+}
+
+// Set scalar setting value.
+fn set_scalar(setting : Setting, path : &str, kind : i16, value : &Value) 
+    -> Result<()> {
+    
+    let mismatch = || { 
+        error(path, &format!("expected {} value", schema::type_name(kind))) 
+    };
+    let result = unsafe {
+        match kind {
+            raw::CONFIG_TYPE_INT => {
+                let value = value.as_i64()
+                    .and_then(|value| { i32::try_from(value).ok() })
+                    .ok_or_else(mismatch)?;
+                raw::config_setting_set_int(setting, value)
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                let value = match value {
+                    Value::String(text) => { text.parse().ok() },
+                    Value::Object(_) => { int64_marker(path, value)? },
+                    value => { value.as_i64() }
+                }.ok_or_else(mismatch)?;
+                raw::config_setting_set_int64(setting, value)
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                let value = value.as_f64().ok_or_else(mismatch)?;
+                raw::config_setting_set_float(setting, value)
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                let value = value.as_bool().ok_or_else(mismatch)?;
+                raw::config_setting_set_bool(setting, value as i32)
+            },
+            _ => {
+                let value = value.as_str().ok_or_else(mismatch)?;
+                let value = CString::new(value).map_err(|_| { 
+                    error(path, "string contains nul character") 
+                })?;
+                raw::config_setting_set_string(setting, value.as_ptr())
+            }
+        }
+    };
+
+    if result == raw::CONFIG_TRUE {
+        Ok(())
+    } else {
+        Err(mismatch())
+    }
+}
+
+// Return libconfig type of plain JSON value.
+fn plain_type(path : &str, value : &Value) -> Result<i16> {
+    match value {
+        Value::Null => { 
+            Err(error(path, "null can't be represented in libconfig")) 
+        },
+        Value::Bool(_) => { Ok(raw::CONFIG_TYPE_BOOL) },
+        Value::String(_) => { Ok(raw::CONFIG_TYPE_STRING) },
+        Value::Number(number) => {
+            match number.as_i64() {
+                Some(value) => { Ok(integer_type(value)) },
+                None if number.is_f64() => { Ok(raw::CONFIG_TYPE_FLOAT) },
+                None => { Err(error(path, "integer exceeds 64bit range")) }
+            }
+        },
+        Value::Object(_) => {
+            match int64_marker(path, value)? {
+                Some(_) => { Ok(raw::CONFIG_TYPE_INT64) },
+                None => { Ok(raw::CONFIG_TYPE_GROUP) }
+            }
+        },
+        Value::Array(elements) => {
+            let mut kinds = Vec::new();
+            for (index, element) in elements.iter().enumerate() {
+                kinds.push(match element {
+                    Value::Array(_) => { raw::CONFIG_TYPE_LIST },
+                    element => { 
+                        plain_type(&element_path(path, index), element)? 
+                    }
+                });
+            }
+            Ok(collection_type(&kinds))
+        }
+    }
+}
+
+// Return libconfig type of integer, 32bit integer if value fits it.
+pub(crate) fn integer_type(value : i64) -> i16 {
+    if i32::try_from(value).is_ok() {
+        raw::CONFIG_TYPE_INT
+    } else {
+        raw::CONFIG_TYPE_INT64
+    }
+}
+
+// Return libconfig type of collection holding elements of kinds, array if 
+// elements are scalars of one type, otherwise list.
+pub(crate) fn collection_type(kinds : &[i16]) -> i16 {
+    let scalar = match kinds.first() {
+        Some(first) => {
+            *first != raw::CONFIG_TYPE_GROUP && 
+                *first != raw::CONFIG_TYPE_ARRAY && 
+                *first != raw::CONFIG_TYPE_LIST && 
+                kinds.iter().all(|kind| { kind == first })
+        },
+        None => { true }
+    };
+    if scalar {
+        raw::CONFIG_TYPE_ARRAY
+    } else {
+        raw::CONFIG_TYPE_LIST
+    }
+}
+
+// Return 64bit integer held by plain mode object, None if value is not 
+// such object.
+fn int64_marker(path : &str, value : &Value) -> Result<Option<i64>> {
+    let object = match value.as_object() {
+        Some(object) if object.len() == 1 => { object },
+        _ => { return Ok(None); }
+    };
+
+    match object.get(INT64_KEY) {
+        None => { Ok(None) },
+        Some(Value::String(text)) => {
+            text.parse().map(Some).map_err(|_| { 
+                error(path, "invalid 64bit integer") 
+            })
+        },
+        Some(value) => {
+            value.as_i64().map(Some).ok_or_else(|| { 
+                error(path, "invalid 64bit integer") 
+            })
+        }
+    }
+}
+
+// Return libconfig type and value of tagged JSON value.
+fn tagged<'a>(path : &str, value : &'a Value) -> Result<(i16, &'a Value)> {
+    let tag = value.get("type").ok_or_else(|| { 
+        error(path, "missing type") 
+    })?;
+    let kind = match tag.as_str().unwrap_or_default() {
+        "int" => { raw::CONFIG_TYPE_INT },
+        "int64" => { raw::CONFIG_TYPE_INT64 },
+        "float" => { raw::CONFIG_TYPE_FLOAT },
+        "bool" => { raw::CONFIG_TYPE_BOOL },
+        "string" => { raw::CONFIG_TYPE_STRING },
+        "group" => { raw::CONFIG_TYPE_GROUP },
+        "array" => { raw::CONFIG_TYPE_ARRAY },
+        "list" => { raw::CONFIG_TYPE_LIST },
+        _ => { return Err(error(path, &format!("unknown type {}", tag))); }
+    };
+    let value = value.get("value").ok_or_else(|| { 
+        error(path, "missing value") 
+    })?;
+    Ok((kind, value))
+}
+
+// Return lossless mode tag of libconfig type.
+fn type_tag(kind : i16) -> &'static str {
+    match kind {
+        raw::CONFIG_TYPE_INT => { "int" },
+        raw::CONFIG_TYPE_INT64 => { "int64" },
+        raw::CONFIG_TYPE_FLOAT => { "float" },
+        raw::CONFIG_TYPE_BOOL => { "bool" },
+        raw::CONFIG_TYPE_STRING => { "string" },
+        raw::CONFIG_TYPE_GROUP => { "group" },
+        raw::CONFIG_TYPE_ARRAY => { "array" },
+        _ => { "list" }
+    }
+}
+
+// Return path of collection element.
+pub(crate) fn element_path(path : &str, index : usize) -> String {
+    schema::join_path(path, &format!("[{}]", index))
+}
+
+// Return conversion error at path.
+fn error(path : &str, message : &str) -> Errors {
+    if path.is_empty() {
+        Errors::JsonError(message.to_string())
+    } else {
+        Errors::JsonError(format!("{}: {}", path, message))
+    }
+}
@@ -28,7 +28,33 @@
 /*                                                                            */
 /******************************************************************************/
 
+pub mod cli;
 pub mod config;
+pub mod diff;
+pub mod document;
+pub mod env;
+pub mod include;
+pub mod interpolate;
+pub mod json;
+pub mod merge;
+pub mod patch;
+pub mod query;
+pub mod schema;
+pub mod secret;
+pub mod stack;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod transaction;
+pub mod typed;
+pub mod usage;
+pub mod vfs;
+pub mod visit;
+pub mod watch;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+#[cfg(test)]
+extern crate self as librustconfig;
 
 #[cfg(test)]
 mod test;
\ No newline at end of file
@@ -0,0 +1,545 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, OptionReader};
+
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+/// Merge strategy of arrays and lists.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CollectionMerge {
+    /// Collection is replaced by collection of merged config.
+    Replace,
+    /// Elements of merged collection are appended to collection.
+    Append,
+    /// Elements of merged collection missing in collection are appended.
+    Deduplicate,
+    /// Existing collection is kept.
+    Keep
+}
+
+/// Merge strategy of scalar values.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScalarMerge {
+    /// Value is overwritten by value of merged config.
+    Overwrite,
+    /// Existing value is kept, only missing values are added.
+    Keep,
+    /// Existing settings are kept whatever their type, only missing settings
+    /// are added.
+    Fill
+}
+
+/// Policy of merging one config tree into another.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MergePolicy {
+    pub scalars : ScalarMerge,
+    pub collections : CollectionMerge
+}
+
+impl MergePolicy {
+
+    /// Constructor.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::merge::{CollectionMerge, MergePolicy, ScalarMerge};
+    /// 
+    /// let policy = MergePolicy::new(ScalarMerge::Keep, 
+    ///     CollectionMerge::Deduplicate);
+    /// ```
+    pub fn new(scalars : ScalarMerge, collections : CollectionMerge) 
+        -> MergePolicy {
+        MergePolicy { scalars, collections }
+    }
+}
+
+/// Default policy overwrites scalars and replaces collections.
+impl Default for MergePolicy {
+    fn default() -> MergePolicy {
+        MergePolicy::new(ScalarMerge::Overwrite, CollectionMerge::Replace)
+    }
+}
+
+/// Raw config setting.
+pub(crate) type Setting = *mut raw::config_setting_t;
+
+/// Merges setting trees of libconfig configs.
+pub(crate) struct Merger<'a> {
+    policy : MergePolicy,
+    observer : &'a mut dyn FnMut(Setting, Setting)
+}
+
+impl<'a> Merger<'a> {
+
+    // Constructor. Observer receives each setting created or changed by merge
+    // together with setting it was merged from.
+    pub(crate) fn new(policy : MergePolicy, 
+        observer : &'a mut dyn FnMut(Setting, Setting)) -> Merger<'a> {
+        Merger { policy, observer }
+    }
+
+    // Merge members of source group into target group. Groups are merged 
+    // recursively, collections and scalars are merged by policy, settings of
+    // different types are replaced.
+    pub(crate) fn merge_group(&mut self, target : Setting, source : Setting) {
+        for index in 0..length(source) {
+            let member = element(source, index);
+            let name = unsafe { CStr::from_ptr(raw::config_setting_name(member)) }
+                .to_owned();
+            let existing = unsafe { 
+                raw::config_setting_get_member(target, name.as_ptr()) 
+            };
+
+            if existing.is_null() {
+                self.copy(target, &name, member);
+                continue;
+            }
+
+            let kind = setting_type(existing);
+            if kind != setting_type(member) {
+                if self.policy.scalars != ScalarMerge::Fill {
+                    self.replace(target, &name, member);
+                }
+                continue;
+            }
+
+            match kind {
+                raw::CONFIG_TYPE_GROUP => {
+                    (self.observer)(existing, member);
+                    self.merge_group(existing, member);
+                },
+                raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                    if !self.merge_collection(existing, member) {
+                        self.replace(target, &name, member);
+                    }
+                },
+                _ => {
+                    if self.policy.scalars == ScalarMerge::Overwrite {
+                        copy_value(existing, member);
+                        (self.observer)(existing, member);
+                    }
+                }
+            }
+        }
+    }
+
+    // Assign value of source setting to target setting of the same type. 
+    // Groups are merged, collection elements are appended.
+    pub(crate) fn assign(&mut self, target : Setting, source : Setting) {
+        match setting_type(source) {
+            raw::CONFIG_TYPE_GROUP => {
+                self.merge_group(target, source);
+            },
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                let empty = CString::default();
+                for index in 0..length(source) {
+                    self.copy(target, &empty, element(source, index));
+                }
+            },
+            _ => {
+                copy_value(target, source);
+            }
+        }
+        (self.observer)(target, source);
+    }
+
+    // Merge elements of source collection into target collection, return 
+    // false if collection has to be replaced.
+    fn merge_collection(&mut self, target : Setting, source : Setting) 
+        -> bool {
+        
+        match self.policy.collections {
+            CollectionMerge::Replace => { return false; },
+            CollectionMerge::Keep => { return true; },
+            _ => {}
+        }
+
+        let empty = CString::default();
+        for index in 0..length(source) {
+            let value = element(source, index);
+            if self.policy.collections == CollectionMerge::Deduplicate && 
+                (0..length(target)).any(|item| { 
+                    equal(element(target, item), value) 
+                }) {
+                continue;
+            }
+
+            if self.copy(target, &empty, value).is_null() {
+                return false;
+            }
+        }
+
+        (self.observer)(target, source);
+        true
+    }
+
+    // Replace target setting by copy of source setting.
+    fn replace(&mut self, parent : Setting, name : &CStr, source : Setting) {
+        unsafe {
+            raw::config_setting_remove(parent, name.as_ptr());
+        }
+        self.copy(parent, name, source);
+    }
+
+    // Add copy of source setting to parent, return null if libconfig refuses 
+    // to add it.
+    pub(crate) fn copy(&mut self, parent : Setting, name : &CStr, source : Setting) 
+        -> Setting {
+        
+        let kind = setting_type(source);
+        let setting = unsafe {
+            raw::config_setting_add(parent, name.as_ptr(), kind as i32)
+        };
+        if setting.is_null() {
+            return ptr::null_mut();
+        }
+
+        self.assign(setting, source);
+        setting
+    }
+}
+
+// Find first pair of settings merge can't combine: settings of different 
+// types, unless existing settings are kept by policy, and arrays of different
+// element types.
+pub(crate) fn find_conflict(target : Setting, source : Setting, 
+    policy : MergePolicy) -> Option<(Setting, Setting)> {
+    
+    for index in 0..length(source) {
+        let member = element(source, index);
+        let existing = unsafe { 
+            raw::config_setting_get_member(target, 
+                raw::config_setting_name(member)) 
+        };
+        if existing.is_null() {
+            continue;
+        }
+
+        let kind = setting_type(existing);
+        if kind != setting_type(member) {
+            if policy.scalars == ScalarMerge::Fill {
+                continue;
+            }
+            return Some((existing, member));
+        }
+
+        match kind {
+            raw::CONFIG_TYPE_GROUP => {
+                let conflict = find_conflict(existing, member, policy);
+                if conflict.is_some() {
+                    return conflict;
+                }
+            },
+            raw::CONFIG_TYPE_ARRAY if 
+                (policy.collections == CollectionMerge::Append || 
+                    policy.collections == CollectionMerge::Deduplicate) && 
+                length(existing) > 0 && length(member) > 0 && 
+                setting_type(element(existing, 0)) != 
+                    setting_type(element(member, 0)) => {
+                return Some((element(existing, 0), element(member, 0)));
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+// Find setting at path of names below group, missing groups on the path are
+// created. Setting of different type is replaced by new setting of kind at 
+// its position and keeps its state, collections of the same type are 
+// emptied.
+pub(crate) fn upsert(group : Setting, names : &[String], kind : i16) 
+    -> std::result::Result<Setting, String> {
+    
+    let mut current = group;
+    for (index, name) in names.iter().enumerate() {
+        let last = index + 1 == names.len();
+        let expected = if last { kind } else { raw::CONFIG_TYPE_GROUP };
+        let name = CString::new(name.as_str()).map_err(|_| { 
+            format!("invalid setting name {:?}", name) 
+        })?;
+        let existing = unsafe { 
+            raw::config_setting_get_member(current, name.as_ptr()) 
+        };
+
+        current = {
+            if existing.is_null() {
+                add(current, &name, expected)?
+            } else if !last && setting_type(existing) != expected {
+                return Err(format!("{} is not a group", 
+                    setting_path(existing)));
+            } else if setting_type(existing) != expected {
+                let index = unsafe { raw::config_setting_index(existing) };
+                let setting = add_at(current, index as u32, &name, expected, 
+                    true);
+                if setting.is_null() {
+                    return Err(format!("can't replace {}", 
+                        setting_path(existing)));
+                }
+                setting
+            } else {
+                existing
+            }
+        };
+    }
+
+    if kind == raw::CONFIG_TYPE_ARRAY || kind == raw::CONFIG_TYPE_LIST {
+        while length(current) > 0 {
+            unsafe { raw::config_setting_remove_elem(current, 0); }
+        }
+    }
+    Ok(current)
+}
+
+// Add setting to group.
+fn add(group : Setting, name : &CStr, kind : i16) 
+    -> std::result::Result<Setting, String> {
+    
+    let setting = unsafe { 
+        raw::config_setting_add(group, name.as_ptr(), kind as i32) 
+    };
+    
+    if setting.is_null() {
+        Err(format!("invalid setting name {:?}", name.to_string_lossy()))
+    } else {
+        Ok(setting)
+    }
+}
+
+// Add setting of kind to parent at index and return it, null if libconfig 
+// refuses to add it. If replace is set, setting at index is replaced and its
+// state is moved to added setting. Settings after index are moved aside 
+// with their state and appended back, libconfig can only append settings.
+pub(crate) fn add_at(parent : Setting, index : u32, name : &CStr, kind : i16,
+    replace : bool) -> Setting {
+    
+    let tail = Config::new();
+    let holder = match tail.root_setting() {
+        Some(root) => {
+            let name = CString::new("tail").unwrap_or_default();
+            unsafe { 
+                raw::config_setting_add(root, name.as_ptr(), 
+                    setting_type(parent) as i32) 
+            }
+        },
+        None => { return ptr::null_mut(); }
+    };
+
+    let mut observer = |_, _| {};
+    let mut merger = Merger::new(MergePolicy::default(), &mut observer);
+    while length(parent) > index {
+        let setting = element(parent, index);
+        let name = CString::new(setting_name(setting)).unwrap_or_default();
+        config::move_setting_state(setting, 
+            merger.copy(holder, &name, setting));
+        unsafe { raw::config_setting_remove_elem(parent, index); }
+    }
+
+    let setting = unsafe { 
+        raw::config_setting_add(parent, name.as_ptr(), kind as i32) 
+    };
+    let skip = if replace && !setting.is_null() && length(holder) > 0 {
+        config::move_setting_state(element(holder, 0), setting);
+        1
+    } else {
+        0
+    };
+    for item in skip..length(holder) {
+        let moved = element(holder, item);
+        let name = CString::new(setting_name(moved)).unwrap_or_default();
+        config::move_setting_state(moved, merger.copy(parent, &name, moved));
+    }
+    setting
+}
+
+// Check setting name against libconfig name grammar.
+pub(crate) fn valid_name(name : &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '*' => {
+            chars.all(|c| { 
+                c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '*' 
+            })
+        },
+        _ => { false }
+    }
+}
+
+// Return setting name, collection elements have empty names.
+pub(crate) fn setting_name(setting : Setting) -> String {
+    let name = raw::config_setting_name(setting);
+    if name.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned()
+}
+
+// Return path of setting, collection elements are addressed by index.
+pub(crate) fn setting_path(setting : Setting) -> String {
+    let mut names = Vec::new();
+    let mut current = setting;
+
+    while !raw::config_setting_parent(current).is_null() {
+        let name = raw::config_setting_name(current);
+        if name.is_null() {
+            names.push(format!("[{}]", unsafe { 
+                raw::config_setting_index(current) 
+            }));
+        } else {
+            names.push(unsafe { CStr::from_ptr(name) }.to_string_lossy()
+                .into_owned());
+        }
+        current = raw::config_setting_parent(current);
+    }
+
+    names.reverse();
+    names.join(".")
+}
+
+// Return setting path with file and line setting was read from.
+pub(crate) fn setting_location(setting : Setting) -> String {
+    let path = setting_path(setting);
+    let reader = OptionReader::new(Some(setting));
+
+    match (reader.source_file(), reader.source_line()) {
+        (Some(file), Some(line)) => { format!("{}:{}: {}", file, line, path) },
+        (None, Some(line)) => { format!("line {}: {}", line, path) },
+        _ => { path }
+    }
+}
+
+// Collect settings of setting tree.
+pub(crate) fn collect(setting : Setting, settings : &mut Vec<Setting>) {
+    settings.push(setting);
+    if raw::config_setting_is_aggregate(setting) == raw::CONFIG_TRUE {
+        for index in 0..length(setting) {
+            collect(element(setting, index), settings);
+        }
+    }
+}
+
+// Return setting type.
+pub(crate) fn setting_type(setting : Setting) -> i16 {
+    raw::config_setting_type(setting) as i16
+}
+
+// Return count of aggregate setting elements.
+pub(crate) fn length(setting : Setting) -> u32 {
+    unsafe { raw::config_setting_length(setting) as u32 }
+}
+
+// Return element of aggregate setting.
+pub(crate) fn element(setting : Setting, index : u32) -> Setting {
+    unsafe { raw::config_setting_get_elem(setting, index) }
+}
+
+// Copy scalar value and its format of source setting to target setting of
+// the same type.
+pub(crate) fn copy_value(target : Setting, source : Setting) {
+    unsafe {
+        match setting_type(source) {
+            raw::CONFIG_TYPE_INT => {
+                raw::config_setting_set_int(target, 
+                    raw::config_setting_get_int(source));
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                raw::config_setting_set_int64(target, 
+                    raw::config_setting_get_int64(source));
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                raw::config_setting_set_float(target, 
+                    raw::config_setting_get_float(source));
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                raw::config_setting_set_bool(target, 
+                    raw::config_setting_get_bool(source));
+            },
+            raw::CONFIG_TYPE_STRING => {
+                raw::config_setting_set_string(target, 
+                    raw::config_setting_get_string(source));
+            },
+            _ => {}
+        }
+        raw::config_setting_set_format(target, 
+            raw::config_setting_get_format(source));
+    }
+}
+
+// Compare settings by type and value.
+pub(crate) fn equal(first : Setting, second : Setting) -> bool {
+    let kind = setting_type(first);
+    if kind != setting_type(second) {
+        return false;
+    }
+
+    unsafe {
+        match kind {
+            raw::CONFIG_TYPE_INT => {
+                raw::config_setting_get_int(first) == 
+                    raw::config_setting_get_int(second)
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                raw::config_setting_get_int64(first) == 
+                    raw::config_setting_get_int64(second)
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                raw::config_setting_get_float(first) == 
+                    raw::config_setting_get_float(second)
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                raw::config_setting_get_bool(first) == 
+                    raw::config_setting_get_bool(second)
+            },
+            raw::CONFIG_TYPE_STRING => {
+                CStr::from_ptr(raw::config_setting_get_string(first)) == 
+                    CStr::from_ptr(raw::config_setting_get_string(second))
+            },
+            raw::CONFIG_TYPE_GROUP => {
+                length(first) == length(second) && 
+                    (0..length(first)).all(|index| {
+                        let member = element(first, index);
+                        let other = raw::config_setting_get_member(second,
+                            raw::config_setting_name(member));
+                        !other.is_null() && equal(member, other)
+                    })
+            },
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                length(first) == length(second) && 
+                    (0..length(first)).all(|index| {
+                        equal(element(first, index), element(second, index))
+                    })
+            },
+            _ => { true }
+        }
+    }
+}
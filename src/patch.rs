@@ -0,0 +1,840 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, OptionReader, Result};
+use crate::diff::{self, Change, ChangeKind};
+use crate::merge::{self, Merger, MergePolicy, Setting};
+use crate::schema;
+
+use std::ffi::CString;
+use std::fmt;
+
+/// Patch operation. Paths are libconfig paths with collection elements 
+/// addressed by index, e.g. `servers.[0].host`. Values are written in 
+/// libconfig syntax, e.g. `8080`, `"localhost"` or `{ port = 80; }`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatchOp {
+    /// Add group member or insert collection element at index, index equal
+    /// to collection length appends element. Existing member is replaced.
+    Add { path : String, value : String },
+    /// Remove setting.
+    Remove { path : String },
+    /// Replace value of existing setting.
+    Replace { path : String, value : String },
+    /// Remove setting and add it at path.
+    Move { from : String, path : String },
+    /// Add copy of setting at path.
+    Copy { from : String, path : String },
+    /// Check setting is equal to value.
+    Test { path : String, value : String }
+}
+
+impl PatchOp {
+
+    // Return operation name.
+    fn name(&self) -> &'static str {
+        match self {
+            PatchOp::Add { .. } => { "add" },
+            PatchOp::Remove { .. } => { "remove" },
+            PatchOp::Replace { .. } => { "replace" },
+            PatchOp::Move { .. } => { "move" },
+            PatchOp::Copy { .. } => { "copy" },
+            PatchOp::Test { .. } => { "test" }
+        }
+    }
+
+    // Return target path of operation.
+    fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. } | PatchOp::Remove { path } |
+            PatchOp::Replace { path, .. } | PatchOp::Move { path, .. } | 
+            PatchOp::Copy { path, .. } | PatchOp::Test { path, .. } => { 
+                path 
+            }
+        }
+    }
+}
+
+/// Sequence of operations applied to config all or nothing.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Patch {
+    ops : Vec<PatchOp>
+}
+
+// Operation of undo log with position of restored group member.
+type Undo = (PatchOp, Option<u32>);
+
+// Path element.
+pub(crate) enum Key {
+    Name(String),
+    Index(u32)
+}
+
+impl Patch {
+
+    /// Constructor.
+    /// Create empty patch.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let patch = Patch::new();
+    /// assert!(patch.ops().is_empty());
+    /// ```
+    pub fn new() -> Patch {
+        Patch { ops : Vec::new() }
+    }
+
+    /// Append operation.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::patch::{Patch, PatchOp};
+    /// 
+    /// let patch = Patch::new().push(PatchOp::Remove { 
+    ///     path : String::from("debug") 
+    /// });
+    /// assert_eq!(patch.ops().len(), 1);
+    /// ```
+    pub fn push(mut self, op : PatchOp) -> Patch {
+        self.ops.push(op);
+        self
+    }
+
+    /// Append add operation, value is written in libconfig syntax.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("ports = [ 80, 443 ];").unwrap();
+    /// cfg.apply_patch(&Patch::new()
+    ///     .add("host", "\"localhost\"")
+    ///     .add("ports.[1]", "8080")).unwrap();
+    /// assert_eq!(cfg.value("host").unwrap().as_string(), 
+    ///     Some(String::from("localhost")));
+    /// assert_eq!(cfg.value("ports.[1]").unwrap().as_int32(), Some(8080));
+    /// ```
+    pub fn add(self, path : &str, value : &str) -> Patch {
+        self.push(PatchOp::Add { 
+            path : String::from(path), 
+            value : String::from(value)
+        })
+    }
+
+    /// Append remove operation.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("debug = true;").unwrap();
+    /// cfg.apply_patch(&Patch::new().remove("debug")).unwrap();
+    /// assert!(cfg.value("debug").is_none());
+    /// ```
+    pub fn remove(self, path : &str) -> Patch {
+        self.push(PatchOp::Remove { path : String::from(path) })
+    }
+
+    /// Append replace operation, value is written in libconfig syntax.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80;").unwrap();
+    /// cfg.apply_patch(&Patch::new().replace("port", "\"http\"")).unwrap();
+    /// assert_eq!(cfg.value("port").unwrap().as_string(), 
+    ///     Some(String::from("http")));
+    /// ```
+    pub fn replace(self, path : &str, value : &str) -> Patch {
+        self.push(PatchOp::Replace { 
+            path : String::from(path), 
+            value : String::from(value)
+        })
+    }
+
+    /// Append move operation.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("host = \"localhost\"; server = {};").unwrap();
+    /// cfg.apply_patch(&Patch::new().move_from("host", "server.host"))
+    ///     .unwrap();
+    /// assert!(cfg.value("host").is_none());
+    /// assert!(cfg.value("server.host").is_some());
+    /// ```
+    pub fn move_from(self, from : &str, path : &str) -> Patch {
+        self.push(PatchOp::Move { 
+            from : String::from(from), 
+            path : String::from(path)
+        })
+    }
+
+    /// Append copy operation.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("primary = { port = 80; };").unwrap();
+    /// cfg.apply_patch(&Patch::new().copy_from("primary", "backup"))
+    ///     .unwrap();
+    /// assert_eq!(cfg.value("backup.port").unwrap().as_int32(), Some(80));
+    /// ```
+    pub fn copy_from(self, from : &str, path : &str) -> Patch {
+        self.push(PatchOp::Copy { 
+            from : String::from(from), 
+            path : String::from(path)
+        })
+    }
+
+    /// Append test operation, patch fails if setting is not equal to value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("version = 1; port = 80;").unwrap();
+    /// let patch = Patch::new().test("version", "2").replace("port", "81");
+    /// assert!(cfg.apply_patch(&patch).is_err());
+    /// assert_eq!(cfg.value("port").unwrap().as_int32(), Some(80));
+    /// ```
+    pub fn test(self, path : &str, value : &str) -> Patch {
+        self.push(PatchOp::Test { 
+            path : String::from(path), 
+            value : String::from(value)
+        })
+    }
+
+    /// Return patch operations.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::patch::{Patch, PatchOp};
+    /// 
+    /// let patch = Patch::new().remove("debug");
+    /// assert_eq!(patch.ops(), &[PatchOp::Remove { 
+    ///     path : String::from("debug") 
+    /// }]);
+    /// ```
+    pub fn ops(&self) -> &[PatchOp] {
+        &self.ops
+    }
+
+    /// Read patch from string in libconfig syntax. Operations are groups of
+    /// `patch` list with `op` and `path` members, `from` member for move and
+    /// copy and `value` member for add, replace and test.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let patch = Patch::from_string(
+    ///     "patch = (
+    ///         { op = \"test\"; path = \"version\"; value = 1; },
+    ///         { op = \"replace\"; path = \"server.port\"; value = 8080; },
+    ///         { op = \"move\"; from = \"host\"; path = \"server.host\"; }
+    ///     );").unwrap();
+    /// assert_eq!(patch, Patch::new()
+    ///     .test("version", "1")
+    ///     .replace("server.port", "8080")
+    ///     .move_from("host", "server.host"));
+    /// ```
+    pub fn from_string(text : &str) -> Result<Patch> {
+        let mut config = Config::new();
+        config.load_from_string(text)?;
+        Patch::from_config(&config)
+    }
+
+    /// Read patch from config, see `Patch::from_string`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "patch = ( { op = \"remove\"; path = \"debug\"; } );").unwrap();
+    /// assert_eq!(Patch::from_config(&cfg).unwrap(), 
+    ///     Patch::new().remove("debug"));
+    /// ```
+    pub fn from_config(config : &Config) -> Result<Patch> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        let list = member(root, "patch")
+            .filter(|list| { 
+                merge::setting_type(*list) == raw::CONFIG_TYPE_LIST 
+            })
+            .ok_or_else(|| { 
+                Errors::PatchError(String::from("patch: list expected"))
+            })?;
+
+        let mut patch = Patch::new();
+        for index in 0..merge::length(list) {
+            let group = merge::element(list, index);
+            let error = |message : &str| -> Errors {
+                Errors::PatchError(format!("{}: {}", 
+                    merge::setting_path(group), message))
+            };
+            let string = |name : &str| -> Result<String> {
+                member(group, name)
+                    .and_then(|setting| { 
+                        OptionReader::new(Some(setting)).as_string() 
+                    })
+                    .ok_or_else(|| { error(&format!("missing {}", name)) })
+            };
+            let value = || -> Result<String> {
+                member(group, "value").map(diff::render_value)
+                    .ok_or_else(|| { error("missing value") })
+            };
+
+            if merge::setting_type(group) != raw::CONFIG_TYPE_GROUP {
+                return Err(error("group expected"));
+            }
+            let path = string("path")?;
+            patch = patch.push(match string("op")?.as_str() {
+                "add" => { PatchOp::Add { path, value : value()? } },
+                "remove" => { PatchOp::Remove { path } },
+                "replace" => { PatchOp::Replace { path, value : value()? } },
+                "move" => { PatchOp::Move { from : string("from")?, path } },
+                "copy" => { PatchOp::Copy { from : string("from")?, path } },
+                "test" => { PatchOp::Test { path, value : value()? } },
+                op => { 
+                    return Err(error(&format!("unknown operation {:?}", op)));
+                }
+            });
+        }
+        Ok(patch)
+    }
+
+    /// Create patch making changes found by `Config::diff`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut old = Config::new();
+    /// old.load_from_string("port = 80; hosts = ( \"a\", \"b\", \"c\" );")
+    ///     .unwrap();
+    /// let mut new = Config::new();
+    /// new.load_from_string("port = 8080; hosts = ( \"a\" );").unwrap();
+    /// 
+    /// let patch = Patch::from_changes(&old.diff(&new));
+    /// assert_eq!(patch, Patch::new()
+    ///     .replace("port", "8080")
+    ///     .remove("hosts.[2]")
+    ///     .remove("hosts.[1]"));
+    /// 
+    /// old.apply_patch(&patch).unwrap();
+    /// assert!(old.diff(&new).is_empty());
+    /// ```
+    pub fn from_changes(changes : &[Change]) -> Patch {
+        let mut patch = Patch::new();
+        let mut removed = Vec::new();
+        
+        // Removed collection elements are reported in ascending order, they 
+        // are removed from the last one to keep indexes valid.
+        for change in changes {
+            let path = String::from(change.path());
+            if change.kind() == ChangeKind::Removed {
+                removed.push(PatchOp::Remove { path });
+                continue;
+            }
+
+            patch.ops.extend(removed.drain(..).rev());
+            let value = String::from(change.new_value().unwrap_or_default());
+            patch.ops.push(match change.kind() {
+                ChangeKind::Added => { PatchOp::Add { path, value } },
+                _ => { PatchOp::Replace { path, value } }
+            });
+        }
+        patch.ops.extend(removed.drain(..).rev());
+        patch
+    }
+
+    /// Return patch reverting changes this patch makes to config, config 
+    /// itself is not changed. See `Config::apply_patch`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80; debug = true;").unwrap();
+    /// let patch = Patch::new().replace("port", "8080").remove("debug");
+    /// 
+    /// assert_eq!(patch.inverse(&cfg).unwrap(), Patch::new()
+    ///     .add("debug", "true")
+    ///     .replace("port", "80"));
+    /// assert_eq!(cfg.value("port").unwrap().as_int32(), Some(80));
+    /// ```
+    pub fn inverse(&self, config : &Config) -> Result<Patch> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        let mut copy = Config::new();
+        let target = copy.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |_, _| {})
+            .merge_group(target, root);
+        copy.apply_patch(self)
+    }
+}
+
+/// Patch is shown in libconfig syntax accepted by `Patch::from_string`.
+impl fmt::Display for Patch {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "patch = (")?;
+        for (index, op) in self.ops.iter().enumerate() {
+            write!(f, "    {{ op = \"{}\";", op.name())?;
+            match op {
+                PatchOp::Move { from, .. } | PatchOp::Copy { from, .. } => {
+                    write!(f, " from = {};", diff::quote(from))?;
+                },
+                _ => {}
+            }
+            write!(f, " path = {};", diff::quote(op.path()))?;
+            match op {
+                PatchOp::Add { value, .. } | PatchOp::Replace { value, .. } |
+                PatchOp::Test { value, .. } => {
+                    write!(f, " value = {};", value)?;
+                },
+                _ => {}
+            }
+            let separator = if index + 1 < self.ops.len() { "," } else { "" };
+            writeln!(f, " }}{}", separator)?;
+        }
+        writeln!(f, ");")
+    }
+}
+
+impl Config {
+
+    /// Apply patch operations in order. Patch is applied all or nothing: it
+    /// is tried on copy of config first and if any operation fails, config is
+    /// left unchanged and `Errors::PatchError` naming failed operation is 
+    /// returned. If patch still fails on config, changes of previous 
+    /// operations are reverted and `Errors::RollbackError` is returned if 
+    /// they can't be. On success patch reverting changes is returned.
+    /// 
+    /// Settings following inserted group member or collection element are 
+    /// re-created, readers of them are invalidated. Their comments, hooks 
+    /// and other state are kept.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// use librustconfig::patch::Patch;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80; hosts = [ \"a\", \"b\" ];").unwrap();
+    /// 
+    /// let inverse = cfg.apply_patch(&Patch::new()
+    ///     .replace("port", "8080")
+    ///     .remove("hosts.[0]")).unwrap();
+    /// assert_eq!(cfg.value("port").unwrap().as_int32(), Some(8080));
+    /// assert_eq!(cfg.value("hosts").unwrap().as_array().count(), 1);
+    /// 
+    /// assert_eq!(cfg.apply_patch(&Patch::new()
+    ///     .add("debug", "true")
+    ///     .remove("missing")), Err(Errors::PatchError(
+    ///         String::from("remove missing: setting not exists"))));
+    /// assert!(cfg.value("debug").is_none());
+    /// 
+    /// cfg.apply_patch(&inverse).unwrap();
+    /// assert_eq!(cfg.value("port").unwrap().as_int32(), Some(80));
+    /// assert_eq!(cfg.value("hosts.[0]").unwrap().as_string(), 
+    ///     Some(String::from("a")));
+    /// ```
+    pub fn apply_patch(&mut self, patch : &Patch) -> Result<Patch> {
+        let root = self.root_setting().ok_or(Errors::ElementNotExists)?;
+        let staged = self.empty_copy();
+        let target = staged.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |setting, source| {
+            if config::is_secret(source) {
+                config::mark_secret(setting);
+            }
+        }).merge_group(target, root);
+        if let (_, Some(message)) = run(target, patch) {
+            return Err(Errors::PatchError(message));
+        }
+
+        let (undo, error) = run(root, patch);
+        match error {
+            None => {
+                Ok(Patch { 
+                    ops : undo.into_iter().rev().flatten().map(|(op, _)| { 
+                        op 
+                    }).collect()
+                })
+            },
+            Some(message) => {
+                let failed : Vec<String> = undo.into_iter().rev().flatten()
+                    .filter_map(|(op, position)| {
+                        apply(root, &op, position).err().map(|error| {
+                            format!("{} {}: {}", op.name(), op.path(), error)
+                        })
+                    }).collect();
+                if failed.is_empty() {
+                    Err(Errors::PatchError(message))
+                } else {
+                    Err(Errors::RollbackError(format!("{}, not reverted {}", 
+                        message, failed.join(", "))))
+                }
+            }
+        }
+    }
+}
+
+// Apply patch operations in order until one fails. Return undo logs of 
+// applied operations and error of failed operation.
+fn run(root : Setting, patch : &Patch) -> (Vec<Vec<Undo>>, Option<String>) {
+    let mut undo = Vec::new();
+    for op in &patch.ops {
+        match apply(root, op, None) {
+            Ok(ops) => { undo.push(ops); },
+            Err(message) => {
+                return (undo, Some(format!("{} {}: {}", op.name(), op.path(), 
+                    message)));
+            }
+        }
+    }
+    (undo, None)
+}
+
+// Apply operation to config root and return undo log of operation. Added 
+// group member is inserted at position if it is set.
+fn apply(root : Setting, op : &PatchOp, position : Option<u32>) 
+    -> std::result::Result<Vec<Undo>, String> {
+    
+    match op {
+        PatchOp::Add { path, value } => {
+            let value = parse(value)?;
+            let old = insert(root, path, single(&value), false, position)?;
+            Ok(vec![restore(path, old)])
+        },
+        PatchOp::Remove { path } => {
+            let (old, index) = remove(root, path)?;
+            Ok(vec![(PatchOp::Add { path : path.clone(), value : old }, 
+                Some(index))])
+        },
+        PatchOp::Replace { path, value } => {
+            let value = parse(value)?;
+            let old = insert(root, path, single(&value), true, None)?;
+            Ok(vec![restore(path, old)])
+        },
+        PatchOp::Move { from, path } => {
+            if from == path {
+                return Ok(Vec::new());
+            }
+            if path.starts_with(&format!("{}.", from)) {
+                return Err(String::from("setting can't be moved into itself"));
+            }
+
+            let value = Config::new();
+            let source = lookup(root, from)?;
+            hold(&value, source)?;
+            config::move_setting_state(source, single(&value));
+            let (_, index) = remove(root, from)?;
+
+            match insert(root, path, single(&value), false, position) {
+                Ok(old) => {
+                    let mut undo = vec![(PatchOp::Move { 
+                        from : path.clone(), 
+                        path : from.clone() 
+                    }, Some(index))];
+                    if let Some((old, index)) = old {
+                        undo.push((PatchOp::Add { 
+                            path : path.clone(), 
+                            value : old 
+                        }, Some(index)));
+                    }
+                    Ok(undo)
+                },
+                Err(message) => {
+                    match insert(root, from, single(&value), false, 
+                        Some(index)) {
+                        Ok(_) => { Err(message) },
+                        Err(error) => {
+                            Err(format!("{}, {} is not restored: {}", message,
+                                from, error))
+                        }
+                    }
+                }
+            }
+        },
+        PatchOp::Copy { from, path } => {
+            let value = Config::new();
+            hold(&value, lookup(root, from)?)?;
+            let old = insert(root, path, single(&value), false, position)?;
+            Ok(vec![restore(path, old)])
+        },
+        PatchOp::Test { path, value } => {
+            let setting = lookup(root, path)?;
+            let value = parse(value)?;
+            if merge::equal(setting, single(&value)) {
+                Ok(Vec::new())
+            } else {
+                Err(format!("test failed, value is {}", 
+                    diff::render_redacted_value(setting)))
+            }
+        }
+    }
+}
+
+// Return undo operation restoring replaced setting or removing added one.
+fn restore(path : &str, old : Option<(String, u32)>) -> Undo {
+    match old {
+        Some((value, _)) => { 
+            (PatchOp::Replace { path : String::from(path), value }, None) 
+        },
+        None => { (PatchOp::Remove { path : String::from(path) }, None) }
+    }
+}
+
+// Parse value written in libconfig syntax into config holding single 
+// setting.
+fn parse(value : &str) -> std::result::Result<Config, String> {
+    let mut config = Config::new();
+    let error = || { format!("invalid value {}", value) };
+    config.load_from_string(format!("value = {};", value))
+        .map_err(|_| { error() })?;
+    match config.root_setting() {
+        Some(root) if merge::length(root) == 1 => { Ok(config) },
+        _ => { Err(error()) }
+    }
+}
+
+// Copy setting into empty config.
+fn hold(config : &Config, setting : Setting) 
+    -> std::result::Result<(), String> {
+    
+    let root = config.root_setting()
+        .ok_or_else(|| { String::from("can't copy setting") })?;
+    let name = CString::new("value").unwrap_or_default();
+    Merger::new(MergePolicy::default(), &mut |_, _| {})
+        .copy(root, &name, setting);
+    Ok(())
+}
+
+// Return single setting of config created by parse or hold.
+fn single(config : &Config) -> Setting {
+    config.root_setting().map(|root| { merge::element(root, 0) })
+        .unwrap_or(std::ptr::null_mut())
+}
+
+// Split path into keys.
+pub(crate) fn keys(path : &str) -> std::result::Result<Vec<Key>, String> {
+    if path.is_empty() {
+        return Err(String::from("empty path"));
+    }
+
+    path.split('.').map(|key| {
+        if key.starts_with('[') && key.ends_with(']') {
+            key[1..key.len() - 1].parse::<u32>().map(Key::Index)
+                .map_err(|_| { format!("invalid index {}", key) })
+        } else if key.is_empty() {
+            Err(String::from("invalid path"))
+        } else {
+            Ok(Key::Name(String::from(key)))
+        }
+    }).collect()
+}
+
+// Return group member by name.
+fn member(group : Setting, name : &str) -> Option<Setting> {
+    let name = CString::new(name).ok()?;
+    let setting = unsafe { 
+        raw::config_setting_get_member(group, name.as_ptr()) 
+    };
+    if setting.is_null() { None } else { Some(setting) }
+}
+
+// Return child of aggregate setting by key.
+fn child(setting : Setting, key : &Key) -> Option<Setting> {
+    match (key, merge::setting_type(setting)) {
+        (Key::Name(name), raw::CONFIG_TYPE_GROUP) => { member(setting, name) },
+        (Key::Index(index), raw::CONFIG_TYPE_ARRAY) | 
+        (Key::Index(index), raw::CONFIG_TYPE_LIST) 
+            if *index < merge::length(setting) => {
+            Some(merge::element(setting, *index))
+        },
+        _ => { None }
+    }
+}
+
+// Find setting by path.
+fn lookup(root : Setting, path : &str) 
+    -> std::result::Result<Setting, String> {
+    
+    keys(path)?.iter().try_fold(root, |setting, key| { 
+        child(setting, key) 
+    }).ok_or_else(|| { String::from("setting not exists") })
+}
+
+// Remove setting and return its value and position.
+fn remove(root : Setting, path : &str) 
+    -> std::result::Result<(String, u32), String> {
+    
+    let setting = lookup(root, path)?;
+    let value = diff::render_value(setting);
+    let index = unsafe { raw::config_setting_index(setting) } as u32;
+    unsafe { 
+        raw::config_setting_remove_elem(raw::config_setting_parent(setting), 
+            index); 
+    }
+    Ok((value, index))
+}
+
+// Insert copy of source setting at path. Existing group member is replaced,
+// collection element is replaced if replace is set, otherwise element is 
+// inserted before it. New group member is inserted at position or appended.
+// State of source setting is moved to inserted one. Return value and 
+// position of replaced setting.
+fn insert(root : Setting, path : &str, source : Setting, replace : bool, 
+    position : Option<u32>) 
+    -> std::result::Result<Option<(String, u32)>, String> {
+    
+    let mut keys = keys(path)?;
+    let key = keys.pop().ok_or_else(|| { String::from("empty path") })?;
+    let parent = keys.iter().try_fold(root, |setting, key| { 
+        child(setting, key) 
+    }).ok_or_else(|| { String::from("parent setting not exists") })?;
+    let length = merge::length(parent);
+
+    let (name, index, existing) = match (&key, merge::setting_type(parent)) {
+        (Key::Name(name), raw::CONFIG_TYPE_GROUP) => {
+            let name = CString::new(name.as_str())
+                .map_err(|_| { format!("invalid setting name {:?}", name) })?;
+            match member(parent, &name.to_string_lossy()) {
+                Some(setting) => {
+                    let index = unsafe { raw::config_setting_index(setting) };
+                    (name, index as u32, Some(setting))
+                },
+                None if replace => { 
+                    return Err(String::from("setting not exists")); 
+                },
+                None => { 
+                    (name, position.unwrap_or(length).min(length), None) 
+                }
+            }
+        },
+        (Key::Index(index), raw::CONFIG_TYPE_ARRAY) | 
+        (Key::Index(index), raw::CONFIG_TYPE_LIST) => {
+            if (replace && *index >= length) || *index > length {
+                return Err(String::from("index out of range"));
+            }
+            let existing = if replace { 
+                Some(merge::element(parent, *index)) 
+            } else { 
+                None 
+            };
+            (CString::default(), *index, existing)
+        },
+        (Key::Name(_), _) => { 
+            return Err(format!("{} is not a group", 
+                merge::setting_path(parent))); 
+        },
+        (Key::Index(_), _) => { 
+            return Err(format!("{} is not a collection", 
+                merge::setting_path(parent))); 
+        }
+    };
+    let old = existing.map(|setting| { 
+        (diff::render_value(setting), index) 
+    });
+
+    if let Some(setting) = existing {
+        let kind = merge::setting_type(setting);
+        if kind == merge::setting_type(source) && 
+            raw::config_setting_is_scalar(setting) == raw::CONFIG_TRUE {
+            merge::copy_value(setting, source);
+            config::move_setting_state(source, setting);
+            return Ok(old);
+        }
+    }
+
+    // Settings after position are moved aside with their state and appended
+    // after inserted setting, libconfig can only append settings.
+    let tail = Config::new();
+    let holder = tail.root_setting().map(|root| {
+        let name = CString::new("tail").unwrap_or_default();
+        unsafe { 
+            raw::config_setting_add(root, name.as_ptr(), 
+                merge::setting_type(parent) as i32) 
+        }
+    }).ok_or_else(|| { String::from("can't insert setting") })?;
+
+    let mut observer = |_, _| {};
+    let mut merger = Merger::new(MergePolicy::default(), &mut observer);
+    while merge::length(parent) > index {
+        let setting = merge::element(parent, index);
+        let name = CString::new(merge::setting_name(setting))
+            .unwrap_or_default();
+        let copy = merger.copy(holder, &name, setting);
+        config::move_setting_state(setting, copy);
+        unsafe { raw::config_setting_remove_elem(parent, index); }
+    }
+
+    let setting = merger.copy(parent, &name, source);
+    config::move_setting_state(source, setting);
+    let added = !setting.is_null();
+    let skip = if added && old.is_some() { 1 } else { 0 };
+    for item in skip..merge::length(holder) {
+        let setting = merge::element(holder, item);
+        let name = CString::new(merge::setting_name(setting))
+            .unwrap_or_default();
+        let copy = merger.copy(parent, &name, setting);
+        config::move_setting_state(setting, copy);
+    }
+
+    if added {
+        Ok(old)
+    } else {
+        Err(format!("can't add {} here", 
+            schema::type_name(merge::setting_type(source))))
+    }
+}
@@ -0,0 +1,490 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{Config, Errors, OptionReader, Result};
+use crate::merge::{self, Setting};
+use crate::patch::{self, Key};
+use crate::schema::SchemaValue;
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::vec;
+
+/// Query selecting settings of config tree. Query is a sequence of steps 
+/// separated by `.`:
+/// 
+/// * `name` selects group member,
+/// * `*` selects all members or elements,
+/// * `[2]`, `[-1]` select collection element by index, negative index 
+///   counts from the end,
+/// * `[1:3]`, `[:2]`, `[-2:]` select slice of elements,
+/// * `[?enabled == true]` selects members or elements matching predicate. 
+///   Predicate compares relative path with string, number or boolean by 
+///   `==`, `!=`, `<`, `<=`, `>` or `>=`, predicate without operator checks 
+///   setting exists.
+/// 
+/// Step preceded by `..` is applied to setting and all its descendants, so 
+/// `..port` selects every `port` setting. Brackets may follow name without 
+/// separator, e.g. `servers[0]`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Query {
+    steps : Vec<Step>
+}
+
+// Query step.
+#[derive(Debug, PartialEq, Clone)]
+struct Step {
+    descendants : bool,
+    selector : Selector
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Filter(Filter)
+}
+
+// Filter predicate.
+#[derive(Debug, PartialEq, Clone)]
+struct Filter {
+    path : String,
+    condition : Option<(Ordering, bool, SchemaValue)>
+}
+
+/// Iterator over settings selected by query with their paths.
+pub struct QueryIterator {
+    base : Option<OptionReader>,
+    settings : vec::IntoIter<Setting>
+}
+
+// Query parser.
+struct Parser<'a> {
+    text : &'a str,
+    pos : usize
+}
+
+impl<'a> Parser<'a> {
+
+    // Return parse error at current position.
+    fn error(&self, message : &str) -> Errors {
+        Errors::QueryError(format!("{}: position {}: {}", self.text, 
+            self.pos + 1, message))
+    }
+
+    // Return rest of query.
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    // Parse query.
+    fn query(&mut self) -> Result<Vec<Step>> {
+        let mut steps = Vec::new();
+        let mut separated = true;
+        while self.pos < self.text.len() {
+            let descendants = self.rest().starts_with("..");
+            if descendants {
+                self.pos += 2;
+            } else if self.rest().starts_with('.') {
+                if steps.is_empty() {
+                    return Err(self.error("step expected"));
+                }
+                self.pos += 1;
+            } else if !separated && !self.rest().starts_with('[') {
+                return Err(self.error("'.' expected"));
+            }
+
+            steps.push(Step { descendants, selector : self.selector()? });
+            separated = false;
+        }
+        Ok(steps)
+    }
+
+    // Parse step selector.
+    fn selector(&mut self) -> Result<Selector> {
+        let rest = self.rest();
+        if rest.starts_with('*') {
+            self.pos += 1;
+            return Ok(Selector::Wildcard);
+        }
+        if rest.starts_with('[') {
+            return self.bracket();
+        }
+
+        let length = rest.find(|character : char| {
+            !(character.is_ascii_alphanumeric() || "-_".contains(character))
+        }).unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error("step expected"));
+        }
+        self.pos += length;
+        Ok(Selector::Name(String::from(&rest[..length])))
+    }
+
+    // Parse bracket selector.
+    fn bracket(&mut self) -> Result<Selector> {
+        self.pos += 1;
+        let end = self.closing().ok_or_else(|| { self.error("']' expected") })?;
+        let content = self.rest()[..end].trim();
+        
+        let selector = if content == "*" {
+            Selector::Wildcard
+        } else if let Some(predicate) = content.strip_prefix('?') {
+            Selector::Filter(self.filter(predicate.trim())?)
+        } else if let Some((start, end)) = content.split_once(':') {
+            Selector::Slice(self.bound(start)?, self.bound(end)?)
+        } else {
+            Selector::Index(content.parse::<i64>()
+                .map_err(|_| { self.error("index expected") })?)
+        };
+        self.pos += end + 1;
+        Ok(selector)
+    }
+
+    // Return offset of closing bracket, brackets in strings are skipped.
+    fn closing(&self) -> Option<usize> {
+        let mut quoted = false;
+        let mut escaped = false;
+        for (index, character) in self.rest().char_indices() {
+            match character {
+                '\\' if quoted && !escaped => { escaped = true; continue; },
+                '"' if !escaped => { quoted = !quoted; },
+                ']' if !quoted => { return Some(index); },
+                _ => {}
+            }
+            escaped = false;
+        }
+        None
+    }
+
+    // Parse slice bound.
+    fn bound(&self, bound : &str) -> Result<Option<i64>> {
+        let bound = bound.trim();
+        if bound.is_empty() {
+            return Ok(None);
+        }
+        bound.parse::<i64>().map(Some)
+            .map_err(|_| { self.error("slice bound expected") })
+    }
+
+    // Parse filter predicate.
+    fn filter(&self, predicate : &str) -> Result<Filter> {
+        let operators = [("==", Ordering::Equal, true), 
+            ("!=", Ordering::Equal, false), ("<=", Ordering::Greater, false), 
+            (">=", Ordering::Less, false), ("<", Ordering::Less, true), 
+            (">", Ordering::Greater, true)];
+        let operator = operators.iter().filter_map(|operator| {
+            predicate.find(operator.0).map(|index| { (index, operator) })
+        }).min_by_key(|(index, _)| { *index });
+
+        let (path, condition) = match operator {
+            Some((index, (text, ordering, equal))) => {
+                let value = literal(predicate[index + text.len()..].trim())
+                    .ok_or_else(|| { self.error("value expected") })?;
+                (predicate[..index].trim(), Some((*ordering, *equal, value)))
+            },
+            None => { (predicate, None) }
+        };
+        
+        patch::keys(path).map_err(|message| { self.error(&message) })?;
+        Ok(Filter { path : String::from(path), condition })
+    }
+}
+
+impl Query {
+
+    /// Parse query.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::query::Query;
+    /// 
+    /// assert!(Query::parse("servers.*.host").is_ok());
+    /// assert!(Query::parse("servers[?enabled == true].port").is_ok());
+    /// assert!(Query::parse("servers.[1:").is_err());
+    /// ```
+    pub fn parse(query : &str) -> Result<Query> {
+        let mut parser = Parser { text : query, pos : 0 };
+        Ok(Query { steps : parser.query()? })
+    }
+
+    /// Return settings selected by query below reader, in config order.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::query::Query;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { ports = [ 80, 443, 8080 ]; };")
+    ///     .unwrap();
+    /// let query = Query::parse("ports[-2:]").unwrap();
+    /// let ports : Vec<i32> = query.select(&cfg.value("server").unwrap())
+    ///     .map(|(_, port)| { port.as_int32().unwrap() }).collect();
+    /// assert_eq!(ports, vec![443, 8080]);
+    /// ```
+    pub fn select(&self, reader : &OptionReader) -> QueryIterator {
+        let mut settings : Vec<Setting> = reader.element.into_iter()
+            .collect();
+        for step in &self.steps {
+            let mut selected = Vec::new();
+            for setting in settings {
+                if step.descendants {
+                    let mut nodes = Vec::new();
+                    merge::collect(setting, &mut nodes);
+                    for node in nodes {
+                        step.selector.select(node, &mut selected);
+                    }
+                } else {
+                    step.selector.select(setting, &mut selected);
+                }
+            }
+            
+            let mut unique = HashSet::new();
+            selected.retain(|setting| { unique.insert(*setting as usize) });
+            settings = selected;
+        }
+        
+        QueryIterator { 
+            base : reader.element.map(|element| { reader.child(element) }),
+            settings : settings.into_iter() 
+        }
+    }
+}
+
+impl Selector {
+
+    // Add children of setting matched by selector.
+    fn select(&self, setting : Setting, selected : &mut Vec<Setting>) {
+        if raw::config_setting_is_aggregate(setting) != raw::CONFIG_TRUE {
+            return;
+        }
+        
+        let length = merge::length(setting) as i64;
+        let index = |index : i64| -> i64 {
+            if index < 0 { (length + index).max(0) } else { index.min(length) }
+        };
+        let collection = merge::setting_type(setting) != 
+            raw::CONFIG_TYPE_GROUP;
+        
+        match self {
+            Selector::Name(name) => {
+                if let Ok(name) = CString::new(name.as_str()) {
+                    let member = unsafe { 
+                        raw::config_setting_get_member(setting, name.as_ptr()) 
+                    };
+                    if !member.is_null() {
+                        selected.push(member);
+                    }
+                }
+            },
+            Selector::Wildcard => {
+                selected.extend((0..length as u32).map(|item| { 
+                    merge::element(setting, item) 
+                }));
+            },
+            Selector::Index(position) if collection => {
+                let position = if *position < 0 { 
+                    length + position 
+                } else { 
+                    *position 
+                };
+                if position >= 0 && position < length {
+                    selected.push(merge::element(setting, position as u32));
+                }
+            },
+            Selector::Slice(start, end) if collection => {
+                let start = start.map_or(0, index);
+                let end = end.map_or(length, index);
+                selected.extend((start..end.max(start)).map(|item| { 
+                    merge::element(setting, item as u32) 
+                }));
+            },
+            Selector::Filter(filter) => {
+                selected.extend((0..length as u32)
+                    .map(|item| { merge::element(setting, item) })
+                    .filter(|child| { filter.matches(*child) }));
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Filter {
+
+    // Check predicate for setting.
+    fn matches(&self, setting : Setting) -> bool {
+        let target = patch::keys(&self.path).ok().and_then(|keys| {
+            keys.iter().try_fold(setting, |setting, key| { 
+                child(setting, key) 
+            })
+        });
+        
+        match (target, &self.condition) {
+            (None, _) => { false },
+            (Some(_), None) => { true },
+            (Some(target), Some((ordering, equal, value))) => {
+                let order = SchemaValue::from_setting(target)
+                    .and_then(|target| { target.compare(value) });
+                match order {
+                    Some(order) => { (order == *ordering) == *equal },
+                    None => { false }
+                }
+            }
+        }
+    }
+}
+
+impl QueryIterator {
+
+    /// Return settings paths and readers as vector.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("a = { port = 1; }; b = { port = 2; };").unwrap();
+    /// let paths : Vec<String> = cfg.query("*.port").unwrap().paths();
+    /// assert_eq!(paths, vec!["a.port", "b.port"]);
+    /// ```
+    pub fn paths(self) -> Vec<String> {
+        self.map(|(path, _)| { path }).collect()
+    }
+}
+
+impl Iterator for QueryIterator {
+    type Item = (String, OptionReader);
+
+    fn next(&mut self) -> Option<(String, OptionReader)> {
+        let setting = self.settings.next()?;
+        let reader = self.base.as_ref()?.child(setting);
+        Some((merge::setting_path(setting), reader))
+    }
+}
+
+impl Config {
+
+    /// Return settings selected by query with their paths, see `Query`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "servers = ( 
+    ///         { host = \"a\"; tls = { enabled = true; }; },
+    ///         { host = \"b\"; tls = { enabled = false; }; },
+    ///         { host = \"c\"; }
+    ///     );").unwrap();
+    /// 
+    /// let hosts : Vec<String> = cfg.query("servers.*.host").unwrap()
+    ///     .map(|(_, host)| { host.as_string().unwrap() }).collect();
+    /// assert_eq!(hosts, vec!["a", "b", "c"]);
+    /// 
+    /// assert_eq!(cfg.query("..tls").unwrap().paths(), 
+    ///     vec!["servers.[0].tls", "servers.[1].tls"]);
+    /// assert_eq!(cfg.query("servers[?tls.enabled == true].host").unwrap()
+    ///     .paths(), vec!["servers.[0].host"]);
+    /// ```
+    pub fn query(&self, query : &str) -> Result<QueryIterator> {
+        let root = self.root_reader().ok_or(Errors::ElementNotExists)?;
+        Ok(Query::parse(query)?.select(&root))
+    }
+}
+
+impl OptionReader {
+
+    /// Return settings below this setting selected by query, see `Query`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("limits = ( 10, 20, 30, 40 );").unwrap();
+    /// let limits = cfg.value("limits").unwrap();
+    /// assert_eq!(limits.query("[1:3]").unwrap().paths(), 
+    ///     vec!["limits.[1]", "limits.[2]"]);
+    /// ```
+    pub fn query(&self, query : &str) -> Result<QueryIterator> {
+        Ok(Query::parse(query)?.select(self))
+    }
+}
+
+// Return child of aggregate setting by key.
+fn child(setting : Setting, key : &Key) -> Option<Setting> {
+    let child = match key {
+        Key::Name(name) => {
+            let name = CString::new(name.as_str()).ok()?;
+            unsafe { raw::config_setting_get_member(setting, name.as_ptr()) }
+        },
+        Key::Index(index) => { 
+            if merge::setting_type(setting) == raw::CONFIG_TYPE_GROUP {
+                return None;
+            }
+            merge::element(setting, *index) 
+        }
+    };
+    if child.is_null() { None } else { Some(child) }
+}
+
+// Parse predicate literal.
+fn literal(text : &str) -> Option<SchemaValue> {
+    if let Some(text) = text.strip_prefix('"') {
+        let text = text.strip_suffix('"')?;
+        let mut value = String::new();
+        let mut escaped = false;
+        for character in text.chars() {
+            match character {
+                '\\' if !escaped => { escaped = true; continue; },
+                '"' if !escaped => { return None; },
+                character => { value.push(character); }
+            }
+            escaped = false;
+        }
+        return Some(SchemaValue::Str(value));
+    }
+
+    match text {
+        "true" => { Some(SchemaValue::Boolean(true)) },
+        "false" => { Some(SchemaValue::Boolean(false)) },
+        _ => {
+            text.parse::<i64>().map(SchemaValue::Integer).ok()
+                .or_else(|| { text.parse::<f64>().ok()
+                    .map(SchemaValue::Float) })
+        }
+    }
+}
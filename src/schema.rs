@@ -0,0 +1,1193 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, OptionReader, OptionType, Result};
+use crate::diff;
+use crate::merge::{self, Setting};
+
+use regex::Regex;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::fmt;
+use std::ptr;
+
+/// Schema of setting used to validate config.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::schema::Schema;
+/// 
+/// let schema = Schema::group()
+///     .field("server", Schema::group()
+///         .field("host", Schema::string().required())
+///         .field("port", Schema::int32().range(1, 65535).required())
+///         .field("mode", Schema::string().one_of(vec!["dev", "prod"]))
+///         .deny_unknown())
+///     .field("hosts", Schema::array(Schema::string().pattern("^[a-z.]+$")));
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("server = { host = \"localhost\"; port = 0; };")
+///     .unwrap();
+/// let violations = schema.validate(&cfg);
+/// assert_eq!(violations[0].to_string(), 
+///     "line 1: server.port: value 0 is less than 1");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    kind : SchemaKind,
+    required : bool,
+    min : Option<SchemaValue>,
+    max : Option<SchemaValue>,
+    min_length : Option<usize>,
+    max_length : Option<usize>,
+    pattern : Option<std::result::Result<Regex, String>>,
+    values : Vec<SchemaValue>,
+    default : Option<SchemaValue>
+}
+
+/// Kind of setting described by schema.
+#[derive(Debug, Clone)]
+enum SchemaKind {
+    Any,
+    Value(OptionType),
+    Group(Vec<(String, Schema)>, bool),
+    Array(Box<Schema>),
+    List(Box<Schema>)
+}
+
+/// Scalar value used in schema constraints.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SchemaValue {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Str(String)
+}
+
+/// Schema violation found by validation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Violation {
+    path : String,
+    file : Option<String>,
+    line : Option<u32>,
+    message : String
+}
+
+impl Schema {
+
+    // Constructor.
+    fn new(kind : SchemaKind) -> Schema {
+        Schema {
+            kind,
+            required : false,
+            min : None,
+            max : None,
+            min_length : None,
+            max_length : None,
+            pattern : None,
+            values : Vec::new(),
+            default : None
+        }
+    }
+
+    /// Create schema of setting of any type.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group().field("extra", Schema::any());
+    /// ```
+    pub fn any() -> Schema {
+        Schema::new(SchemaKind::Any)
+    }
+
+    /// Create schema of scalar value of type. Integer values are accepted for
+    /// 64bit integer type.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::OptionType;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::value(OptionType::FloatType);
+    /// ```
+    pub fn value(value_type : OptionType) -> Schema {
+        Schema::new(SchemaKind::Value(value_type))
+    }
+
+    /// Create schema of integer value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::int32().range(1, 65535);
+    /// ```
+    pub fn int32() -> Schema {
+        Schema::value(OptionType::IntegerType)
+    }
+
+    /// Create schema of 64bit integer value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::int64().min(0);
+    /// ```
+    pub fn int64() -> Schema {
+        Schema::value(OptionType::Int64Type)
+    }
+
+    /// Create schema of float value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::float64().range(0.0, 1.0);
+    /// ```
+    pub fn float64() -> Schema {
+        Schema::value(OptionType::FloatType)
+    }
+
+    /// Create schema of boolean value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::boolean().required();
+    /// ```
+    pub fn boolean() -> Schema {
+        Schema::value(OptionType::BooleanType)
+    }
+
+    /// Create schema of string value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::string().length(1, 255);
+    /// ```
+    pub fn string() -> Schema {
+        Schema::value(OptionType::StringType)
+    }
+
+    /// Create schema of group, unknown settings are allowed until 
+    /// `deny_unknown` is called.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("name", Schema::string());
+    /// ```
+    pub fn group() -> Schema {
+        Schema::new(SchemaKind::Group(Vec::new(), true))
+    }
+
+    /// Create schema of array with elements described by element schema.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::array(Schema::int32()).length(1, 8);
+    /// ```
+    pub fn array(element : Schema) -> Schema {
+        Schema::new(SchemaKind::Array(Box::new(element)))
+    }
+
+    /// Create schema of list with elements described by element schema.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::list(Schema::group()
+    ///     .field("host", Schema::string().required()));
+    /// ```
+    pub fn list(element : Schema) -> Schema {
+        Schema::new(SchemaKind::List(Box::new(element)))
+    }
+
+    /// Add group member setting schema.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("port", Schema::int32());
+    /// ```
+    pub fn field<S>(mut self, name : S, schema : Schema) -> Schema 
+        where S: Into<String> {
+        
+        if let SchemaKind::Group(fields, _) = &mut self.kind {
+            fields.push((name.into(), schema));
+        }
+        self
+    }
+
+    /// Report group settings missing in schema as violations.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("port", Schema::int32())
+    ///     .deny_unknown();
+    /// ```
+    pub fn deny_unknown(mut self) -> Schema {
+        if let SchemaKind::Group(_, allow_unknown) = &mut self.kind {
+            *allow_unknown = false;
+        }
+        self
+    }
+
+    /// Make setting required.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::string().required();
+    /// ```
+    pub fn required(mut self) -> Schema {
+        self.required = true;
+        self
+    }
+
+    /// Set inclusive range of numeric value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::int32().range(1, 65535);
+    /// ```
+    pub fn range<V>(self, min : V, max : V) -> Schema 
+        where V: Into<SchemaValue> {
+        self.min(min).max(max)
+    }
+
+    /// Set minimal numeric value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::float64().min(0.5);
+    /// ```
+    pub fn min<V>(mut self, min : V) -> Schema where V: Into<SchemaValue> {
+        self.min = Some(min.into());
+        self
+    }
+
+    /// Set maximal numeric value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::int64().max(1_000_000_000_000i64);
+    /// ```
+    pub fn max<V>(mut self, max : V) -> Schema where V: Into<SchemaValue> {
+        self.max = Some(max.into());
+        self
+    }
+
+    /// Set inclusive range of string length or count of collection elements.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::string().length(1, 64);
+    /// ```
+    pub fn length(mut self, min : usize, max : usize) -> Schema {
+        self.min_length = Some(min);
+        self.max_length = Some(max);
+        self
+    }
+
+    /// Set regular expression string value must match. Invalid expression is
+    /// reported as violation by validation.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::string().pattern("^[a-z]+$");
+    /// ```
+    pub fn pattern(mut self, pattern : &str) -> Schema {
+        self.pattern = Some(Regex::new(pattern).map_err(|error| {
+            format!("invalid schema pattern {:?}: {}", pattern, error)
+        }));
+        self
+    }
+
+    /// Set allowed values.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::string().one_of(vec!["debug", "info", "error"]);
+    /// ```
+    pub fn one_of<I, V>(mut self, values : I) -> Schema 
+        where I: IntoIterator<Item = V>, V: Into<SchemaValue> {
+        self.values = values.into_iter().map(|value| { value.into() })
+            .collect();
+        self
+    }
+
+    /// Set default value inserted by `Schema::apply_defaults` when setting is
+    /// missing.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::int32().range(1, 65535).default(8080);
+    /// ```
+    pub fn default<V>(mut self, value : V) -> Schema 
+        where V: Into<SchemaValue> {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Create schema from config written in libconfig syntax. Each setting 
+    /// is described by group with members `type` (`"int"`, `"int64"`, 
+    /// `"float"`, `"bool"`, `"string"`, `"group"`, `"array"`, `"list"` or 
+    /// `"any"`, groups are default), `required`, `min`, `max`, `min_length`,
+    /// `max_length`, `pattern`, `enum` and `default`. Groups describe 
+    /// members in `fields` group and deny unknown settings by 
+    /// `allow_unknown = false`, collections describe elements in `element` 
+    /// group. Root setting describes root group.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let mut definition = Config::new();
+    /// definition.load_from_string(r#"
+    ///     allow_unknown = false;
+    ///     fields = {
+    ///         port = { type = "int"; min = 1; max = 65535; default = 80; };
+    ///         hosts = { type = "array"; element = { type = "string"; }; };
+    ///     };"#).unwrap();
+    /// let schema = Schema::from_config(&definition).unwrap();
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 8080;").unwrap();
+    /// assert!(schema.validate(&cfg).is_empty());
+    /// ```
+    pub fn from_config(config : &Config) -> Result<Schema> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        Schema::from_setting(root)
+    }
+
+    // Create schema from setting describing it.
+    fn from_setting(setting : Setting) -> Result<Schema> {
+        let error = |setting : Setting, message : String| -> Errors {
+            Errors::SchemaError(format!("{}: {}", 
+                merge::setting_location(setting), message))
+        };
+        if merge::setting_type(setting) != raw::CONFIG_TYPE_GROUP {
+            return Err(error(setting, String::from(
+                "setting schema must be a group")));
+        }
+
+        let reader = OptionReader::new(Some(setting));
+        let kind = reader.value("type").map(|value| { 
+            value.as_string().ok_or_else(|| { 
+                error(value.element.unwrap(), String::from(
+                    "type must be a string"))
+            })
+        }).transpose()?.unwrap_or_else(|| { String::from("group") });
+        
+        let element = |name : &str| -> Result<Schema> {
+            match reader.value("element") {
+                Some(value) => { Schema::from_setting(value.element.unwrap()) },
+                None => { 
+                    Err(error(setting, format!("{} schema without element", 
+                        name))) 
+                }
+            }
+        };
+
+        let mut schema = {
+            match kind.as_str() {
+                "any" => { Schema::any() },
+                "int" => { Schema::int32() },
+                "int64" => { Schema::int64() },
+                "float" => { Schema::float64() },
+                "bool" => { Schema::boolean() },
+                "string" => { Schema::string() },
+                "array" => { Schema::array(element("array")?) },
+                "list" => { Schema::list(element("list")?) },
+                "group" => {
+                    let mut group = Schema::group();
+                    if let Some(fields) = reader.value("fields") {
+                        let fields = fields.element.unwrap();
+                        if merge::setting_type(fields) != 
+                            raw::CONFIG_TYPE_GROUP {
+                            return Err(error(fields, String::from(
+                                "fields must be a group")));
+                        }
+                        for index in 0..merge::length(fields) {
+                            let field = merge::element(fields, index);
+                            group = group.field(merge::setting_name(field), 
+                                Schema::from_setting(field)?);
+                        }
+                    }
+                    if reader.value("allow_unknown")
+                        .and_then(|value| { value.as_bool() }) == Some(false) {
+                        group = group.deny_unknown();
+                    }
+                    group
+                },
+                kind => {
+                    return Err(error(setting, format!(
+                        "unknown setting type {:?}", kind)));
+                }
+            }
+        };
+
+        if let Some(required) = reader.value("required") {
+            schema.required = required.as_bool().ok_or_else(|| {
+                error(required.element.unwrap(), String::from(
+                    "required must be a boolean"))
+            })?;
+        }
+        for (name, bound) in [("min", &mut schema.min), 
+            ("max", &mut schema.max), ("default", &mut schema.default)] {
+            if let Some(value) = reader.value(name) {
+                *bound = Some(SchemaValue::from_setting(value.element.unwrap())
+                    .ok_or_else(|| {
+                        error(value.element.unwrap(), format!(
+                            "{} must be a scalar value", name))
+                    })?);
+            }
+        }
+        for (name, length) in [("min_length", &mut schema.min_length), 
+            ("max_length", &mut schema.max_length)] {
+            if let Some(value) = reader.value(name) {
+                *length = Some(value.as_int64().filter(|value| { *value >= 0 })
+                    .ok_or_else(|| {
+                        error(value.element.unwrap(), format!(
+                            "{} must be a non-negative integer", name))
+                    })? as usize);
+            }
+        }
+        if let Some(pattern) = reader.value("pattern") {
+            let text = pattern.as_string().ok_or_else(|| {
+                error(pattern.element.unwrap(), String::from(
+                    "pattern must be a string"))
+            })?;
+            schema = schema.pattern(&text);
+            if let Some(Err(message)) = &schema.pattern {
+                return Err(error(pattern.element.unwrap(), message.clone()));
+            }
+        }
+        if let Some(values) = reader.value("enum") {
+            let values = values.element.unwrap();
+            for index in 0..merge::length(values) {
+                let value = merge::element(values, index);
+                schema.values.push(SchemaValue::from_setting(value)
+                    .ok_or_else(|| {
+                        error(value, String::from("enum values must be scalar"))
+                    })?);
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Create config containing default values of schema, groups are created
+    /// only if they contain defaults.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("server", Schema::group()
+    ///         .field("host", Schema::string().required())
+    ///         .field("port", Schema::int32().default(8080)));
+    /// 
+    /// let defaults = schema.defaults();
+    /// assert_eq!(defaults.value("server.port").unwrap().as_int32(), 
+    ///     Some(8080));
+    /// assert!(defaults.value("server.host").is_none());
+    /// ```
+    pub fn defaults(&self) -> Config {
+        self.build(false)
+    }
+
+    /// Create fully populated example config for documentation purposes. 
+    /// Values are defaults, first allowed values, minimal values or zero 
+    /// values in that order. Strings and collections have minimal length,
+    /// collections contain at least one example element. String patterns 
+    /// are not considered.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("host", Schema::string().required())
+    ///     .field("port", Schema::int32().min(1).default(8080))
+    ///     .field("mode", Schema::string().one_of(vec!["dev", "prod"]))
+    ///     .field("hosts", Schema::array(Schema::string()))
+    ///     .field("name", Schema::string().length(3, 64));
+    /// 
+    /// let example = schema.example();
+    /// assert_eq!(example.value("host").unwrap().as_string(), 
+    ///     Some(String::new()));
+    /// assert_eq!(example.value("port").unwrap().as_int32(), Some(8080));
+    /// assert_eq!(example.value("mode").unwrap().as_string(), 
+    ///     Some(String::from("dev")));
+    /// assert_eq!(example.value("hosts").unwrap().as_array().count(), 1);
+    /// assert_eq!(example.value("name").unwrap().as_string(), 
+    ///     Some(String::from("xxx")));
+    /// ```
+    pub fn example(&self) -> Config {
+        self.build(true)
+    }
+
+    /// Insert default values of missing settings to config and return paths
+    /// of inserted settings, see `Config::apply_defaults`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("port", Schema::int32().default(8080))
+    ///     .field("debug", Schema::boolean().default(false));
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 9090;").unwrap();
+    /// assert_eq!(schema.apply_defaults(&mut cfg).unwrap(), vec!["debug"]);
+    /// assert!(cfg.value("debug").unwrap().is_defaulted());
+    /// ```
+    pub fn apply_defaults(&self, config : &mut Config) -> Result<Vec<String>> {
+        config.apply_defaults(&self.defaults())
+    }
+
+    // Create config filled by defaults or example values.
+    fn build(&self, example : bool) -> Config {
+        let config = Config::new();
+        if let Some(root) = config.root_setting() {
+            self.fill(root, example);
+        }
+        config
+    }
+
+    // Add settings described by group schema to group setting.
+    fn fill(&self, setting : Setting, example : bool) {
+        if let SchemaKind::Group(fields, _) = &self.kind {
+            for (name, schema) in fields {
+                if let Ok(name) = CString::new(name.as_str()) {
+                    schema.add(setting, name.as_ptr(), example);
+                }
+            }
+        }
+    }
+
+    // Add setting described by schema to parent setting, collection elements
+    // are added with null name.
+    fn add(&self, parent : Setting, name : *const libc::c_char, 
+        example : bool) {
+        
+        let value = {
+            if example { self.example_value() } else { self.default.clone() }
+        };
+        let kind = {
+            match (&self.kind, &value) {
+                (SchemaKind::Group(_, _), _) => {
+                    if !example && !self.has_defaults() {
+                        return;
+                    }
+                    raw::CONFIG_TYPE_GROUP
+                },
+                (SchemaKind::Array(_), _) | (SchemaKind::List(_), _) => {
+                    if !example {
+                        return;
+                    }
+                    self.setting_type()
+                },
+                (kind, Some(value)) => {
+                    match value_type(kind, value) {
+                        Some(kind) => { kind },
+                        None => { return; }
+                    }
+                },
+                (_, None) => { return; }
+            }
+        };
+
+        let setting = unsafe { 
+            raw::config_setting_add(parent, name, kind as i32) 
+        };
+        if setting.is_null() {
+            return;
+        }
+        match (&self.kind, &value) {
+            (SchemaKind::Group(_, _), _) => { self.fill(setting, example); },
+            (SchemaKind::Array(element), _) | 
+            (SchemaKind::List(element), _) => {
+                for _ in 0..self.min_length.unwrap_or(0).max(1) {
+                    element.add(setting, ptr::null(), example);
+                }
+            },
+            (_, Some(value)) => { write_value(setting, value); },
+            _ => {}
+        }
+    }
+
+    // Return true if schema or its members have default values.
+    fn has_defaults(&self) -> bool {
+        match &self.kind {
+            SchemaKind::Group(fields, _) => {
+                fields.iter().any(|(_, schema)| { schema.has_defaults() })
+            },
+            SchemaKind::Array(_) | SchemaKind::List(_) => { false },
+            _ => { self.default.is_some() }
+        }
+    }
+
+    // Return value used in example config.
+    fn example_value(&self) -> Option<SchemaValue> {
+        self.default.clone()
+            .or_else(|| { self.values.first().cloned() })
+            .or_else(|| { self.min.clone() })
+            .or_else(|| { self.max.clone() })
+            .or_else(|| {
+                match &self.kind {
+                    SchemaKind::Value(OptionType::IntegerType) | 
+                    SchemaKind::Value(OptionType::Int64Type) => {
+                        Some(SchemaValue::Integer(0))
+                    },
+                    SchemaKind::Value(OptionType::FloatType) => {
+                        Some(SchemaValue::Float(0.0))
+                    },
+                    SchemaKind::Value(OptionType::BooleanType) => {
+                        Some(SchemaValue::Boolean(false))
+                    },
+                    SchemaKind::Value(OptionType::StringType) => {
+                        Some(SchemaValue::Str(
+                            "x".repeat(self.min_length.unwrap_or(0))))
+                    },
+                    _ => { None }
+                }
+            })
+    }
+
+    /// Validate config and return all violations, config is valid if there 
+    /// are none.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group()
+    ///     .field("name", Schema::string().required());
+    /// 
+    /// let cfg = Config::new();
+    /// let violations = schema.validate(&cfg);
+    /// assert_eq!(violations[0].path(), "name");
+    /// assert_eq!(violations[0].message(), "missing required setting");
+    /// ```
+    pub fn validate(&self, config : &Config) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        if let Some(root) = config.root_setting() {
+            self.check(root, &mut violations);
+        }
+        violations
+    }
+
+    // Validate setting against schema.
+    fn check(&self, setting : Setting, violations : &mut Vec<Violation>) {
+        let kind = merge::setting_type(setting);
+        let expected = self.setting_type();
+        if !matches!(self.kind, SchemaKind::Any) && kind != expected && 
+            !(kind == raw::CONFIG_TYPE_INT && 
+                expected == raw::CONFIG_TYPE_INT64) {
+            violations.push(Violation::new(setting, format!(
+                "expected {}, found {}", type_name(expected), 
+                type_name(kind))));
+            return;
+        }
+
+        match &self.kind {
+            SchemaKind::Group(fields, allow_unknown) => {
+                for (name, schema) in fields {
+                    match member(setting, name) {
+                        Some(member) => { schema.check(member, violations); },
+                        None if schema.required => {
+                            let mut violation = Violation::new(setting, 
+                                String::from("missing required setting"));
+                            violation.path = join_path(&violation.path, name);
+                            violations.push(violation);
+                        },
+                        None => {}
+                    }
+                }
+
+                if !allow_unknown {
+                    for index in 0..merge::length(setting) {
+                        let member = merge::element(setting, index);
+                        let member_name = merge::setting_name(member);
+                        if !fields.iter().any(|(name, _)| { 
+                            *name == member_name 
+                        }) {
+                            violations.push(Violation::new(member, 
+                                String::from("unknown setting")));
+                        }
+                    }
+                }
+            },
+            SchemaKind::Array(element) | SchemaKind::List(element) => {
+                self.check_length(setting, merge::length(setting) as usize,
+                    "elements", violations);
+                for index in 0..merge::length(setting) {
+                    element.check(merge::element(setting, index), violations);
+                }
+            },
+            _ => {
+                self.check_value(setting, violations);
+            }
+        }
+    }
+
+    // Validate scalar value constraints.
+    fn check_value(&self, setting : Setting, violations : &mut Vec<Violation>) {
+        let value = {
+            match SchemaValue::from_setting(setting) {
+                Some(value) => { value },
+                None => { return; }
+            }
+        };
+        let shown = {
+            if config::is_secret(setting) {
+                diff::render_redacted_value(setting)
+            } else {
+                value.to_string()
+            }
+        };
+
+        if let Some(min) = &self.min {
+            if value.compare(min) == Some(Ordering::Less) {
+                violations.push(Violation::new(setting, format!(
+                    "value {} is less than {}", shown, min)));
+            }
+        }
+        if let Some(max) = &self.max {
+            if value.compare(max) == Some(Ordering::Greater) {
+                violations.push(Violation::new(setting, format!(
+                    "value {} is greater than {}", shown, max)));
+            }
+        }
+        if !self.values.is_empty() && !self.values.iter().any(|item| { 
+            value.compare(item) == Some(Ordering::Equal) 
+        }) {
+            let values : Vec<String> = self.values.iter()
+                .map(|value| { value.to_string() }).collect();
+            violations.push(Violation::new(setting, format!(
+                "value {} is not one of {}", shown, values.join(", "))));
+        }
+
+        if let SchemaValue::Str(text) = &value {
+            self.check_length(setting, text.chars().count(), "characters", 
+                violations);
+            
+            match &self.pattern {
+                Some(Ok(pattern)) if !pattern.is_match(text) => {
+                    violations.push(Violation::new(setting, format!(
+                        "value {} does not match pattern {:?}", shown, 
+                        pattern.as_str())));
+                },
+                Some(Err(message)) => {
+                    violations.push(Violation::new(setting, message.clone()));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Validate length of string or collection.
+    fn check_length(&self, setting : Setting, length : usize, unit : &str,
+        violations : &mut Vec<Violation>) {
+        
+        if let Some(min) = self.min_length {
+            if length < min {
+                violations.push(Violation::new(setting, format!(
+                    "length {} is less than {} {}", length, min, unit)));
+            }
+        }
+        if let Some(max) = self.max_length {
+            if length > max {
+                violations.push(Violation::new(setting, format!(
+                    "length {} is greater than {} {}", length, max, unit)));
+            }
+        }
+    }
+
+    // Return libconfig type of setting described by schema.
+    fn setting_type(&self) -> i16 {
+        match &self.kind {
+            SchemaKind::Any => { raw::CONFIG_TYPE_NONE },
+            SchemaKind::Value(value_type) => {
+                match value_type {
+                    OptionType::IntegerType => { raw::CONFIG_TYPE_INT },
+                    OptionType::Int64Type => { raw::CONFIG_TYPE_INT64 },
+                    OptionType::FloatType => { raw::CONFIG_TYPE_FLOAT },
+                    OptionType::StringType => { raw::CONFIG_TYPE_STRING },
+                    OptionType::BooleanType => { raw::CONFIG_TYPE_BOOL }
+                }
+            },
+            SchemaKind::Group(_, _) => { raw::CONFIG_TYPE_GROUP },
+            SchemaKind::Array(_) => { raw::CONFIG_TYPE_ARRAY },
+            SchemaKind::List(_) => { raw::CONFIG_TYPE_LIST }
+        }
+    }
+}
+
+impl SchemaValue {
+
+    // Read scalar value of setting.
+    pub(crate) fn from_setting(setting : Setting) -> Option<SchemaValue> {
+        let reader = OptionReader::new(Some(setting));
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_INT => { 
+                reader.as_int32().map(|value| { 
+                    SchemaValue::Integer(value as i64) 
+                }) 
+            },
+            raw::CONFIG_TYPE_INT64 => { 
+                reader.as_int64().map(SchemaValue::Integer) 
+            },
+            raw::CONFIG_TYPE_FLOAT => { 
+                reader.as_float64().map(SchemaValue::Float) 
+            },
+            raw::CONFIG_TYPE_BOOL => { 
+                reader.as_bool().map(SchemaValue::Boolean) 
+            },
+            raw::CONFIG_TYPE_STRING => { 
+                reader.as_string().map(SchemaValue::Str) 
+            },
+            _ => { None }
+        }
+    }
+
+    // Compare values, numbers are compared by value.
+    pub(crate) fn compare(&self, other : &SchemaValue) -> Option<Ordering> {
+        match (self, other) {
+            (SchemaValue::Integer(first), SchemaValue::Integer(second)) => {
+                Some(first.cmp(second))
+            },
+            (SchemaValue::Integer(first), SchemaValue::Float(second)) => {
+                (*first as f64).partial_cmp(second)
+            },
+            (SchemaValue::Float(first), SchemaValue::Integer(second)) => {
+                first.partial_cmp(&(*second as f64))
+            },
+            (SchemaValue::Float(first), SchemaValue::Float(second)) => {
+                first.partial_cmp(second)
+            },
+            (SchemaValue::Boolean(first), SchemaValue::Boolean(second)) => {
+                Some(first.cmp(second))
+            },
+            (SchemaValue::Str(first), SchemaValue::Str(second)) => {
+                Some(first.cmp(second))
+            },
+            _ => { None }
+        }
+    }
+}
+
+impl fmt::Display for SchemaValue {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaValue::Integer(value) => { write!(f, "{}", value) },
+            SchemaValue::Float(value) => { write!(f, "{:?}", value) },
+            SchemaValue::Boolean(value) => { write!(f, "{}", value) },
+            SchemaValue::Str(value) => { write!(f, "{:?}", value) }
+        }
+    }
+}
+
+impl From<i32> for SchemaValue {
+    fn from(value : i32) -> SchemaValue {
+        SchemaValue::Integer(value as i64)
+    }
+}
+
+impl From<i64> for SchemaValue {
+    fn from(value : i64) -> SchemaValue {
+        SchemaValue::Integer(value)
+    }
+}
+
+impl From<f64> for SchemaValue {
+    fn from(value : f64) -> SchemaValue {
+        SchemaValue::Float(value)
+    }
+}
+
+impl From<bool> for SchemaValue {
+    fn from(value : bool) -> SchemaValue {
+        SchemaValue::Boolean(value)
+    }
+}
+
+impl From<&str> for SchemaValue {
+    fn from(value : &str) -> SchemaValue {
+        SchemaValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for SchemaValue {
+    fn from(value : String) -> SchemaValue {
+        SchemaValue::Str(value)
+    }
+}
+
+impl Violation {
+
+    // Constructor.
+    fn new(setting : Setting, message : String) -> Violation {
+        let reader = OptionReader::new(Some(setting));
+        Violation {
+            path : merge::setting_path(setting),
+            file : reader.source_file(),
+            line : reader.source_line(),
+            message
+        }
+    }
+
+    /// Return path of setting.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group().field("port", Schema::int32());
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = \"http\";").unwrap();
+    /// assert_eq!(schema.validate(&cfg)[0].path(), "port");
+    /// ```
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return name of file setting was read from. Violations of missing 
+    /// settings refer to their group.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// use std::path::Path;
+    /// 
+    /// let schema = Schema::group().field("port", Schema::int32());
+    /// let mut cfg = Config::new();
+    /// if cfg.load_from_file(Path::new("/etc/app.cfg")).is_ok() {
+    ///     for violation in schema.validate(&cfg) {
+    ///         println!("{:?}", violation.file());
+    ///     }
+    /// }
+    /// ```
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Return line number of setting.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group().field("port", Schema::int32());
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("\nport = \"http\";").unwrap();
+    /// assert_eq!(schema.validate(&cfg)[0].line(), Some(2));
+    /// ```
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Return violation description.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::schema::Schema;
+    /// 
+    /// let schema = Schema::group().field("port", Schema::int32());
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = \"http\";").unwrap();
+    /// assert_eq!(schema.validate(&cfg)[0].message(), 
+    ///     "expected integer, found string");
+    /// ```
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Violation is shown as `file:line: path: message`.
+impl fmt::Display for Violation {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => { write!(f, "{}:{}: ", file, line)?; },
+            (Some(file), None) => { write!(f, "{}: ", file)?; },
+            (None, Some(line)) => { write!(f, "line {}: ", line)?; },
+            (None, None) => {}
+        }
+
+        match self.path.as_str() {
+            "" => { write!(f, "{}", self.message) },
+            path => { write!(f, "{}: {}", path, self.message) }
+        }
+    }
+}
+
+// Find group member by name.
+fn member(group : Setting, name : &str) -> Option<Setting> {
+    (0..merge::length(group)).map(|index| { merge::element(group, index) })
+        .find(|setting| { merge::setting_name(*setting) == name })
+}
+
+// Join group path and member name.
+pub(crate) fn join_path(path : &str, name : &str) -> String {
+    match path {
+        "" => { name.to_string() },
+        path => { format!("{}.{}", path, name) }
+    }
+}
+
+// Return libconfig type of setting holding value, None if value doesn't fit
+// schema type.
+fn value_type(kind : &SchemaKind, value : &SchemaValue) -> Option<i16> {
+    match (kind, value) {
+        (SchemaKind::Value(OptionType::IntegerType), 
+            SchemaValue::Integer(value)) => {
+            i32::try_from(*value).ok().map(|_| { raw::CONFIG_TYPE_INT })
+        },
+        (SchemaKind::Value(OptionType::Int64Type), SchemaValue::Integer(_)) => {
+            Some(raw::CONFIG_TYPE_INT64)
+        },
+        (SchemaKind::Value(OptionType::FloatType), SchemaValue::Integer(_)) |
+        (SchemaKind::Value(OptionType::FloatType), SchemaValue::Float(_)) => {
+            Some(raw::CONFIG_TYPE_FLOAT)
+        },
+        (SchemaKind::Value(OptionType::BooleanType), 
+            SchemaValue::Boolean(_)) => {
+            Some(raw::CONFIG_TYPE_BOOL)
+        },
+        (SchemaKind::Value(OptionType::StringType), SchemaValue::Str(_)) => {
+            Some(raw::CONFIG_TYPE_STRING)
+        },
+        (SchemaKind::Any, SchemaValue::Integer(value)) => {
+            match i32::try_from(*value) {
+                Ok(_) => { Some(raw::CONFIG_TYPE_INT) },
+                Err(_) => { Some(raw::CONFIG_TYPE_INT64) }
+            }
+        },
+        (SchemaKind::Any, SchemaValue::Float(_)) => { 
+            Some(raw::CONFIG_TYPE_FLOAT) 
+        },
+        (SchemaKind::Any, SchemaValue::Boolean(_)) => { 
+            Some(raw::CONFIG_TYPE_BOOL) 
+        },
+        (SchemaKind::Any, SchemaValue::Str(_)) => { 
+            Some(raw::CONFIG_TYPE_STRING) 
+        },
+        _ => { None }
+    }
+}
+
+// Write value to scalar setting of type returned by value_type.
+fn write_value(setting : Setting, value : &SchemaValue) {
+    unsafe {
+        match (merge::setting_type(setting), value) {
+            (raw::CONFIG_TYPE_INT, SchemaValue::Integer(value)) => {
+                raw::config_setting_set_int(setting, *value as i32);
+            },
+            (raw::CONFIG_TYPE_INT64, SchemaValue::Integer(value)) => {
+                raw::config_setting_set_int64(setting, *value);
+            },
+            (raw::CONFIG_TYPE_FLOAT, SchemaValue::Integer(value)) => {
+                raw::config_setting_set_float(setting, *value as f64);
+            },
+            (raw::CONFIG_TYPE_FLOAT, SchemaValue::Float(value)) => {
+                raw::config_setting_set_float(setting, *value);
+            },
+            (raw::CONFIG_TYPE_BOOL, SchemaValue::Boolean(value)) => {
+                raw::config_setting_set_bool(setting, *value as i32);
+            },
+            (raw::CONFIG_TYPE_STRING, SchemaValue::Str(value)) => {
+                let value = CString::new(value.as_str()).unwrap_or_default();
+                raw::config_setting_set_string(setting, value.as_ptr());
+            },
+            _ => {}
+        }
+    }
+}
+
+// Return name of libconfig type used in violations.
+pub(crate) fn type_name(kind : i16) -> &'static str {
+    match kind {
+        raw::CONFIG_TYPE_INT => { "integer" },
+        raw::CONFIG_TYPE_INT64 => { "64bit integer" },
+        raw::CONFIG_TYPE_FLOAT => { "float" },
+        raw::CONFIG_TYPE_BOOL => { "boolean" },
+        raw::CONFIG_TYPE_STRING => { "string" },
+        raw::CONFIG_TYPE_GROUP => { "group" },
+        raw::CONFIG_TYPE_ARRAY => { "array" },
+        raw::CONFIG_TYPE_LIST => { "list" },
+        _ => { "any" }
+    }
+}
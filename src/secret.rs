@@ -0,0 +1,157 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{Errors, Result};
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+/// Prefix of string values referring to secrets: `secret:<provider>:<name>`.
+pub const SECRET_PREFIX : &str = "secret:";
+
+/// Text shown instead of secret values.
+pub const REDACTED : &str = "[REDACTED]";
+
+/// Sensitive string value. Memory is overwritten with zeros on drop and value
+/// is redacted in `Debug` and `Display` output.
+#[derive(Clone, PartialEq)]
+pub struct Secret {
+    value : String
+}
+
+/// Provider of secrets referred by `secret:<provider>:<name>` values.
+pub trait SecretResolver {
+
+    /// Return secret of provider by name.
+    fn resolve(&self, provider : &str, name : &str) -> Result<Secret>;
+}
+
+/// Secret resolver reading `secret:file:<path>` from file, trailing line 
+/// break is removed, and `secret:env:<name>` from environment variable.
+pub struct DefaultSecretResolver;
+
+impl Secret {
+
+    /// Constructor.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::secret::Secret;
+    /// 
+    /// let secret = Secret::new(String::from("password"));
+    /// assert_eq!(format!("{:?}", secret), "[REDACTED]");
+    /// ```
+    pub fn new(value : String) -> Secret {
+        Secret { value }
+    }
+
+    /// Return secret value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::secret::Secret;
+    /// 
+    /// let secret = Secret::new(String::from("password"));
+    /// assert_eq!(secret.expose(), "password");
+    /// ```
+    pub fn expose(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        let bytes = unsafe { self.value.as_mut_vec() };
+        for byte in bytes.iter_mut() {
+            unsafe { ptr::write_volatile(byte, 0); }
+        }
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl SecretResolver for DefaultSecretResolver {
+    fn resolve(&self, provider : &str, name : &str) -> Result<Secret> {
+        match provider {
+            "file" => {
+                let mut value = fs::read_to_string(name).map_err(|error| {
+                    Errors::SecretError(format!("secret file {}: {}", name, 
+                        error))
+                })?;
+                
+                let length = value.trim_end_matches(['\r', '\n']).len();
+                value.truncate(length);
+                Ok(Secret::new(value))
+            },
+            "env" => {
+                env::var(name).map(Secret::new).map_err(|_| {
+                    Errors::SecretError(format!(
+                        "secret environment variable {} is not set", name))
+                })
+            },
+            _ => {
+                Err(Errors::SecretError(format!("unknown secret provider {}", 
+                    provider)))
+            }
+        }
+    }
+}
+
+/// Resolver is called with provider and name of `secret:<provider>:<name>`.
+impl<F> SecretResolver for F where F: Fn(&str, &str) -> Result<Secret> {
+    fn resolve(&self, provider : &str, name : &str) -> Result<Secret> {
+        self(provider, name)
+    }
+}
+
+// Return provider and name of secret reference.
+pub(crate) fn parse_reference(value : &str) -> Option<Result<(&str, &str)>> {
+    let reference = value.strip_prefix(SECRET_PREFIX)?;
+    
+    Some(reference.split_once(':').ok_or_else(|| {
+        Errors::SecretError(format!(
+            "invalid secret reference {:?}, expected secret:<provider>:<name>",
+            value))
+    }))
+}
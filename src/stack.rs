@@ -0,0 +1,361 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{self, Config, OptionReader, Result};
+use crate::merge::{CollectionMerge, MergePolicy, Merger, ScalarMerge, 
+    Setting};
+
+use std::ops::Deref;
+use std::path::Path;
+
+/// Layered configuration. Lookups are resolved through the stack of configs,
+/// later layers override earlier ones: groups are merged, other settings are
+/// replaced and arrays and lists are merged by collection merge strategy.
+pub struct ConfigStack {
+    layers : Vec<Layer>,
+    collections : CollectionMerge,
+    effective : Config
+}
+
+/// Configuration layer.
+struct Layer {
+    name : String,
+    config : Config
+}
+
+/// Layer effective value came from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Origin {
+    layer : String,
+    file : Option<String>,
+    line : Option<u32>
+}
+
+/// Effective value of configuration stack.
+pub struct StackValue {
+    value : OptionReader,
+    origin : Option<Origin>
+}
+
+impl ConfigStack {
+
+    /// Constructor.
+    /// Create empty configuration stack, collections are replaced by later 
+    /// layers.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let stack = ConfigStack::new();
+    /// ```
+    pub fn new() -> ConfigStack {
+        ConfigStack {
+            layers : Vec::new(),
+            collections : CollectionMerge::Replace,
+            effective : Config::new()
+        }
+    }
+
+    /// Set merge strategy of arrays and lists.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::merge::CollectionMerge;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.set_collection_merge(CollectionMerge::Append);
+    /// ```
+    pub fn set_collection_merge(&mut self, collections : CollectionMerge) {
+        self.collections = collections;
+        self.rebuild();
+    }
+
+    /// Add config as top layer of the stack.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut vendor = Config::new();
+    /// vendor.load_from_string("port = 8080;").unwrap();
+    /// let mut host = Config::new();
+    /// host.load_from_string("port = 9090;").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("vendor", vendor);
+    /// stack.push("host", host);
+    /// assert_eq!(stack.value("port").unwrap().as_int32(), Some(9090));
+    /// ```
+    pub fn push<S>(&mut self, name : S, config : Config) 
+        where S: Into<String> {
+        
+        self.layers.push(Layer { name : name.into(), config });
+        self.merge_layer(self.layers.len() - 1);
+    }
+
+    /// Load config file and add it as top layer of the stack, layer is named 
+    /// by file name.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::stack::ConfigStack;
+    /// use std::path::Path;
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// if stack.push_file(Path::new("/etc/app/vendor.cfg")).is_ok() {
+    ///     /* ... */
+    /// }
+    /// ```
+    pub fn push_file(&mut self, file_name : &Path) -> Result<()> {
+        let mut config = Config::new();
+        config.load_from_file(file_name)?;
+        
+        self.push(file_name.to_string_lossy(), config);
+        Ok(())
+    }
+
+    /// Return names of layers from the bottom to the top of the stack.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("vendor", Config::new());
+    /// stack.push("site", Config::new());
+    /// assert_eq!(stack.layers(), vec!["vendor", "site"]);
+    /// ```
+    pub fn layers(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| { layer.name.as_str() }).collect()
+    }
+
+    /// Return config of layer.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("vendor", Config::new());
+    /// assert!(stack.layer("vendor").is_some());
+    /// ```
+    pub fn layer(&self, name : &str) -> Option<&Config> {
+        self.layers.iter().rev().find(|layer| { layer.name == name })
+            .map(|layer| { &layer.config })
+    }
+
+    /// Return effective config composed of all layers.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut site = Config::new();
+    /// site.load_from_string("port = 8080;").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("site", site);
+    /// assert_eq!(stack.config().save_to_string().unwrap(), "port = 8080;\n");
+    /// ```
+    pub fn config(&self) -> &Config {
+        &self.effective
+    }
+
+    /// Read effective value from path.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut vendor = Config::new();
+    /// vendor.load_from_string("server = { host = \"localhost\"; };").unwrap();
+    /// let mut host = Config::new();
+    /// host.load_from_string("server = { port = 9090; };").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("vendor", vendor);
+    /// stack.push("host", host);
+    /// 
+    /// let host = stack.value("server.host").unwrap();
+    /// assert_eq!(host.as_string().unwrap(), "localhost");
+    /// assert_eq!(host.origin().unwrap().layer(), "vendor");
+    /// ```
+    pub fn value<S>(&self, path : S) -> Option<StackValue> 
+        where S: Into<String> {
+        
+        let value = self.effective.value(path)?;
+        let origin = value.element.and_then(|element| {
+            config::setting_state(element)?.origin.borrow().clone()
+        });
+
+        Some(StackValue { value, origin })
+    }
+
+    // Merge all layers into new effective config.
+    fn rebuild(&mut self) {
+        self.effective = Config::new();
+
+        for index in 0..self.layers.len() {
+            self.merge_layer(index);
+        }
+    }
+
+    // Merge layer into effective config.
+    fn merge_layer(&mut self, index : usize) {
+        let (target, source) = {
+            match (self.effective.root_setting(), 
+                self.layers[index].config.root_setting()) {
+                (Some(target), Some(source)) => { (target, source) },
+                _ => { return; }
+            }
+        };
+
+        let layer = &self.layers[index].name;
+        let mut observer = |setting : Setting, source : Setting| {
+            let source = OptionReader::new(Some(source));
+            *config::setting_data(setting).origin.borrow_mut() = Some(Origin {
+                layer : layer.clone(),
+                file : source.source_file(),
+                line : source.source_line()
+            });
+        };
+
+        let policy = MergePolicy::new(ScalarMerge::Overwrite, 
+            self.collections);
+        Merger::new(policy, &mut observer)
+            .merge_group(target, source);
+    }
+}
+
+impl Default for ConfigStack {
+    fn default() -> ConfigStack {
+        ConfigStack::new()
+    }
+}
+
+impl Origin {
+
+    /// Return name of layer.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut site = Config::new();
+    /// site.load_from_string("port = 8080;").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("site", site);
+    /// assert_eq!(stack.value("port").unwrap().origin().unwrap().layer(), 
+    ///     "site");
+    /// ```
+    pub fn layer(&self) -> &str {
+        &self.layer
+    }
+
+    /// Return name of file value was read from.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::stack::ConfigStack;
+    /// use std::path::Path;
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// if stack.push_file(Path::new("/etc/app/host.cfg")).is_ok() {
+    ///     match stack.value("port").and_then(|value| { 
+    ///         value.origin().cloned() 
+    ///     }) {
+    ///         Some(origin) => { println!("{:?}", origin.file()); },
+    ///         None => { /* ... */ }
+    ///     }
+    /// }
+    /// ```
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Return line number value was read from.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut site = Config::new();
+    /// site.load_from_string("name = \"app\";\nport = 8080;").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("site", site);
+    /// assert_eq!(stack.value("port").unwrap().origin().unwrap().line(), 
+    ///     Some(2));
+    /// ```
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+}
+
+impl StackValue {
+
+    /// Return layer value came from. Root group has no origin.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::stack::ConfigStack;
+    /// 
+    /// let mut vendor = Config::new();
+    /// vendor.load_from_string("port = 8080;").unwrap();
+    /// 
+    /// let mut stack = ConfigStack::new();
+    /// stack.push("vendor", vendor);
+    /// stack.push("host", Config::new());
+    /// assert_eq!(stack.value("port").unwrap().origin().unwrap().layer(), 
+    ///     "vendor");
+    /// ```
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+}
+
+impl Deref for StackValue {
+    type Target = OptionReader;
+
+    fn deref(&self) -> &OptionReader {
+        &self.value
+    }
+}
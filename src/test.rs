@@ -28,9 +28,38 @@
 /*                                                                            */
 /******************************************************************************/
 
-use crate::config::{Config, OptionType};
-use std::path::Path;
+use libconfig_sys as raw;
+use crate::cli::CliOverrides;
+use crate::config::{self, Config, ConfigOption, Errors, OptionReader, 
+    OptionType};
+use crate::diff::{self, ChangeKind};
+use crate::document::{Comments, Document};
+use crate::env::EnvOverlay;
+use crate::include::IncludeSource;
+use crate::interpolate::Interpolator;
+use crate::json::JsonMode;
+use crate::schema::Schema;
+use crate::secret::Secret;
+use crate::merge::{CollectionMerge, MergePolicy, ScalarMerge};
+use crate::patch::Patch;
+use crate::query::Query;
+use crate::stack::ConfigStack;
+#[cfg(feature = "derive")]
+use crate::typed::{self, LibConfig};
+use crate::vfs::{ConfigFs, MemoryFs};
+use crate::visit::{ConfigVisitor, ConfigVisitorMut, OptionEditor};
+use crate::watch::{ConfigWatcher, Reload};
+use std::path::{Path, PathBuf};
+use std::cell::Cell;
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::raw::c_void;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::fs;
+use std::io;
+use std::time::Duration;
 
 macro_rules! assert_delta {
     ($x:expr, $y:expr, $d:expr) => {
@@ -171,4 +200,2212 @@ fn test_collection_options() {
         }
         counter += 1;
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_config_options() {
+    let mut cfg = Config::new();
+    
+    cfg.set_option(ConfigOption::AllowOverrides, true);
+    assert!(cfg.option(ConfigOption::AllowOverrides));
+    cfg.set_option(ConfigOption::AllowOverrides, false);
+    assert!(!cfg.option(ConfigOption::AllowOverrides));
+
+    cfg.set_option(ConfigOption::Fsync, true);
+    assert!(cfg.option(ConfigOption::Fsync));
+    cfg.set_option(ConfigOption::AllowScientificNotation, true);
+    assert!(cfg.option(ConfigOption::AllowScientificNotation));
+
+    cfg.set_float_precision(3);
+    assert_eq!(cfg.float_precision(), 3);
+}
+
+#[test]
+fn test_clear_config() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string("value = 1; group : { item = 2; };").is_ok());
+    assert!(cfg.value("group.item").is_some());
+
+    cfg.clear();
+    assert!(cfg.value("value").is_none());
+    assert!(cfg.value("group").is_none());
+    assert!(cfg.root().write_int32("value", 3).is_some());
+    assert_eq!(cfg.value("value").unwrap().as_int32().unwrap(), 3);
+}
+
+/// Count of setting hooks passed to destructor.
+static RELEASED_HOOKS : AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn release_hook(_hook : *mut c_void) {
+    RELEASED_HOOKS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn test_setting_hooks() {
+    let mut marker = 0u8;
+    let marker = &mut marker as *mut u8 as *mut c_void;
+    let name = CString::new("value").unwrap();
+
+    let mut config = MaybeUninit::<raw::config_t>::uninit();
+    unsafe {
+        let config = config.as_mut_ptr();
+        raw::config_init(config);
+        raw::config_set_hook(config, marker);
+        assert_eq!(raw::config_get_hook(config), marker);
+        raw::config_set_destructor(config, release_hook);
+
+        let root = raw::config_root_setting(config);
+        let setting = raw::config_setting_add(root, name.as_ptr(), 
+            raw::CONFIG_TYPE_INT as i32);
+        assert!(raw::config_setting_get_hook(setting).is_null());
+        raw::config_setting_set_hook(setting, marker);
+        assert_eq!(raw::config_setting_get_hook(setting), marker);
+
+        assert_eq!(raw::config_setting_remove(root, name.as_ptr()), 
+            raw::CONFIG_TRUE);
+        assert_eq!(RELEASED_HOOKS.load(Ordering::SeqCst), 1);
+        raw::config_destroy(config);
+    }
+
+    let value = Rc::new(String::from("listener"));
+    let cfg = Config::new();
+    assert!(cfg.root().write_int32("port", 8080).unwrap()
+        .write_hook("port", value.clone()).is_some());
+    assert!(cfg.root().write_hook("missing", value.clone()).is_none());
+    assert_eq!(Rc::strong_count(&value), 2);
+    assert_eq!(cfg.value("port").unwrap().hook::<Rc<String>>().unwrap(), 
+        value);
+    assert_eq!(cfg.value("port").unwrap().hook::<String>(), None);
+    assert_eq!(cfg.root_reader().unwrap().hook::<Rc<String>>(), None);
+
+    cfg.root().write_hook("port", 1);
+    assert_eq!(Rc::strong_count(&value), 1);
+    assert_eq!(cfg.value("port").unwrap().hook::<i32>(), Some(1));
+
+    cfg.root().write_hook("port", value.clone());
+    assert!(cfg.value("port").unwrap().delete().is_ok());
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    cfg.root().write_bool("debug", true).unwrap()
+        .write_hook("debug", value.clone());
+    drop(cfg);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn test_setting_state_released() {
+    let mut defaults = Config::new();
+    assert!(defaults.load_from_string("port = 8080;").is_ok());
+
+    let mut cfg = Config::new();
+    for _ in 0..16 {
+        assert!(cfg.apply_defaults(&defaults).is_ok());
+        assert!(cfg.root().write_string("password", "secret").unwrap()
+            .write_comment("password", "Database password").is_some());
+        assert!(cfg.value("password").unwrap().as_secret().is_ok());
+        assert!(cfg.value("port").unwrap().is_defaulted());
+        assert!(cfg.value("port").unwrap().delete().is_ok());
+        assert!(cfg.value("password").unwrap().delete().is_ok());
+
+        cfg.root().write_int32("port", 80).unwrap()
+            .write_string("password", "open");
+        assert!(!cfg.value("port").unwrap().is_defaulted());
+        assert!(cfg.value("password").unwrap().comments().leading()
+            .is_empty());
+        assert_eq!(cfg.render_redacted().unwrap(),
+            "port = 80;\npassword = \"open\";\n");
+        cfg.reset_access_tracking();
+        assert_eq!(cfg.unused_settings().len(), 2);
+        assert!(cfg.value("port").unwrap().delete().is_ok());
+        assert!(cfg.value("password").unwrap().delete().is_ok());
+    }
+
+    let mut stack = ConfigStack::new();
+    stack.push("defaults", defaults);
+    assert_eq!(stack.value("port").unwrap().origin().unwrap().layer(),
+        "defaults");
+}
+
+/// Message passed to fatal error function.
+static FATAL_ERROR : Mutex<String> = Mutex::new(String::new());
+
+#[test]
+fn test_fatal_error_func() {
+    Config::set_fatal_error_func(|message : &str| {
+        *FATAL_ERROR.lock().unwrap() = message.to_string();
+    });
+    let message = CString::new("out of memory").unwrap();
+    config::fatal_error_func_callback(message.as_ptr());
+    assert_eq!(*FATAL_ERROR.lock().unwrap(), "out of memory");
+
+    Config::set_fatal_error_func(|_message : &str| { panic!(); });
+    config::fatal_error_func_callback(message.as_ptr());
+    unsafe { raw::config_set_fatal_error_func(None); }
+}
+
+#[test]
+fn test_include_func() {
+    assert!(fs::write("include_func_first.cfg", "first = 1;").is_ok());
+    assert!(fs::write("include_func_second.cfg", "second = 2;").is_ok());
+
+    let mut cfg = Config::new();
+    cfg.set_include_func(|_dir : &Path, path : &str| {
+        match path {
+            "both" => { 
+                Ok(vec![PathBuf::from("include_func_first.cfg"), 
+                    PathBuf::from("include_func_second.cfg")])
+            },
+            _ => { Err(format!("unknown include {}", path)) }
+        }
+    });
+
+    assert!(cfg.load_from_string("@include \"both\"\nthird = 3;").is_ok());
+    assert_eq!(cfg.value("first").unwrap().as_int32().unwrap(), 1);
+    assert_eq!(cfg.value("second").unwrap().as_int32().unwrap(), 2);
+    assert_eq!(cfg.value("third").unwrap().as_int32().unwrap(), 3);
+
+    assert!(cfg.load_from_string("@include \"missing\"\n").is_err());
+
+    assert!(fs::remove_file("include_func_first.cfg").is_ok());
+    assert!(fs::remove_file("include_func_second.cfg").is_ok());
+}
+
+#[test]
+fn test_include_resolver() {
+    assert!(fs::write("include_resolver_file.cfg", "from_file = 1;").is_ok());
+
+    let mut cfg = Config::new();
+    cfg.set_include_resolver(|_dir : &Path, path : &str| {
+        match path {
+            "all" => { 
+                Ok(vec![
+                    IncludeSource::Memory {
+                        name : String::from("memory:defaults"),
+                        contents : String::from(
+                            "\nfrom_memory = 2; // in memory")
+                    },
+                    IncludeSource::File(
+                        PathBuf::from("include_resolver_file.cfg"))
+                ])
+            },
+            _ => { Err(Errors::IncludeError(format!("unknown {}", path))) }
+        }
+    });
+
+    assert!(cfg.load_from_string("@include \"all\"\nlocal = 3;").is_ok());
+    assert_eq!(cfg.value("from_memory").unwrap().as_int32().unwrap(), 2);
+    assert_eq!(cfg.value("from_memory").unwrap().source_file().unwrap(), 
+        "memory:defaults");
+    assert_eq!(cfg.value("from_memory").unwrap().source_line().unwrap(), 2);
+    assert_eq!(cfg.value("from_memory").unwrap().comments().trailing(), 
+        Some("in memory"));
+    assert_eq!(cfg.value("from_file").unwrap().as_int32().unwrap(), 1);
+    assert_eq!(cfg.value("from_file").unwrap().source_file().unwrap(), 
+        "include_resolver_file.cfg");
+    assert_eq!(cfg.value("local").unwrap().source_file(), None);
+    assert_eq!(cfg.value("local").unwrap().source_line().unwrap(), 2);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let prefix = format!("librustconfig-{}-", std::process::id());
+        let dirs : Vec<PathBuf> = fs::read_dir(std::env::temp_dir()).unwrap()
+            .map(|entry| { entry.unwrap() })
+            .filter(|entry| { 
+                entry.file_name().to_string_lossy().starts_with(&prefix) 
+            }).map(|entry| { entry.path() }).collect();
+        #[cfg(target_os = "linux")]
+        assert!(dirs.is_empty());
+        for dir in dirs {
+            let metadata = fs::symlink_metadata(dir).unwrap();
+            assert!(metadata.is_dir());
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o700);
+        }
+    }
+
+    assert!(cfg.load_from_string("@include \"unknown\"\n").is_err());
+
+    assert!(fs::remove_file("include_resolver_file.cfg").is_ok());
+}
+
+#[test]
+fn test_include_glob() {
+    let dir = Path::new("include_glob_test");
+    assert!(fs::create_dir_all(dir.join("conf.d")).is_ok());
+    assert!(fs::write(dir.join("conf.d/20-second.cfg"), 
+        "value = 2; second = true;").is_ok());
+    assert!(fs::write(dir.join("conf.d/10-first.cfg"), 
+        "value = 1; first = true;").is_ok());
+    assert!(fs::write(dir.join("conf.d/notes.txt"), "broken").is_ok());
+    assert!(fs::write(dir.join("main.cfg"), 
+        "@include \"conf.d/*.cfg\"\n").is_ok());
+    assert!(fs::write(dir.join("dir.cfg"), "@include \"conf.d\"\n").is_ok());
+
+    let mut cfg = Config::new();
+    cfg.include_dir(dir);
+    cfg.set_option(ConfigOption::AllowOverrides, true);
+
+    assert!(cfg.load_from_file(&dir.join("main.cfg")).is_ok());
+    assert_eq!(cfg.value("value").unwrap().as_int32().unwrap(), 2);
+    assert!(cfg.value("first").unwrap().as_bool().unwrap());
+    assert!(cfg.value("second").unwrap().as_bool().unwrap());
+
+    assert!(cfg.load_from_file(&dir.join("dir.cfg")).is_ok());
+    assert_eq!(cfg.value("value").unwrap().as_int32().unwrap(), 2);
+
+    assert!(cfg.load_from_string("@include \"missing.d/*.cfg\"\nv = 1;")
+        .is_ok());
+    assert!(cfg.value("v").is_some());
+
+    assert!(fs::remove_dir_all(dir).is_ok());
+}
+
+#[test]
+fn test_include_cycle() {
+    let dir = Path::new("include_cycle_test");
+    assert!(fs::create_dir_all(dir).is_ok());
+    assert!(fs::write(dir.join("a.cfg"), "@include \"b.cfg\"\na = 1;").is_ok());
+    assert!(fs::write(dir.join("b.cfg"), "@include \"a.cfg\"\nb = 1;").is_ok());
+    assert!(fs::write(dir.join("c.cfg"), "@include \"d.cfg\"\nc = 1;").is_ok());
+    assert!(fs::write(dir.join("d.cfg"), "d = 1;").is_ok());
+
+    let mut cfg = Config::new();
+    cfg.include_dir(dir);
+
+    match cfg.load_from_file(&dir.join("a.cfg")) {
+        Err(Errors::IncludeError(message)) => {
+            assert!(message.contains("a.cfg -> include_cycle_test/b.cfg"));
+            assert!(message.contains("cycle"));
+        },
+        _ => { panic!("Include cycle must be detected!"); }
+    }
+
+    assert!(cfg.load_from_string("@include \"c.cfg\"\n").is_ok());
+    cfg.set_include_depth(1);
+    match cfg.load_from_string("@include \"c.cfg\"\n") {
+        Err(Errors::IncludeError(message)) => {
+            assert!(message.contains("<string> -> include_cycle_test/c.cfg"));
+            assert!(message.contains("depth"));
+        },
+        _ => { panic!("Include depth limit must be checked!"); }
+    }
+
+    assert!(fs::remove_dir_all(dir).is_ok());
+
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app.cfg", 
+        "glob = \"/var/log/*.log\"; # see /* docs\n@include \"a.cfg\"\n");
+    memory.insert("/etc/a.cfg", "@include \"a.cfg\"\na = 1;");
+    memory.insert("/etc/b.cfg", "b = \"a \\\" /*\";\n@include \"c.cfg\"\n");
+    memory.insert("/etc/c.cfg", "c = 1;");
+
+    let mut cfg = Config::new();
+    cfg.set_fs(memory);
+    cfg.include_dir(Path::new("/etc"));
+    match cfg.load_from_file(Path::new("/etc/app.cfg")) {
+        Err(Errors::IncludeError(message)) => {
+            assert!(message.contains("cycle"));
+        },
+        _ => { panic!("Include after \"/*\" string must be checked!"); }
+    }
+    assert!(cfg.load_from_file(Path::new("/etc/b.cfg")).is_ok());
+    assert_eq!(cfg.value("c").unwrap().as_int32(), Some(1));
+    assert_eq!(cfg.value("b").unwrap().as_string(), 
+        Some(String::from("a \" /*")));
+}
+
+/// Filesystem returning new contents of files on every read until limit of
+/// reads, `N` in contents is replaced by number of read.
+struct ChangingFs {
+    fs : MemoryFs,
+    reads : Rc<Cell<usize>>,
+    limit : usize
+}
+
+impl ChangingFs {
+    fn new(fs : MemoryFs, reads : Rc<Cell<usize>>, limit : usize) 
+        -> ChangingFs {
+        ChangingFs { fs, reads, limit }
+    }
+}
+
+impl ConfigFs for ChangingFs {
+    fn read(&self, path : &Path) -> io::Result<String> {
+        self.reads.set(self.reads.get() + 1);
+        self.fs.read(path).map(|contents| {
+            contents.replace("N", &self.reads.get().min(self.limit)
+                .to_string())
+        })
+    }
+
+    fn write(&self, path : &Path, contents : &str) -> io::Result<()> {
+        self.fs.write(path, contents)
+    }
+
+    fn exists(&self, path : &Path) -> bool {
+        self.fs.exists(path)
+    }
+
+    fn list(&self, path : &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.list(path)
+    }
+
+    fn rename(&self, from : &Path, to : &Path) -> io::Result<()> {
+        self.fs.rename(from, to)
+    }
+
+    fn remove(&self, path : &Path) -> io::Result<()> {
+        ConfigFs::remove(&self.fs, path)
+    }
+}
+
+#[test]
+fn test_include_read_once() {
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app.cfg", "@include \"a.cfg\"\n@include \"a.cfg\"\n");
+    memory.insert("/etc/a.cfg", "value = N;");
+
+    let mut cfg = Config::new();
+    cfg.set_fs(ChangingFs::new(memory, Rc::new(Cell::new(0)), usize::MAX));
+    cfg.include_dir(Path::new("/etc"));
+    cfg.set_option(ConfigOption::AllowOverrides, true);
+    assert!(cfg.load_from_file(Path::new("/etc/app.cfg")).is_ok());
+    assert_eq!(cfg.value("value").unwrap().as_int32(), Some(2));
+
+    assert!(fs::write("read_error.cfg", [b'v', b'=', b'"', 0xff, b'"', 
+        b';']).is_ok());
+    assert!(matches!(Config::new().load_from_file(Path::new(
+        "read_error.cfg")), Err(Errors::ReadError(_))));
+    assert!(fs::remove_file("read_error.cfg").is_ok());
+}
+
+#[test]
+fn test_memory_fs() {
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app/app.cfg", 
+        "@include \"conf.d/*.cfg\"\nport = 8080;");
+    memory.insert("/etc/app/conf.d/10-host.cfg", "host = \"localhost\";");
+    memory.insert("/etc/app/conf.d/20-debug.cfg", "\ndebug = true;");
+
+    let mut cfg = Config::new();
+    cfg.set_fs(memory.clone());
+    cfg.include_dir(Path::new("/etc/app"));
+
+    assert!(cfg.load_from_file(Path::new("/etc/app/app.cfg")).is_ok());
+    assert_eq!(cfg.value("port").unwrap().as_int32().unwrap(), 8080);
+    assert_eq!(cfg.value("port").unwrap().source_file().unwrap(), 
+        "/etc/app/app.cfg");
+    assert_eq!(cfg.value("host").unwrap().as_string().unwrap(), "localhost");
+    assert_eq!(cfg.value("debug").unwrap().source_file().unwrap(), 
+        "/etc/app/conf.d/20-debug.cfg");
+    assert_eq!(cfg.value("debug").unwrap().source_line().unwrap(), 2);
+
+    assert_eq!(cfg.load_from_file(Path::new("/etc/app/missing.cfg")), 
+        Err(Errors::FileNotExists));
+
+    let mut out = Config::new();
+    out.set_fs(memory.clone());
+    out.root().write_int32("saved", 12);
+    assert!(out.save_to_file(Path::new("/var/app/saved.cfg")).is_ok());
+    assert_eq!(memory.get("/var/app/saved.cfg").unwrap(), 
+        out.save_to_string().unwrap());
+
+    assert!(cfg.load_from_file(Path::new("/var/app/saved.cfg")).is_ok());
+    assert_eq!(cfg.value("saved").unwrap().as_int32().unwrap(), 12);
+    assert!(!Path::new("/var/app/saved.cfg").exists());
+}
+
+/// Filesystem failing on chosen write operation, operations are counted from
+/// one.
+struct FailingFs {
+    fs : MemoryFs,
+    fail_at : usize,
+    operations : Cell<usize>
+}
+
+impl FailingFs {
+    fn new(fs : MemoryFs, fail_at : usize) -> FailingFs {
+        FailingFs { fs, fail_at, operations : Cell::new(0) }
+    }
+
+    fn operation(&self) -> io::Result<()> {
+        self.operations.set(self.operations.get() + 1);
+        if self.operations.get() == self.fail_at {
+            return Err(io::Error::other("simulated failure"));
+        }
+        Ok(())
+    }
+}
+
+impl ConfigFs for FailingFs {
+    fn read(&self, path : &Path) -> io::Result<String> {
+        self.fs.read(path)
+    }
+
+    fn write(&self, path : &Path, contents : &str) -> io::Result<()> {
+        self.operation()?;
+        self.fs.write(path, contents)
+    }
+
+    fn exists(&self, path : &Path) -> bool {
+        self.fs.exists(path)
+    }
+
+    fn list(&self, path : &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.list(path)
+    }
+
+    fn rename(&self, from : &Path, to : &Path) -> io::Result<()> {
+        self.operation()?;
+        self.fs.rename(from, to)
+    }
+
+    fn remove(&self, path : &Path) -> io::Result<()> {
+        ConfigFs::remove(&self.fs, path)
+    }
+
+    fn sync(&self, _path : &Path) -> io::Result<()> {
+        self.operation()
+    }
+}
+
+#[test]
+fn test_atomic_save() {
+    // Save steps: write temporary file, sync it, write temporary backup, sync
+    // it, rename it to backup, sync backup, rename temporary file and sync it.
+    for fail_at in 1..9 {
+        let memory = MemoryFs::new();
+        memory.insert("/etc/app.cfg", "value = 1;\n");
+
+        let mut cfg = Config::new();
+        cfg.set_fs(FailingFs::new(memory.clone(), fail_at));
+        cfg.set_save_backup(true);
+        cfg.root().write_int32("value", 2);
+
+        assert_eq!(cfg.save_to_file(Path::new("/etc/app.cfg")), 
+            Err(Errors::SaveError));
+        
+        let expected = if fail_at < 8 { "value = 1;\n" } else { "value = 2;\n" };
+        assert_eq!(memory.get("/etc/app.cfg").unwrap(), expected);
+        assert_eq!(memory.list(Path::new("/etc")).unwrap().len(), 
+            if fail_at < 6 { 1 } else { 2 });
+    }
+
+    let memory = MemoryFs::new();
+    let mut cfg = Config::new();
+    cfg.set_fs(FailingFs::new(memory.clone(), 0));
+    cfg.set_save_backup(true);
+    cfg.root().write_int32("value", 3);
+    assert!(cfg.save_to_file(Path::new("/etc/app.cfg")).is_ok());
+    assert_eq!(memory.get("/etc/app.cfg").unwrap(), "value = 3;\n");
+    assert!(memory.get("/etc/app.cfg.bak").is_none());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_atomic_save_disk() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = Path::new("atomic_save_test");
+    let file = dir.join("app.cfg");
+    fs::create_dir_all(dir).unwrap();
+    fs::write(&file, "value = 1;\n").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let mut cfg = Config::new();
+    cfg.set_save_backup(true);
+    cfg.root().write_int32("value", 2);
+    assert!(cfg.save_to_file(&file).is_ok());
+
+    assert_eq!(fs::read_to_string(&file).unwrap(), "value = 2;\n");
+    assert_eq!(fs::read_to_string(dir.join("app.cfg.bak")).unwrap(), 
+        "value = 1;\n");
+    assert_eq!(fs::metadata(&file).unwrap().permissions().mode() & 0o777, 
+        0o600);
+    assert_eq!(fs::metadata(dir.join("app.cfg.bak")).unwrap().permissions()
+        .mode() & 0o777, 0o600);
+    assert_eq!(fs::read_dir(dir).unwrap().count(), 2);
+
+    cfg.root().write_int32("value", 3);
+    assert!(cfg.save_to_file(&file).is_ok());
+    assert_eq!(fs::read_to_string(dir.join("app.cfg.bak")).unwrap(), 
+        "value = 2;\n");
+    assert_eq!(fs::read_dir(dir).unwrap().count(), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_config_stack() {
+    let mut vendor = Config::new();
+    assert!(vendor.load_from_string(r#"
+        server = {
+            host = "localhost";
+            port = 8080;
+            tls = { enabled = false; };
+        };
+        plugins = [ "auth", "log" ];
+        log = "info";"#).is_ok());
+
+    let mut site = Config::new();
+    assert!(site.load_from_string(r#"
+        server = {
+            port = 9090;
+            tls = { enabled = true; cert = "/etc/ssl/site.pem"; };
+        };
+        plugins = [ "log", "metrics" ];"#).is_ok());
+
+    let mut host = Config::new();
+    assert!(host.load_from_string(r#"
+        log = { level = "debug"; };"#).is_ok());
+
+    let mut stack = ConfigStack::new();
+    stack.push("vendor", vendor);
+    stack.push("site", site);
+    stack.push("host", host);
+    assert_eq!(stack.layers(), vec!["vendor", "site", "host"]);
+
+    assert_eq!(stack.value("server.host").unwrap().as_string().unwrap(), 
+        "localhost");
+    assert_eq!(stack.value("server.host").unwrap().origin().unwrap().layer(),
+        "vendor");
+    
+    let port = stack.value("server.port").unwrap();
+    assert_eq!(port.as_int32().unwrap(), 9090);
+    assert_eq!(port.origin().unwrap().layer(), "site");
+    assert_eq!(port.origin().unwrap().line(), Some(3));
+    assert_eq!(port.origin().unwrap().file(), None);
+
+    assert_eq!(stack.value("server.tls.enabled").unwrap().as_bool(), 
+        Some(true));
+    assert_eq!(stack.value("server.tls.cert").unwrap().origin().unwrap()
+        .layer(), "site");
+    assert_eq!(stack.value("log.level").unwrap().as_string().unwrap(), 
+        "debug");
+    assert_eq!(stack.value("log").unwrap().origin().unwrap().layer(), "host");
+    assert!(stack.value("missing").is_none());
+
+    let plugins = |stack : &ConfigStack| -> Vec<String> {
+        stack.value("plugins").unwrap().as_array()
+            .map(|value| { value.as_string().unwrap() }).collect()
+    };
+    assert_eq!(plugins(&stack), vec!["log", "metrics"]);
+
+    stack.set_collection_merge(CollectionMerge::Append);
+    assert_eq!(plugins(&stack), vec!["auth", "log", "log", "metrics"]);
+    assert_eq!(stack.value("plugins.[0]").unwrap().origin().unwrap().layer(),
+        "vendor");
+    assert_eq!(stack.value("plugins.[3]").unwrap().origin().unwrap().layer(),
+        "site");
+
+    stack.set_collection_merge(CollectionMerge::Deduplicate);
+    assert_eq!(plugins(&stack), vec!["auth", "log", "metrics"]);
+    assert_eq!(stack.value("server.port").unwrap().as_int32().unwrap(), 9090);
+    assert_eq!(stack.layer("vendor").unwrap().value("server.port").unwrap()
+        .as_int32().unwrap(), 8080);
+}
+
+#[test]
+fn test_merge_from() {
+    let base = r#"
+        server = { host = "localhost"; port = 8080; };
+        ports = [ 80, 443 ];
+        tags = ( "a", { name = "b"; } );"#;
+    let mut other = Config::new();
+    assert!(other.load_from_string(r#"
+        server = { port = 9090; timeout = 30; };
+        ports = [ 443, 8443 ];
+        tags = ( { name = "b"; }, "c" );"#).is_ok());
+
+    let merged = |policy : MergePolicy| -> Config {
+        let mut cfg = Config::new();
+        assert!(cfg.load_from_string(base).is_ok());
+        assert!(cfg.merge_from(&other, policy).is_ok());
+        cfg
+    };
+    let ports = |cfg : &Config| -> Vec<i32> {
+        cfg.value("ports").unwrap().as_array()
+            .map(|value| { value.as_int32().unwrap() }).collect()
+    };
+
+    let cfg = merged(MergePolicy::default());
+    assert_eq!(cfg.value("server.host").unwrap().as_string().unwrap(), 
+        "localhost");
+    assert_eq!(cfg.value("server.port").unwrap().as_int32().unwrap(), 9090);
+    assert_eq!(cfg.value("server.timeout").unwrap().as_int32().unwrap(), 30);
+    assert_eq!(ports(&cfg), vec![443, 8443]);
+
+    let cfg = merged(MergePolicy::new(ScalarMerge::Keep, 
+        CollectionMerge::Append));
+    assert_eq!(cfg.value("server.port").unwrap().as_int32().unwrap(), 8080);
+    assert_eq!(cfg.value("server.timeout").unwrap().as_int32().unwrap(), 30);
+    assert_eq!(ports(&cfg), vec![80, 443, 443, 8443]);
+    assert_eq!(cfg.value("tags").unwrap().as_list().count(), 4);
+
+    let cfg = merged(MergePolicy::new(ScalarMerge::Overwrite, 
+        CollectionMerge::Deduplicate));
+    assert_eq!(ports(&cfg), vec![80, 443, 8443]);
+    assert_eq!(cfg.value("tags").unwrap().as_list().count(), 3);
+    assert_eq!(cfg.value("tags.[2]").unwrap().as_string().unwrap(), "c");
+
+    let mut conflict = Config::new();
+    assert!(conflict.load_from_string(r#"
+        server = { port = "http"; };"#).is_ok());
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(base).is_ok());
+    assert_eq!(cfg.merge_from(&conflict, MergePolicy::default()), 
+        Err(Errors::MergeConflict(String::from("line 2: server.port"), 
+            String::from("line 2: server.port"))));
+    assert_eq!(cfg.value("server.port").unwrap().as_int32().unwrap(), 8080);
+    assert!(matches!(cfg.merge_from(&conflict, MergePolicy::new(
+        ScalarMerge::Keep, CollectionMerge::Replace)), 
+        Err(Errors::MergeConflict(_, _))));
+    assert!(cfg.merge_from(&conflict, MergePolicy::new(ScalarMerge::Fill, 
+        CollectionMerge::Replace)).is_ok());
+    assert_eq!(cfg.value("server.port").unwrap().as_int32().unwrap(), 8080);
+
+    let mut conflict = Config::new();
+    assert!(conflict.load_from_string("ports = [ \"http\" ];").is_ok());
+    assert_eq!(cfg.merge_from(&conflict, MergePolicy::new(
+        ScalarMerge::Overwrite, CollectionMerge::Append)), 
+        Err(Errors::MergeConflict(String::from("line 3: ports.[0]"), 
+            String::from("line 1: ports.[0]"))));
+    assert!(cfg.merge_from(&conflict, MergePolicy::default()).is_ok());
+    assert_eq!(cfg.value("ports.[0]").unwrap().as_string().unwrap(), "http");
+}
+
+#[test]
+fn test_env_overlay() {
+    let vars = |vars : &[(&str, &str)]| -> Vec<(String, String)> {
+        vars.iter().map(|(name, value)| { 
+            (name.to_string(), value.to_string()) 
+        }).collect()
+    };
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = { port = 80; maxConnections = 10L; ratio = 0.5; };
+        hosts = [ "a" ];
+        debug = false;"#).is_ok());
+
+    let overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP__SERVER__PORT", "8080"),
+        ("APP__SERVER__MAXCONNECTIONS", "20"),
+        ("APP__SERVER__RATIO", "1"),
+        ("APP__HOSTS", "b, c"),
+        ("APP__DEBUG", "TRUE"),
+        ("APP__LOG__LEVEL", "debug"),
+        ("APP__LOG__SIZE", "5000000000"),
+        ("APP__LOG__TAGS", "(1, \"x\", 2.5)"),
+        ("APP__WEIGHTS", "[1, 5000000000]"),
+        ("OTHER__VALUE", "1")
+    ]));
+    let report = overlay.apply(&mut cfg).unwrap();
+    
+    let paths : Vec<&str> = report.iter().map(|item| { item.path() })
+        .collect();
+    assert_eq!(paths, vec!["debug", "hosts", "log.level", "log.size", 
+        "log.tags", "server.maxConnections", "server.port", "server.ratio",
+        "weights"]);
+    assert!(!report[0].created());
+    assert!(report[2].created());
+    assert_eq!(report[2].variable(), "APP__LOG__LEVEL");
+    assert_eq!(report[4].value(), "(1, \"x\", 2.5)");
+
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    assert_eq!(cfg.value("server.maxConnections").unwrap().as_int64(), 
+        Some(20));
+    assert_eq!(cfg.value("server.ratio").unwrap().as_float64(), Some(1.0));
+    assert_eq!(cfg.value("debug").unwrap().as_bool(), Some(true));
+    assert_eq!(cfg.value("log.level").unwrap().as_string().unwrap(), "debug");
+    assert_eq!(cfg.value("log.size").unwrap().value_type(), 
+        Some(OptionType::Int64Type));
+    assert_eq!(cfg.value("log.tags").unwrap().as_list().count(), 3);
+    assert_eq!(cfg.value("log.tags.[1]").unwrap().as_string().unwrap(), "x");
+    assert_eq!(cfg.value("weights.[0]").unwrap().as_int64(), Some(1));
+    let hosts : Vec<String> = cfg.value("hosts").unwrap().as_array()
+        .map(|value| { value.as_string().unwrap() }).collect();
+    assert_eq!(hosts, vec!["b", "c"]);
+
+    let overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP__DEBUG", "false"),
+        ("APP__SERVER__PORT", "http")
+    ]));
+    assert_eq!(overlay.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("APP__SERVER__PORT: invalid integer value \"http\" \
+            for server.port"))));
+    assert_eq!(cfg.value("debug").unwrap().as_bool(), Some(true));
+
+    let overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP__DEBUG__LEVEL", "1")
+    ]));
+    assert_eq!(overlay.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("APP__DEBUG__LEVEL: debug is not a group"))));
+
+    let mut overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP_SERVER_PORT", "9090")
+    ]));
+    overlay.set_separator("_");
+    assert!(overlay.apply(&mut cfg).is_ok());
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(9090));
+
+    let overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP__NEW", "1"),
+        ("APP__NEW__X", "2")
+    ]));
+    assert_eq!(overlay.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("APP__NEW__X: new is overridden by APP__NEW"))));
+    assert!(cfg.value("new").is_none());
+
+    let overlay = EnvOverlay::from_vars("APP", vars(&[
+        ("APP__FIRST", "1"),
+        ("APP__SECOND__9LIVES", "2")
+    ]));
+    assert_eq!(overlay.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("APP__SECOND__9LIVES: invalid setting name \"9LIVES\""))));
+    assert!(cfg.value("first").is_none());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        std::env::set_var("LIBRUSTCONFIG_TEST__PORT", "1");
+        std::env::set_var("LIBRUSTCONFIG_TEST__NAME", 
+            std::ffi::OsString::from_vec(vec![0x66, 0xff]));
+        let report = EnvOverlay::new("LIBRUSTCONFIG_TEST").apply(&mut cfg)
+            .unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].path(), "port");
+    }
+}
+
+#[test]
+fn test_cli_overrides() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = { host = "localhost"; port = 80; };
+        features = [ "x" ];"#).is_ok());
+
+    let (overrides, args) = CliOverrides::from_args(vec!["app", "--set", 
+        "server.port=9000", "--set=features=[\"a\", \"b\"]", "-v", 
+        "-olog.level=\"debug\"", "-o", "server={ tls = true; }", 
+        "-offline"]).unwrap();
+    assert_eq!(args, vec!["app", "-v", "-offline"]);
+    assert_eq!(overrides.len(), 4);
+    assert!(overrides.apply(&mut cfg).is_ok());
+
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(9000));
+    assert_eq!(cfg.value("server.host").unwrap().as_string().unwrap(), 
+        "localhost");
+    assert_eq!(cfg.value("server.tls").unwrap().as_bool(), Some(true));
+    assert_eq!(cfg.value("log.level").unwrap().as_string().unwrap(), "debug");
+    let features : Vec<String> = cfg.value("features").unwrap().as_array()
+        .map(|value| { value.as_string().unwrap() }).collect();
+    assert_eq!(features, vec!["a", "b"]);
+
+    let mut overrides = CliOverrides::new();
+    assert!(overrides.add("server.port=(1, \"a\", [2L])").is_ok());
+    assert!(overrides.apply(&mut cfg).is_ok());
+    assert!(cfg.value("server.port").unwrap().is_list().unwrap());
+    assert_eq!(cfg.value("server.port.[2].[0]").unwrap().as_int64(), Some(2));
+
+    assert_eq!(overrides.add("server.port"), Err(Errors::OverrideError(
+        String::from("server.port: expected path=value"))));
+    assert_eq!(overrides.add("server..port=1"), Err(Errors::OverrideError(
+        String::from("server..port=1: invalid setting name \"\" in \
+            \"server..port\""))));
+    assert!(matches!(overrides.add("name=app"), 
+        Err(Errors::OverrideError(message)) if message.starts_with(
+            "name=app: invalid value \"app\"") && 
+            message.ends_with("(strings must be quoted)")));
+    assert!(matches!(overrides.add("port=1; debug = true"), 
+        Err(Errors::OverrideError(message)) if message.ends_with(
+            "unexpected \"debug\"")));
+    assert!(matches!(CliOverrides::from_args(vec!["--set"]), 
+        Err(Errors::OverrideError(message)) if message == 
+            "--set: missing path=value"));
+
+    let mut overrides = CliOverrides::new();
+    assert!(overrides.add("server.host.name=\"a\"").is_ok());
+    assert_eq!(overrides.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("server.host.name=\"a\": server.host is not a group"))));
+
+    let mut overrides = CliOverrides::new();
+    assert!(overrides.add("limits=1").is_ok());
+    assert!(overrides.add("limits.cpu=2").is_ok());
+    assert_eq!(overrides.apply(&mut cfg), Err(Errors::OverrideError(
+        String::from("limits.cpu=2: limits is not a group"))));
+    assert!(cfg.value("limits").is_none());
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string("a = 1;\nb = 2;\nc = 3;").is_ok());
+    assert!(cfg.root().write_comment("b", "Second").is_some());
+    let mut overrides = CliOverrides::new();
+    assert!(overrides.add("b=\"two\"").is_ok());
+    assert!(overrides.apply(&mut cfg).is_ok());
+    assert_eq!(cfg.save_to_string().unwrap(), 
+        "a = 1;\n# Second\nb = \"two\";\nc = 3;\n");
+    assert_eq!(cfg.value("c").unwrap().source_line(), Some(3));
+}
+
+#[test]
+fn test_interpolation() {
+    let interpolator = Interpolator::with_env(|name| {
+        match name {
+            "HOME" => { Some(String::from("/home/app")) },
+            _ => { None }
+        }
+    });
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        base = "/var/app";
+        log_dir = "${base}/log";
+        data_dir = "${ base }/data";
+        archive = "${log_dir}/archive";
+        cache = "${env:HOME}/.cache";
+        literal = "$${base}";
+        port = 8080;
+        server = { port = "${port}"; url = "http://localhost:${port}/"; };
+        limits = { cpu = 2; memory = "${server.port}"; };
+        copy = "${limits}";
+        names = [ "${port}", "${base}" ];
+        tags = ( "${limits.cpu}", 1 );"#).is_ok());
+    assert_eq!(interpolator.apply(&mut cfg), Ok(()));
+
+    let string = |path : &str| -> String {
+        cfg.value(path).unwrap().as_string().unwrap()
+    };
+    assert_eq!(string("log_dir"), "/var/app/log");
+    assert_eq!(string("data_dir"), "/var/app/data");
+    assert_eq!(string("archive"), "/var/app/log/archive");
+    assert_eq!(string("cache"), "/home/app/.cache");
+    assert_eq!(string("literal"), "${base}");
+    assert_eq!(string("server.url"), "http://localhost:8080/");
+    assert_eq!(string("names.[0]"), "8080");
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    assert_eq!(cfg.value("limits.memory").unwrap().as_int32(), Some(8080));
+    assert_eq!(cfg.value("copy.memory").unwrap().as_int32(), Some(8080));
+    assert_eq!(string("tags.[0]"), "2");
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(
+        "a = \"${c}\";\nb = \"${c.x}\";\nc = { x = 1; };\nd = 2;").is_ok());
+    assert!(cfg.root().write_comment("a", "First").is_some());
+    assert_eq!(interpolator.apply(&mut cfg), Ok(()));
+    let mut expected = Config::new();
+    assert!(expected.load_from_string(
+        "a = { x = 1; }; b = 1; c = { x = 1; }; d = 2;").is_ok());
+    assert!(expected.root().write_comment("a", "First").is_some());
+    assert_eq!(cfg.save_to_string(), expected.save_to_string());
+    assert_eq!(cfg.value("a").unwrap().source_line(), Some(1));
+    assert_eq!(cfg.value("b").unwrap().source_line(), Some(2));
+    assert_eq!(cfg.value("d").unwrap().source_line(), Some(4));
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        a = "${b}";
+        b = "x${a}";
+        c = "${missing}";
+        d = "${env:UNSET}";
+        e = "${c}";
+        f = "${group}";
+        group = { g = "${f}"; };
+        h = "${";
+        i = [ "${group}" ];"#).is_ok());
+    assert_eq!(interpolator.apply(&mut cfg), Err(Errors::ReferenceError(
+        [
+            "line 2: a: reference cycle a -> b -> a",
+            "line 4: c: unresolved reference ${missing}",
+            "line 5: d: unresolved reference ${env:UNSET}",
+            "line 7: f: reference cycle f -> group -> group.g -> f",
+            "line 9: h: unterminated reference in \"${\""
+        ].join("\n"))));
+    assert_eq!(cfg.value("a").unwrap().as_string().unwrap(), "${b}");
+}
+
+#[test]
+fn test_secrets() {
+    fs::write("secret_test_password", "file password\n").unwrap();
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        db = {
+            user = "app";
+            password = "secret:file:secret_test_password";
+            token = "secret:env:LIBRUSTCONFIG_SECRET_TEST_UNSET";
+            key = "plain key";
+            bad = "secret:file";
+        };"#).is_ok());
+
+    let password = cfg.value("db.password").unwrap().as_secret().unwrap();
+    assert_eq!(password.expose(), "file password");
+    assert_eq!(format!("{:?} {}", password, password), 
+        "[REDACTED] [REDACTED]");
+    assert_eq!(cfg.value("db.token").unwrap().as_secret(), 
+        Err(Errors::SecretError(String::from("secret environment variable \
+            LIBRUSTCONFIG_SECRET_TEST_UNSET is not set"))));
+    assert!(matches!(cfg.value("db.bad").unwrap().as_secret(), 
+        Err(Errors::SecretError(_))));
+    assert_eq!(cfg.value("db.key").unwrap().as_secret().unwrap().expose(), 
+        "plain key");
+    
+    let rendered = format!("{:?}", cfg);
+    assert!(rendered.contains("user = \"app\";"));
+    assert!(rendered.contains("password = \"[REDACTED]\";"));
+    assert!(rendered.contains("key = \"[REDACTED]\";"));
+    assert!(!rendered.contains("plain key"));
+    assert!(!rendered.contains("secret:"));
+    assert!(cfg.save_to_string().unwrap().contains("plain key"));
+
+    cfg.set_secret_resolver(|provider : &str, name : &str| {
+        match provider {
+            "env" => { Ok(Secret::new(format!("token of {}", name))) },
+            _ => { Err(Errors::SecretError(String::from("no provider"))) }
+        }
+    });
+    assert_eq!(cfg.value("db.token").unwrap().as_secret().unwrap().expose(),
+        "token of LIBRUSTCONFIG_SECRET_TEST_UNSET");
+    assert!(cfg.value("db.password").unwrap().as_secret().is_err());
+    assert!(cfg.value("db.user").unwrap().value("missing").is_none());
+
+    let mut other = Config::new();
+    assert!(other.load_from_string("db = { key = \"new key\"; };").is_ok());
+    let changes = cfg.diff(&other);
+    let key = changes.iter().find(|change| { change.path() == "db.key" })
+        .unwrap();
+    assert_eq!(key.to_string(), 
+        "~ db.key = \"[REDACTED]\" -> \"[REDACTED]\"");
+    assert_eq!(key.new_value(), Some("\"new key\""));
+    let shown = format!("{}{:?}", diff::unified(&changes, "old", "new"), 
+        changes);
+    assert!(shown.contains("-db.password = \"[REDACTED]\";"));
+    assert!(!shown.contains("plain key") && !shown.contains("new key"));
+    assert!(!shown.contains("secret:"));
+    assert_eq!(cfg.apply_patch(&Patch::new().test("db.key", "\"x\"")), 
+        Err(Errors::PatchError(String::from(
+            "test db.key: test failed, value is \"[REDACTED]\""))));
+
+    let redacted = |export : String| {
+        assert!(export.contains("[REDACTED]"));
+        assert!(!export.contains("plain key") && !export.contains("secret:"));
+    };
+    redacted(cfg.save_to_json(JsonMode::Plain).unwrap());
+    let lossless = cfg.save_to_json(JsonMode::Lossless).unwrap();
+    assert!(lossless.contains("[REDACTED]") && !lossless.contains("plain key"));
+    assert!(lossless.contains("secret:file:secret_test_password"));
+    #[cfg(feature = "toml")]
+    redacted(cfg.save_to_toml().unwrap());
+    #[cfg(feature = "yaml")]
+    redacted(cfg.save_to_yaml().unwrap());
+    cfg.set_export_secrets(true);
+    assert!(cfg.save_to_json(JsonMode::Plain).unwrap().contains("plain key"));
+    #[cfg(feature = "toml")]
+    assert!(cfg.save_to_toml().unwrap().contains("plain key"));
+    #[cfg(feature = "yaml")]
+    assert!(cfg.save_to_yaml().unwrap().contains("plain key"));
+    cfg.set_export_secrets(false);
+
+    let mut copy = Config::new();
+    assert!(copy.load_from_json(&lossless, JsonMode::Lossless).is_ok());
+    assert_eq!(copy.value("db.password").unwrap().as_string().unwrap(),
+        "secret:file:secret_test_password");
+    assert_eq!(copy.value("db.password").unwrap().as_secret().unwrap()
+        .expose(), "file password");
+
+    assert!(format!("{}", cfg).contains("key = \"[REDACTED]\";"));
+    assert!(!format!("{}", cfg).contains("plain key"));
+
+    let schema = Schema::group().field("db", Schema::group()
+        .field("key", Schema::string().pattern("^x"))
+        .field("password", Schema::string().one_of(vec!["x"])));
+    let messages : Vec<String> = schema.validate(&cfg).iter()
+        .map(|violation| { violation.message().to_string() }).collect();
+    assert_eq!(messages.len(), 2);
+    assert!(messages.iter().all(|message| { 
+        message.contains("[REDACTED]") && !message.contains("plain key") 
+            && !message.contains("secret:")
+    }));
+
+    let overlay = EnvOverlay::from_vars("APP", vec![
+        (String::from("APP__DB__KEY"), String::from("other key")),
+        (String::from("APP__DB__TOKEN"), String::from("secret:env:X"))]);
+    let report = overlay.apply(&mut cfg).unwrap();
+    assert!(report.iter().all(|item| { item.value() == "[REDACTED]" }));
+    assert_eq!(cfg.value("db.key").unwrap().as_string().unwrap(), 
+        "other key");
+
+    fs::remove_file("secret_test_password").unwrap();
+}
+
+#[test]
+fn test_schema() {
+    let schema = Schema::group()
+        .field("server", Schema::group()
+            .field("host", Schema::string().required().pattern("^[a-z.]+$"))
+            .field("port", Schema::int32().range(1, 65535).required())
+            .field("timeout", Schema::int64().min(0))
+            .field("ratio", Schema::float64().range(0.0, 1.0))
+            .field("mode", Schema::string().one_of(vec!["dev", "prod"]))
+            .field("debug", Schema::boolean())
+            .deny_unknown())
+        .field("hosts", Schema::array(Schema::string().length(1, 8))
+            .length(1, 2))
+        .field("peers", Schema::list(Schema::group()
+            .field("name", Schema::string().required())))
+        .field("name", Schema::string().required())
+        .field("extra", Schema::any());
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = "localhost";
+            port = 8080;
+            timeout = 30;
+            ratio = 0.5;
+            mode = "prod";
+        };
+        hosts = [ "a", "b" ];
+        peers = ( { name = "p1"; } );
+        name = "app";
+        extra = ( 1, "a" );"#).is_ok());
+    assert!(schema.validate(&cfg).is_empty());
+
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = "Local Host";
+            port = 0;
+            timeout = -1L;
+            ratio = 2;
+            mode = "test";
+            debug = "yes";
+            color = "red";
+        };
+        hosts = [ "a", "toolonghostname", "c" ];
+        peers = ( { port = 1; } );"#).is_ok());
+    let violations : Vec<String> = schema.validate(&cfg).iter()
+        .map(|violation| { violation.to_string() }).collect();
+    assert_eq!(violations, vec![
+        "line 3: server.host: value \"Local Host\" does not match pattern \
+            \"^[a-z.]+$\"",
+        "line 4: server.port: value 0 is less than 1",
+        "line 5: server.timeout: value -1 is less than 0",
+        "line 6: server.ratio: expected float, found integer",
+        "line 7: server.mode: value \"test\" is not one of \"dev\", \"prod\"",
+        "line 8: server.debug: expected boolean, found string",
+        "line 9: server.color: unknown setting",
+        "line 11: hosts: length 3 is greater than 2 elements",
+        "line 11: hosts.[1]: length 15 is greater than 8 characters",
+        "line 12: peers.[0].name: missing required setting",
+        "name: missing required setting"
+    ]);
+
+    let mut definition = Config::new();
+    assert!(definition.load_from_string(r#"
+        fields = {
+            server = {
+                allow_unknown = false;
+                fields = {
+                    port = { type = "int"; required = true; min = 1; 
+                        max = 65535; };
+                    mode = { type = "string"; enum = [ "dev", "prod" ]; };
+                };
+            };
+            hosts = { type = "array"; max_length = 2; 
+                element = { type = "string"; pattern = "^[a-z]$"; }; };
+        };"#).is_ok());
+    let schema = Schema::from_config(&definition).unwrap();
+    let violations : Vec<String> = schema.validate(&cfg).iter()
+        .map(|violation| { violation.to_string() }).collect();
+    assert_eq!(violations, vec![
+        "line 4: server.port: value 0 is less than 1",
+        "line 7: server.mode: value \"test\" is not one of \"dev\", \"prod\"",
+        "line 3: server.host: unknown setting",
+        "line 5: server.timeout: unknown setting",
+        "line 6: server.ratio: unknown setting",
+        "line 8: server.debug: unknown setting",
+        "line 9: server.color: unknown setting",
+        "line 11: hosts: length 3 is greater than 2 elements",
+        "line 11: hosts.[1]: value \"toolonghostname\" does not match \
+            pattern \"^[a-z]$\""
+    ]);
+
+    assert!(definition.load_from_string(r#"
+        fields = { port = { type = "integer"; }; };"#).is_ok());
+    assert!(matches!(Schema::from_config(&definition), 
+        Err(Errors::SchemaError(message)) if message == 
+            "line 2: fields.port: unknown setting type \"integer\""));
+    assert!(definition.load_from_string(r#"
+        fields = { name = { type = "string"; pattern = "["; }; };"#).is_ok());
+    assert!(matches!(Schema::from_config(&definition), 
+        Err(Errors::SchemaError(message)) if message.starts_with(
+            "line 2: fields.name.pattern: invalid schema pattern")));
+}
+
+#[test]
+fn test_defaults() {
+    let mut defaults = Config::new();
+    assert!(defaults.load_from_string(r#"
+        server = { 
+            host = "localhost"; port = 8080; tls = { enabled = false; }; 
+        };
+        hosts = [ "a", "b" ];
+        level = "info";"#).is_ok());
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = { port = "9090"; };
+        hosts = [ "c" ];"#).is_ok());
+    assert_eq!(cfg.apply_defaults(&defaults), Ok(vec![
+        String::from("server.host"), String::from("server.tls"), 
+        String::from("level")]));
+    
+    assert_eq!(cfg.value("server.port").unwrap().as_string(), 
+        Some(String::from("9090")));
+    assert!(!cfg.value("server.port").unwrap().is_defaulted());
+    assert!(!cfg.value("server").unwrap().is_defaulted());
+    assert!(cfg.value("server.tls.enabled").unwrap().is_defaulted());
+    assert_eq!(cfg.value("hosts").unwrap().as_array().count(), 1);
+    assert_eq!(cfg.value("level").unwrap().as_string(), 
+        Some(String::from("info")));
+    assert_eq!(cfg.apply_defaults(&defaults), Ok(vec![]));
+
+    let schema = Schema::group()
+        .field("server", Schema::group()
+            .field("host", Schema::string().required())
+            .field("port", Schema::int32().range(1, 65535).default(8080)))
+        .field("workers", Schema::int64().min(1i64))
+        .field("ratio", Schema::float64().default(1))
+        .field("mode", Schema::string().one_of(vec!["dev", "prod"]))
+        .field("name", Schema::string().length(1, 64))
+        .field("tags", Schema::array(Schema::string().length(2, 8))
+            .length(3, 4))
+        .field("users", Schema::list(Schema::group()
+            .field("name", Schema::string().default("admin"))));
+    
+    let mut cfg = Config::new();
+    assert_eq!(schema.apply_defaults(&mut cfg), Ok(vec![
+        String::from("server"), String::from("ratio")]));
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    assert_eq!(cfg.value("ratio").unwrap().as_float64(), Some(1.0));
+    assert!(cfg.value("server.host").is_none());
+    assert!(cfg.value("users").is_none());
+
+    let example = schema.example();
+    assert!(schema.validate(&example).is_empty());
+    assert_eq!(example.value("workers").unwrap().as_int64(), Some(1));
+    assert_eq!(example.value("mode").unwrap().as_string(), 
+        Some(String::from("dev")));
+    assert_eq!(example.value("name").unwrap().as_string(), 
+        Some(String::from("x")));
+    assert_eq!(example.value("tags").unwrap().as_array().count(), 3);
+    assert_eq!(example.value("users.[0].name").unwrap().as_string(), 
+        Some(String::from("admin")));
+}
+
+#[cfg(feature = "derive")]
+#[derive(LibConfig, Debug, PartialEq)]
+struct TypedServer {
+    host : String,
+    #[libconfig(range(min = 1, max = 65535), default = "typed_default_port")]
+    port : u16,
+    #[libconfig(rename = "tls")]
+    secure : Option<bool>,
+    #[libconfig(hex)]
+    flags : u32,
+    #[libconfig(list)]
+    aliases : Vec<String>,
+    weights : Vec<f64>,
+    #[libconfig(flatten)]
+    limits : TypedLimits,
+    #[libconfig(skip)]
+    connections : usize,
+    mode : TypedMode,
+    backends : Vec<TypedBackend>
+}
+
+#[cfg(feature = "derive")]
+#[derive(LibConfig, Debug, PartialEq, Default)]
+struct TypedLimits {
+    #[libconfig(default)]
+    timeout : i64
+}
+
+#[cfg(feature = "derive")]
+#[derive(LibConfig, Debug, PartialEq)]
+enum TypedMode {
+    Plain,
+    #[libconfig(rename = "proxy")]
+    Proxy(String),
+    Balanced { size : i32 }
+}
+
+#[cfg(feature = "derive")]
+#[derive(LibConfig, Debug, PartialEq)]
+enum TypedBackend {
+    Local,
+    Remote { host : String }
+}
+
+#[cfg(feature = "derive")]
+fn typed_default_port() -> u16 {
+    8080
+}
+
+#[test]
+#[cfg(feature = "derive")]
+fn test_typed() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = "localhost";
+            flags = 0x1F;
+            aliases = ( "www" );
+            weights = [ 0.5, 1.5 ];
+            mode = { Balanced = { size = 3; }; };
+            backends = ( "Local", { Remote = { host = "db"; }; } );
+        };"#).is_ok());
+    
+    let server = TypedServer::from_config(&cfg.value("server").unwrap());
+    assert_eq!(server, Ok(TypedServer {
+        host : String::from("localhost"),
+        port : 8080,
+        secure : None,
+        flags : 31,
+        aliases : vec![String::from("www")],
+        weights : vec![0.5, 1.5],
+        limits : TypedLimits { timeout : 0 },
+        connections : 0,
+        mode : TypedMode::Balanced { size : 3 },
+        backends : vec![TypedBackend::Local, TypedBackend::Remote { 
+            host : String::from("db") 
+        }]
+    }));
+
+    let mut server = server.unwrap();
+    server.secure = Some(true);
+    server.mode = TypedMode::Proxy(String::from("front"));
+    let out = Config::new();
+    assert_eq!(server.to_config(&out.create_section("server").unwrap()), 
+        Ok(()));
+    assert_eq!(out.value("server.tls").unwrap().as_bool(), Some(true));
+    assert_eq!(out.value("server.timeout").unwrap().as_int64(), Some(0));
+    assert_eq!(out.value("server.aliases").unwrap().is_list(), Some(true));
+    assert_eq!(out.value("server.weights").unwrap().is_array(), Some(true));
+    assert!(out.value("server.connections").is_none());
+    assert_eq!(out.value("server.mode.proxy").unwrap().as_string(), 
+        Some(String::from("front")));
+    assert!(out.save_to_string().unwrap().contains("flags = 0x1F;"));
+    assert_eq!(TypedServer::from_config(&out.value("server").unwrap()), 
+        Ok(server));
+
+    let mut out = Config::new();
+    assert!(out.load_from_string(r#"
+        server = {
+            host = 1;
+            port = 80;
+            tls = true;
+            aliases = ( "old" );
+            mode = { proxy = "front"; };
+        };"#).is_ok());
+    let server = TypedServer {
+        host : String::from("example.com"),
+        port : 8080,
+        secure : None,
+        flags : 1,
+        aliases : vec![String::from("new")],
+        weights : vec![],
+        limits : TypedLimits { timeout : 5 },
+        connections : 0,
+        mode : TypedMode::Balanced { size : 2 },
+        backends : vec![]
+    };
+    let group = typed::create_group(&out.root(), "server").unwrap();
+    assert_eq!(server.to_config(&group), Ok(()));
+    assert_eq!(out.value("server.host").unwrap().source_line(), Some(3));
+    assert_eq!(out.value("server.port").unwrap().source_line(), Some(4));
+    assert!(out.value("server.tls").is_none());
+    assert!(out.value("server.mode.proxy").is_none());
+    assert_eq!(TypedServer::from_config(&out.value("server").unwrap()), 
+        Ok(server));
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = "localhost";
+            port = 0;
+        };"#).is_ok());
+    assert_eq!(TypedServer::from_config(&cfg.value("server").unwrap()), 
+        Err(Errors::ValueError(String::from(
+            "line 4: server.port: value 0 is less than 1"))));
+    
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = 1;
+        };"#).is_ok());
+    assert_eq!(TypedServer::from_config(&cfg.value("server").unwrap()), 
+        Err(Errors::ValueError(String::from(
+            "line 3: server.host: expected string, found integer"))));
+    
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(r#"
+        server = {
+            host = "localhost"; flags = 1; aliases = (); weights = [];
+            backends = ();
+            mode = "Direct";
+        };"#).is_ok());
+    assert_eq!(TypedServer::from_config(&cfg.value("server").unwrap()), 
+        Err(Errors::ValueError(String::from("line 5: server.mode: unknown \
+            variant \"Direct\", expected one of Plain"))));
+    
+    assert!(cfg.load_from_string("server = { host = \"localhost\"; };")
+        .is_ok());
+    assert_eq!(TypedServer::from_config(&cfg.value("server").unwrap()), 
+        Err(Errors::ValueError(String::from(
+            "line 1: server.flags: missing setting"))));
+}
+
+#[test]
+fn test_unused_settings() {
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app/app.cfg", 
+        "@include \"conf.d/*.cfg\"\nserver = { port = 8080; };\n\
+        sever = { port = 80; };\nusers = ( { name = \"root\"; uid = 0; } );");
+    memory.insert("/etc/app/conf.d/10-debug.cfg", "\ndebug = true;");
+
+    let mut cfg = Config::new();
+    cfg.set_fs(memory);
+    cfg.include_dir(Path::new("/etc/app"));
+    assert!(cfg.load_from_file(Path::new("/etc/app/app.cfg")).is_ok());
+    assert_eq!(cfg.unused_settings().len(), 4);
+
+    assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(8080));
+    for user in cfg.value("users").unwrap().as_list() {
+        assert!(user.value("name").is_some());
+    }
+    let unused : Vec<String> = cfg.unused_settings().iter()
+        .map(|setting| { setting.to_string() }).collect();
+    assert_eq!(unused, vec![
+        String::from("/etc/app/conf.d/10-debug.cfg:2: debug"),
+        String::from("/etc/app/app.cfg:3: sever"),
+        String::from("/etc/app/app.cfg:4: users.[0].uid")]);
+
+    let schema = Schema::group().field("debug", Schema::boolean());
+    assert!(schema.validate(&cfg).is_empty());
+    assert_eq!(cfg.unused_settings().len(), 3);
+
+    #[cfg(feature = "derive")]
+    assert_eq!(TypedLimits::from_config(&cfg.root_reader().unwrap()), 
+        Ok(TypedLimits { timeout : 0 }));
+    assert!(cfg.value("debug").is_some());
+    assert_eq!(cfg.unused_settings()[0].path(), "sever");
+
+    cfg.reset_access_tracking();
+    assert_eq!(cfg.unused_settings().len(), 4);
+    assert!(cfg.load_from_string("port = 1;").is_ok());
+    assert_eq!(cfg.unused_settings()[0].path(), "port");
+}
+
+#[test]
+fn test_config_watcher() {
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app/app.cfg", 
+        "@include \"local.cfg\"\n@include \"conf.d/*.cfg\"\nport = 80;");
+    memory.insert("/etc/app/local.cfg", "host = \"localhost\";");
+    memory.insert("/etc/app/conf.d/10-debug.cfg", "debug = false;");
+
+    let setup_fs = memory.clone();
+    let mut watcher = ConfigWatcher::with_setup("/etc/app/app.cfg", 
+        move |cfg| {
+            cfg.set_fs(setup_fs.clone());
+            cfg.include_dir(Path::new("/etc/app"));
+        }).unwrap();
+    assert!(watcher.is_polling());
+    assert_eq!(watcher.files(), &[PathBuf::from("/etc/app/app.cfg"), 
+        PathBuf::from("/etc/app/conf.d/10-debug.cfg"), 
+        PathBuf::from("/etc/app/local.cfg")]);
+    assert_eq!(watcher.check(), None);
+
+    memory.insert("/etc/app/local.cfg", "host = \"example.com\";");
+    assert_eq!(watcher.check(), Some(Reload::Reloaded));
+    assert_eq!(watcher.config().value("host").unwrap().as_string(), 
+        Some(String::from("example.com")));
+    assert_eq!(watcher.check(), None);
+
+    memory.insert("/etc/app/conf.d/20-broken.cfg", "\n\nworkers = ;");
+    match watcher.check() {
+        Some(Reload::Failed(error)) => {
+            assert_eq!(error.file(), Some("/etc/app/conf.d/20-broken.cfg"));
+            assert_eq!(error.line(), Some(3));
+        },
+        reload => { panic!("unexpected reload {:?}", reload); }
+    }
+    assert!(watcher.config().value("workers").is_none());
+    assert_eq!(watcher.check(), None);
+
+    memory.insert("/etc/app/conf.d/20-broken.cfg", "\n\nworkers = 4;");
+    assert_eq!(watcher.wait(Duration::from_secs(1)), Some(Reload::Reloaded));
+    assert_eq!(watcher.config().value("workers").unwrap().as_int32(), Some(4));
+
+    memory.take("/etc/app/app.cfg");
+    let mut reloads = Vec::new();
+    watcher.run(|reload, cfg| {
+        reloads.push(reload.clone());
+        assert_eq!(cfg.value("workers").unwrap().as_int32(), Some(4));
+        false
+    });
+    assert!(matches!(&reloads[..], [Reload::Failed(error)] 
+        if error.to_string() == "/etc/app/app.cfg: file not exists"));
+}
+
+#[test]
+fn test_config_watcher_change_after_load() {
+    let memory = MemoryFs::new();
+    memory.insert("app.cfg", "value = N;");
+
+    // File changes once right after it is loaded.
+    let reads = Rc::new(Cell::new(0));
+    let mut watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+        cfg.set_fs(ChangingFs::new(memory.clone(), reads.clone(), 2));
+    }).unwrap();
+    assert_eq!(watcher.config().value("value").unwrap().as_int32(), Some(1));
+    assert_eq!(watcher.check(), Some(Reload::Reloaded));
+    assert_eq!(watcher.config().value("value").unwrap().as_int32(), Some(2));
+    assert_eq!(watcher.check(), None);
+}
+
+#[test]
+fn test_config_watcher_disk() {
+    let dir = Path::new("config_watcher_test");
+    let file = dir.join("app.cfg");
+    fs::create_dir_all(dir).unwrap();
+    fs::write(&file, "value = 1;\n").unwrap();
+
+    let mut watcher = ConfigWatcher::new(&file).unwrap();
+    assert!(!watcher.is_polling());
+    assert_eq!(watcher.wait(Duration::from_millis(50)), None);
+
+    let mut cfg = Config::new();
+    cfg.root().write_int32("value", 2);
+    assert!(cfg.save_to_file(&file).is_ok());
+    assert_eq!(watcher.wait(Duration::from_secs(5)), Some(Reload::Reloaded));
+    assert_eq!(watcher.config().value("value").unwrap().as_int32(), Some(2));
+
+    watcher.set_polling(true);
+    assert!(watcher.is_polling());
+    fs::write(&file, "value = 3;\n").unwrap();
+    assert_eq!(watcher.wait(Duration::from_secs(5)), Some(Reload::Reloaded));
+    assert_eq!(watcher.config().value("value").unwrap().as_int32(), Some(3));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_diff() {
+    let mut old = Config::new();
+    assert!(old.load_from_string(
+        "version = 1;
+        name = \"app\";
+        mask = 0xFF;
+        server = { host = \"localhost\"; port = 80; debug = true; };
+        ports = [ 80, 443 ];
+        workers = ( { id = 1; }, { id = 2; }, { id = 3; } );"
+    ).is_ok());
+
+    let mut new = Config::new();
+    assert!(new.load_from_string(
+        "version = \"1\";
+        name = \"app\\tname\";
+        mask = 0xFF;
+        server = { host = \"localhost\"; port = 8080; tls = { on = true; }; };
+        ports = [ 80, 8443 ];
+        workers = ( { id = 1; }, { id = 5; } );"
+    ).is_ok());
+
+    assert!(old.diff(&old).is_empty());
+
+    let changes = old.diff(&new);
+    let lines : Vec<String> = changes.iter()
+        .map(|change| { change.to_string() }).collect();
+    assert_eq!(lines, vec![
+        "~ version = 1 -> \"1\" (integer -> string)",
+        "~ name = \"app\" -> \"app\\tname\"",
+        "~ server.port = 80 -> 8080",
+        "- server.debug = true",
+        "+ server.tls = { on = true; }",
+        "~ ports = [ 80, 443 ] -> [ 80, 8443 ]",
+        "~ workers.[1].id = 2 -> 5",
+        "- workers.[2] = { id = 3; }"
+    ]);
+
+    assert_eq!(changes[0].kind(), ChangeKind::Modified);
+    assert!(changes[0].is_type_change());
+    assert_eq!(changes[0].old_type(), Some("integer"));
+    assert_eq!(changes[0].new_type(), Some("string"));
+    assert!(!changes[2].is_type_change());
+    assert_eq!(changes[3].kind(), ChangeKind::Removed);
+    assert_eq!(changes[3].new_value(), None);
+    assert_eq!(changes[4].kind(), ChangeKind::Added);
+    assert_eq!(changes[4].old_value(), None);
+    assert_eq!(changes[7].path(), "workers.[2]");
+
+    let reverse = new.diff(&old);
+    assert_eq!(reverse.len(), changes.len());
+    assert_eq!(reverse[7].kind(), ChangeKind::Added);
+
+    assert_eq!(diff::unified(&changes[..4], "a.cfg", "b.cfg"),
+        "--- a.cfg\n+++ b.cfg\n\
+        @@ version: integer -> string @@\n-version = 1;\n+version = \"1\";\n\
+        @@ name @@\n-name = \"app\";\n+name = \"app\\tname\";\n\
+        @@ server.port @@\n-server.port = 80;\n+server.port = 8080;\n\
+        @@ server.debug @@\n-server.debug = true;\n");
+
+    let empty = Config::new();
+    assert_eq!(empty.diff(&old).len(), 6);
+    assert!(empty.diff(&old).iter()
+        .all(|change| { change.kind() == ChangeKind::Added }));
+}
+
+#[test]
+fn test_patch() {
+    let text = "version = 1;
+        server = { host = \"localhost\"; port = 80; mask = 0x1F; };
+        ports = [ 80, 443 ];
+        workers = ( { id = 1; }, { id = 2; } );";
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(text).is_ok());
+    let original = cfg.save_to_string().unwrap();
+
+    let patch = Patch::from_string(
+        "patch = (
+            { op = \"test\"; path = \"version\"; value = 1; },
+            { op = \"add\"; path = \"server.tls\"; value = { on = true; }; },
+            { op = \"replace\"; path = \"server.port\"; value = \"http\"; },
+            { op = \"remove\"; path = \"server.host\"; },
+            { op = \"add\"; path = \"ports.[0]\"; value = 8080; },
+            { op = \"move\"; from = \"workers.[1]\"; path = \"workers.[0]\"; },
+            { op = \"copy\"; from = \"server\"; path = \"backup\"; }
+        );").unwrap();
+    assert_eq!(patch.ops().len(), 7);
+    assert_eq!(Patch::from_string(&patch.to_string()).unwrap(), patch);
+
+    let expected = patch.inverse(&cfg).unwrap();
+    assert_eq!(cfg.save_to_string().unwrap(), original);
+
+    let inverse = cfg.apply_patch(&patch).unwrap();
+    assert_eq!(inverse, expected);
+    assert_eq!(cfg.value("server.tls.on").unwrap().as_bool(), Some(true));
+    assert_eq!(cfg.value("server.port").unwrap().as_string(), 
+        Some(String::from("http")));
+    assert!(cfg.value("server.host").is_none());
+    assert_eq!(cfg.value("ports.[0]").unwrap().as_int32(), Some(8080));
+    assert_eq!(cfg.value("ports.[2]").unwrap().as_int32(), Some(443));
+    assert_eq!(cfg.value("workers.[0].id").unwrap().as_int32(), Some(2));
+    assert_eq!(cfg.value("backup.mask").unwrap().as_int32(), Some(0x1F));
+
+    let mut before = Config::new();
+    assert!(before.load_from_string(text).is_ok());
+    assert!(cfg.apply_patch(&inverse).is_ok());
+    assert!(cfg.diff(&before).is_empty());
+    assert!(cfg.load_from_string(text).is_ok());
+
+    let failing = Patch::new()
+        .remove("server.host")
+        .replace("version", "2")
+        .add("ports.[1]", "\"https\"");
+    assert_eq!(cfg.apply_patch(&failing), Err(Errors::PatchError(
+        String::from("add ports.[1]: can't add string here"))));
+    assert_eq!(cfg.save_to_string().unwrap(), original);
+
+    for (patch, message) in [
+        (Patch::new().test("version", "2"), 
+            "test version: test failed, value is 1"),
+        (Patch::new().replace("server.user", "1"), 
+            "replace server.user: setting not exists"),
+        (Patch::new().add("ports.[5]", "1"), 
+            "add ports.[5]: index out of range"),
+        (Patch::new().add("version.x", "1"), 
+            "add version.x: version is not a group"),
+        (Patch::new().add("x", "{"), "add x: invalid value {"),
+        (Patch::new().move_from("server", "server.inner"), 
+            "move server.inner: setting can't be moved into itself")
+    ] {
+        assert_eq!(cfg.apply_patch(&patch), 
+            Err(Errors::PatchError(String::from(message))));
+    }
+    assert_eq!(cfg.save_to_string().unwrap(), original);
+
+    assert_eq!(Patch::from_string(
+        "patch = ( { op = \"drop\"; path = \"a\"; } );"), 
+        Err(Errors::PatchError(String::from(
+            "patch.[0]: unknown operation \"drop\""))));
+    assert_eq!(Patch::from_string(
+        "patch = ( { op = \"move\"; path = \"a\"; } );"), 
+        Err(Errors::PatchError(String::from("patch.[0]: missing from"))));
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string("hosts = ( \"a\", \"b\" );").is_ok());
+    assert!(cfg.value("hosts.[1]").unwrap().as_secret().is_ok());
+    assert!(cfg.root().write_int32("version", 1).unwrap()
+        .write_comment("version", "Format version").is_some());
+    assert!(cfg.apply_patch(&Patch::new()
+        .add("hosts.[0]", "\"x\"")
+        .move_from("version", "release")).is_ok());
+    assert_eq!(cfg.render_redacted().unwrap(),
+        "hosts = ( \"x\", \"a\", \"[REDACTED]\" );\nrelease = 1;\n");
+    assert!(cfg.save_to_string().unwrap()
+        .ends_with("# Format version\nrelease = 1;\n"));
+}
+
+#[test]
+fn test_transaction() {
+    let text = "version = 1;
+        server = { host = \"localhost\"; port = 80; };
+        hosts = ( \"a\", \"b\", \"c\" );";
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(text).is_ok());
+    let original = cfg.save_to_string().unwrap();
+
+    let result : Result<(), Errors> = cfg.transaction(|tx| {
+        tx.writer("version").unwrap().delete()?;
+        tx.root().write_int32("version", 2);
+        tx.writer("server").unwrap().create_section("tls").unwrap()
+            .write_bool("on", true);
+        tx.root().write_int32("version", 3)
+            .ok_or(Errors::ValueError(String::from("version exists")))?;
+        Ok(())
+    });
+    assert_eq!(result, Err(Errors::ValueError(String::from("version exists"))));
+    assert_eq!(cfg.save_to_string().unwrap(), original);
+
+    let count = cfg.transaction(|tx| {
+        tx.writer("version").unwrap().delete()?;
+        tx.root().write_int32("version", 2);
+        tx.writer("hosts.[1]").unwrap().delete()?;
+        tx.writer("server.port").unwrap().delete()?;
+        tx.root().write_int64("timeout", 30);
+        assert_eq!(tx.value("version").unwrap().as_int32(), Some(2));
+        Ok(tx.changes().len())
+    }).unwrap();
+    assert_eq!(count, 5);
+
+    assert_eq!(cfg.value("version").unwrap().as_int32(), Some(2));
+    assert!(cfg.value("server.port").is_none());
+    assert_eq!(cfg.value("hosts.[1]").unwrap().as_string(), 
+        Some(String::from("c")));
+    assert_eq!(cfg.value("timeout").unwrap().as_int64(), Some(30));
+    assert_eq!(cfg.value("server.host").unwrap().source_line(), Some(2));
+    assert_eq!(cfg.save_to_string().unwrap(), 
+        "version = 2;\nserver :\n{\n  host = \"localhost\";\n};\n\
+        hosts = ( \"a\", \"c\" );\ntimeout = 30L;\n");
+
+    assert!(cfg.root().write_comment("version", "Format version").unwrap()
+        .write_hook("timeout", 5).is_some());
+    let name = CString::new(vec![b'n', 0xFF]).unwrap();
+    assert!(cfg.transaction(|tx| {
+        tx.root().write_float64("ratio", f64::NAN).unwrap()
+            .write_string("name", "");
+        tx.writer("timeout").unwrap().delete()?;
+        tx.root().write_int64("timeout", 60).unwrap()
+            .write_trailing_comment("timeout", "seconds");
+        let element = tx.value("name").unwrap().element.unwrap();
+        unsafe { raw::config_setting_set_string(element, name.as_ptr()); }
+        Ok(())
+    }).is_ok());
+    assert!(cfg.value("ratio").unwrap().as_float64().unwrap().is_nan());
+    assert_eq!(cfg.value("version").unwrap().comments().leading(),
+        &["Format version"]);
+    assert_eq!(cfg.value("timeout").unwrap().as_int64(), Some(60));
+    assert_eq!(cfg.value("timeout").unwrap().hook::<i32>(), Some(5));
+    assert_eq!(cfg.value("timeout").unwrap().comments().trailing(),
+        Some("seconds"));
+    let element = cfg.value("name").unwrap().element.unwrap();
+    assert_eq!(unsafe {
+        std::ffi::CStr::from_ptr(raw::config_setting_get_string(element))
+    }, name.as_c_str());
+
+    assert!(cfg.transaction(|tx| {
+        tx.writer("name").unwrap().delete()?;
+        tx.writer("ratio").unwrap().delete()?;
+        tx.writer("server").unwrap().delete()?;
+        tx.root().write_string("server", "none");
+        Ok(())
+    }).is_ok());
+    assert!(cfg.save_to_string().unwrap().starts_with(
+        "# Format version\nversion = 2;\nserver = \"none\";\nhosts"));
+    assert_eq!(cfg.value("timeout").unwrap().hook::<i32>(), Some(5));
+}
+
+#[test]
+fn test_document() {
+    let text = concat!(
+        "# Application settings\n",
+        "\n",
+        "/* Server\n",
+        "   section */\n",
+        "server : {\n",
+        "    host = \"example\" /* joined */ \".com\";  # public name\n",
+        "    port = 0x1F90;\n",
+        "    timeout = 30L; // seconds\n",
+        "};\n",
+        "\n",
+        "// Ratio\n",
+        "ratio = 1.5e-3;\n",
+        "workers = ( { id = 1; }, { id = 2; } );\n",
+        "ports = [\n",
+        "    80,\n",
+        "    443\n",
+        "];\n",
+        "@include \"extra.cfg\"\n",
+        "# end\n");
+
+    let mut doc = Document::parse(text).unwrap();
+    assert_eq!(doc.to_string(), text);
+    assert_eq!(doc.get("server.host"), 
+        Some("\"example\" /* joined */ \".com\""));
+    assert_eq!(doc.get("server.port"), Some("0x1F90"));
+    assert_eq!(doc.get("server.timeout"), Some("30L"));
+    assert_eq!(doc.get("ratio"), Some("1.5e-3"));
+    assert_eq!(doc.get("workers.[1].id"), Some("2"));
+    assert_eq!(doc.get("ports.[1]"), Some("443"));
+
+    assert!(doc.set("server.port", "8080").is_ok());
+    assert!(doc.insert("server.tls", "{ enabled = true; }").is_ok());
+    assert!(doc.insert("workers.[2]", "{ id = 3; }").is_ok());
+    assert!(doc.insert("ports.[2]", "8443").is_ok());
+    assert!(doc.remove("ratio").is_ok());
+    assert!(doc.remove("workers.[0]").is_ok());
+    assert!(doc.insert("debug", "false").is_ok());
+    assert_eq!(doc.to_string(), concat!(
+        "# Application settings\n",
+        "\n",
+        "/* Server\n",
+        "   section */\n",
+        "server : {\n",
+        "    host = \"example\" /* joined */ \".com\";  # public name\n",
+        "    port = 8080;\n",
+        "    timeout = 30L; // seconds\n",
+        "    tls = { enabled = true; };\n",
+        "};\n",
+        "\n",
+        "workers = ( { id = 2; }, { id = 3; } );\n",
+        "ports = [\n",
+        "    80,\n",
+        "    443,\n",
+        "    8443\n",
+        "];\n",
+        "debug = false;\n",
+        "@include \"extra.cfg\"\n",
+        "# end\n"));
+
+    let mut doc = Document::parse(
+        "a = 1;\nlist = ();\ngroup = {};\n").unwrap();
+    assert!(doc.insert("list.[0]", "\"x\"").is_ok());
+    assert!(doc.insert("group.b", "2").is_ok());
+    assert_eq!(doc.to_string(), 
+        "a = 1;\nlist = ( \"x\" );\ngroup = { b = 2; };\n");
+    let cfg = doc.to_config().unwrap();
+    assert_eq!(cfg.value("group.b").unwrap().as_int32(), Some(2));
+
+    for (result, message) in [
+        (doc.set("missing", "1"), "missing: setting not exists"),
+        (doc.insert("a", "2"), "a: setting already exists"),
+        (doc.insert("list.[5]", "1"), "list.[5]: index out of range"),
+        (doc.insert("a.b", "1"), "a.b: parent is not a group"),
+        (doc.insert("1a", "1"), "1a: invalid setting name"),
+        (doc.remove("list.[1]"), "list.[1]: setting not exists"),
+        (doc.set("a", "1; b = 2"), "invalid value 1; b = 2")
+    ] {
+        assert_eq!(result, Err(Errors::DocumentError(String::from(message))));
+    }
+
+    assert_eq!(Document::parse("a = 1;\nb = {\nc = 2;\n").err(), 
+        Some(Errors::DocumentError(String::from("line 4: missing '}'"))));
+    assert_eq!(Document::parse("a = 1;\nb = [ 1 2 ];").err(), 
+        Some(Errors::DocumentError(String::from(
+            "line 2: ',' or ']' expected"))));
+}
+
+#[test]
+fn test_comments() {
+    let memory = MemoryFs::new();
+    memory.insert("/etc/app/app.cfg", concat!(
+        "# Application settings\n",
+        "\n",
+        "# Server section\n",
+        "server = {\n",
+        "  /* Public host name */\n",
+        "  host = \"example.com\"; # or address\n",
+        "  @include \"tls.cfg\"\n",
+        "};\n",
+        "ports = [ 80, 443 ]; // open ports\n"));
+    memory.insert("/etc/app/tls.cfg", "# Enable TLS\ntls = true;\n");
+
+    let mut cfg = Config::new();
+    cfg.set_fs(memory.clone());
+    cfg.include_dir(Path::new("/etc/app"));
+    assert!(cfg.load_from_file(Path::new("/etc/app/app.cfg")).is_ok());
+    memory.insert("/etc/app/tls.cfg", "# Changed\ntls = false;\n");
+
+    let comments = cfg.value("server").unwrap().comments();
+    assert_eq!(comments.leading(), &["Server section"]);
+    assert_eq!(comments.trailing(), None);
+    let comments = cfg.value("server.host").unwrap().comments();
+    assert_eq!(comments.leading(), &["Public host name"]);
+    assert_eq!(comments.trailing(), Some("or address"));
+    assert_eq!(cfg.value("server.tls").unwrap().comments().leading(), 
+        &["Enable TLS"]);
+    assert_eq!(cfg.value("ports").unwrap().comments().trailing(), 
+        Some("open ports"));
+    assert!(cfg.value("ports.[0]").unwrap().comments().is_empty());
+
+    let out = Config::new();
+    let server = out.create_section("server").unwrap();
+    out.root().write_comment("server", "Server section\nedit with care");
+    server.write_string("host", "localhost").unwrap()
+        .write_comment("host", "Host name").unwrap()
+        .write_int32("port", 8080).unwrap()
+        .write_trailing_comment("port", "default port");
+    assert!(out.root().write_comment("missing", "comment").is_none());
+    assert!(server.write_trailing_comment("port", "a\nport = 1;").is_none());
+    assert!(server.write_comment("port", "a */ port = 1; /*").is_none());
+
+    assert_eq!(out.value("server.host").unwrap().comments().leading(), 
+        &["Host name"]);
+    let text = out.save_to_string().unwrap();
+    assert_eq!(text, concat!(
+        "# Server section\n",
+        "# edit with care\n",
+        "server :\n",
+        "{\n",
+        "  # Host name\n",
+        "  host = \"localhost\";\n",
+        "  port = 8080; # default port\n",
+        "};\n"));
+
+    let mut read = Config::new();
+    assert!(read.load_from_string(text).is_ok());
+    assert_eq!(read.value("server").unwrap().comments().leading(), 
+        &["Server section", "edit with care"]);
+    assert_eq!(read.value("server.port").unwrap().comments().trailing(), 
+        Some("default port"));
+
+    let mut doc = Document::parse("port = 80;\n").unwrap();
+    assert!(matches!(doc.set_comments("port", &Comments::new(Vec::new(), 
+        Some(String::from("a */ b")))), Err(Errors::DocumentError(_))));
+    assert!(matches!(doc.set_comments("port", &Comments::new(Vec::new(), 
+        Some(String::from("a\nb")))), Err(Errors::DocumentError(_))));
+    assert_eq!(doc.to_string(), "port = 80;\n");
+}
+
+#[test]
+fn test_query() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(
+        "servers = (
+            { name = \"web\"; port = 443; enabled = true; 
+                tls = { cert = \"web.pem\"; }; },
+            { name = \"api\"; port = 8443; enabled = false; 
+                tls = { cert = \"api.pem\"; }; },
+            { name = \"dev\"; port = 8080; enabled = true; }
+        );
+        admin = { port = 9000L; tls = { cert = \"admin.pem\"; }; };
+        ports = [ 1, 2, 3, 4, 5 ];"
+    ).is_ok());
+
+    let query = |text : &str| -> Vec<String> { 
+        cfg.query(text).unwrap().paths() 
+    };
+    assert_eq!(query("servers.*.name"), 
+        vec!["servers.[0].name", "servers.[1].name", "servers.[2].name"]);
+    assert_eq!(query("..port"), vec!["servers.[0].port", "servers.[1].port", 
+        "servers.[2].port", "admin.port"]);
+    assert_eq!(query("..tls.cert"), vec!["servers.[0].tls.cert", 
+        "servers.[1].tls.cert", "admin.tls.cert"]);
+    assert_eq!(query("servers[1:3].name"), 
+        vec!["servers.[1].name", "servers.[2].name"]);
+    assert_eq!(query("ports.[-2:]"), vec!["ports.[3]", "ports.[4]"]);
+    assert_eq!(query("ports[:2]"), vec!["ports.[0]", "ports.[1]"]);
+    assert_eq!(query("ports[-1]"), vec!["ports.[4]"]);
+    assert_eq!(query("ports[9]"), Vec::<String>::new());
+    assert_eq!(query("servers[?enabled == true].name"), 
+        vec!["servers.[0].name", "servers.[2].name"]);
+    assert_eq!(query("servers[?port >= 8080][?tls].name"), 
+        Vec::<String>::new());
+    assert_eq!(query("servers[?port >= 8080].name"), 
+        vec!["servers.[1].name", "servers.[2].name"]);
+    assert_eq!(query("servers[?name != \"web\"].port"), 
+        vec!["servers.[1].port", "servers.[2].port"]);
+    assert_eq!(query("servers[?tls].name"), 
+        vec!["servers.[0].name", "servers.[1].name"]);
+    assert_eq!(query("*[?cert == \"admin.pem\"]"), vec!["admin.tls"]);
+    assert_eq!(query("..[?port > 9000.5]"), Vec::<String>::new());
+    assert_eq!(query("..[?port < 9001]").len(), 4);
+
+    let ports : Vec<i64> = cfg.query("..port").unwrap()
+        .map(|(_, port)| { port.as_int64().unwrap_or_else(|| { 
+            port.as_int32().unwrap() as i64 
+        }) }).collect();
+    assert_eq!(ports, vec![443, 8443, 8080, 9000]);
+
+    let admin = cfg.value("admin").unwrap();
+    assert_eq!(admin.query("..cert").unwrap().paths(), vec!["admin.tls.cert"]);
+    let query = Query::parse("tls.cert").unwrap();
+    assert_eq!(query.select(&admin).paths(), vec!["admin.tls.cert"]);
+
+    for (text, message) in [
+        ("servers[", "servers[: position 9: ']' expected"),
+        ("servers[a]", "servers[a]: position 9: index expected"),
+        ("servers[?a == ]", "servers[?a == ]: position 9: value expected"),
+        (".port", ".port: position 1: step expected"),
+        ("servers*", "servers*: position 8: '.' expected")
+    ] {
+        assert_eq!(cfg.query(text).err(), 
+            Some(Errors::QueryError(String::from(message))));
+    }
+}
+
+#[test]
+fn test_visitor() {
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+    impl ConfigVisitor for Trace {
+        fn enter_group(&mut self, path : &str, _group : &OptionReader) 
+            -> bool {
+            self.0.push(format!("+group {}", path));
+            path != "skip"
+        }
+        fn leave_group(&mut self, path : &str, _group : &OptionReader) {
+            self.0.push(format!("-group {}", path));
+        }
+        fn enter_array(&mut self, path : &str, _array : &OptionReader) 
+            -> bool {
+            self.0.push(format!("+array {}", path));
+            true
+        }
+        fn leave_array(&mut self, path : &str, _array : &OptionReader) {
+            self.0.push(format!("-array {}", path));
+        }
+        fn enter_list(&mut self, path : &str, _list : &OptionReader) -> bool {
+            self.0.push(format!("+list {}", path));
+            true
+        }
+        fn leave_list(&mut self, path : &str, _list : &OptionReader) {
+            self.0.push(format!("-list {}", path));
+        }
+        fn visit_scalar(&mut self, path : &str, _value : &OptionReader) {
+            self.0.push(String::from(path));
+        }
+    }
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(
+        "name = \"app\";
+        ports = [ 80, 443 ];
+        users = ( { name = \"root\"; password = \"x\"; }, \"guest\" );
+        skip = { hidden = 1; };"
+    ).is_ok());
+
+    let mut trace = Trace::default();
+    cfg.walk(&mut trace);
+    assert_eq!(trace.0, vec!["+group ", "name", "+array ports", "ports.[0]", 
+        "ports.[1]", "-array ports", "+list users", "+group users.[0]", 
+        "users.[0].name", "users.[0].password", "-group users.[0]", 
+        "users.[1]", "-list users", "+group skip", "-group skip", 
+        "-group "]);
+    assert!(cfg.unused_settings().iter()
+        .any(|setting| { setting.path() == "name" }));
+
+    let mut trace = Trace::default();
+    cfg.value("users").unwrap().walk(&mut trace);
+    assert_eq!(trace.0.first(), Some(&String::from("+list users")));
+    assert_eq!(trace.0.len(), 7);
+
+    struct Rewrite;
+    impl ConfigVisitorMut for Rewrite {
+        fn enter_group(&mut self, path : &str, group : &mut OptionEditor) 
+            -> bool {
+            if path == "skip" {
+                assert!(group.remove().is_ok());
+                assert_eq!(group.remove().err(), Some(Errors::DeleteError));
+                assert_eq!(group.set_int64(1).err(), 
+                    Some(Errors::ElementNotExists));
+                assert!(group.writer().is_none());
+            }
+            true
+        }
+        fn visit_scalar(&mut self, path : &str, value : &mut OptionEditor) {
+            if path.ends_with("password") {
+                assert!(value.set_string("***").is_ok());
+            } else if path.starts_with("ports") {
+                assert!(value.set_string("80").is_err());
+                let port = value.reader().unwrap().as_int32().unwrap();
+                assert!(value.set_int32(port + 8000).is_ok());
+            } else if path == "users.[1]" {
+                assert!(value.remove().is_ok());
+                assert_eq!(value.set_bool(true).err(), 
+                    Some(Errors::ElementNotExists));
+                assert_eq!(value.set_int32(1).err(), 
+                    Some(Errors::ElementNotExists));
+                assert_eq!(value.set_string("x").err(), 
+                    Some(Errors::ElementNotExists));
+                assert!(value.reader().is_none());
+                assert!(value.writer().is_none());
+            }
+        }
+        fn leave_list(&mut self, _path : &str, list : &mut OptionEditor) {
+            list.writer().unwrap().write_string("", "admin");
+        }
+    }
+
+    let mut root = OptionEditor::new(cfg.root_setting().unwrap());
+    assert_eq!(root.remove().err(), Some(Errors::DeleteError));
+    cfg.walk_mut(&mut Rewrite);
+    assert_eq!(cfg.save_to_string().unwrap(), "name = \"app\";\n\
+        ports = [ 8080, 8443 ];\n\
+        users = ( {\n    name = \"root\";\n    password = \"***\";\n  }, \
+        \"admin\" );\n");
+}
+
+#[test]
+fn test_json() {
+    let text = include_str!("../fixtures/json/settings.cfg");
+    let plain = include_str!("../fixtures/json/settings.json");
+    let lossless = include_str!("../fixtures/json/settings.lossless.json");
+
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(text).is_ok());
+    assert_eq!(cfg.save_to_string().unwrap(), text);
+    assert_eq!(cfg.save_to_json(JsonMode::Plain).unwrap(), plain.trim_end());
+    assert_eq!(cfg.save_to_json(JsonMode::Lossless).unwrap(), 
+        lossless.trim_end());
+
+    let mut restored = Config::new();
+    assert!(restored.load_from_json(lossless, JsonMode::Lossless).is_ok());
+    assert_eq!(restored.save_to_string().unwrap(), text);
+    assert!(cfg.diff(&restored).is_empty());
+
+    assert!(restored.load_from_json(plain, JsonMode::Plain).is_ok());
+    assert_eq!(restored.value("mask").unwrap().as_int32(), Some(255));
+    assert_eq!(restored.value("size").unwrap().value_type().unwrap(), 
+        OptionType::Int64Type);
+    assert_eq!(restored.value("mixed.[3].[1]").unwrap().as_int64(), Some(5));
+    assert!(cfg.diff(&restored).is_empty());
+    assert_eq!(restored.save_to_string().unwrap(), text.replace("0xFF;", "255;")
+        .replace("0x1FFFFFFFFL", "8589934591L"));
+    assert_eq!(restored.save_to_json(JsonMode::Plain).unwrap(), 
+        plain.trim_end());
+
+    for (json, mode, message) in vec![
+        ("[]", JsonMode::Plain, "expected object"),
+        ("{ \"$int64\": \"1\" }", JsonMode::Plain, "expected object"),
+        ("{ \"a\": [ 1, null ] }", JsonMode::Plain, 
+            "a.[1]: null can't be represented in libconfig"),
+        ("{ \"a\": { \"$int64\": \"x\" } }", JsonMode::Plain, 
+            "a: invalid 64bit integer"),
+        ("{ \"1st\": 1 }", JsonMode::Plain, "1st: invalid setting name"),
+        ("{ \"a\": 18446744073709551615 }", JsonMode::Plain, 
+            "a: integer exceeds 64bit range"),
+        ("{ \"type\": \"list\", \"value\": [] }", JsonMode::Lossless, 
+            "expected group"),
+        ("{ \"value\": {} }", JsonMode::Lossless, "missing type"),
+        ("{ \"type\": \"group\", \"value\": { \"a\": { \"type\": \"map\", \
+            \"value\": 1 } } }", JsonMode::Lossless, 
+            "a: unknown type \"map\""),
+        ("{ \"type\": \"group\", \"value\": { \"a\": { \"type\": \"int\", \
+            \"value\": 5000000000 } } }", JsonMode::Lossless, 
+            "a: expected integer value"),
+        ("{ \"type\": \"group\", \"value\": { \"a\": { \"type\": \"array\", \
+            \"value\": [ { \"type\": \"int\", \"value\": 1 }, \
+            { \"type\": \"bool\", \"value\": true } ] } } }", 
+            JsonMode::Lossless, 
+            "a.[1]: array elements must be scalars of one type"),
+        ("{ \"type\": \"group\", \"value\": { \"a\": { \"type\": \"string\", \
+            \"value\": \"x\", \"format\": \"hex\" } } }", JsonMode::Lossless, 
+            "a: unsupported format")
+    ] {
+        assert_eq!(restored.load_from_json(json, mode), 
+            Err(Errors::JsonError(String::from(message))));
+    }
+    assert!(matches!(restored.load_from_json("{", JsonMode::Plain), 
+        Err(Errors::JsonError(_))));
+    assert_eq!(restored.save_to_json(JsonMode::Plain).unwrap(), 
+        plain.trim_end());
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_toml() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(include_str!("../fixtures/json/settings.cfg"))
+        .is_ok());
+    let toml = cfg.save_to_toml().unwrap();
+    assert_eq!(toml, "name = 'dashboard \"main\"'\nversion = 3\nmask = 255\n\
+        size = 5000000000\nflags = 8589934591\nratio = 0.75\nenabled = true\n\
+        mixed = [1, \"two\", 3.5, [4, 5], { six = 6 }]\n\n\
+        [server]\nhost = \"localhost\"\nports = [80, 443]\n\n\
+        [server.tls]\ncert = \"server.pem\"\nverify = false\n\n[empty]\n");
+
+    let mut restored = Config::new();
+    assert!(restored.load_from_toml(toml.as_str()).is_ok());
+    let changes : Vec<String> = cfg.diff(&restored).iter()
+        .map(|change| { change.to_string() }).collect();
+    assert_eq!(changes, vec!["~ mixed.[3] = [ 4L, 5L ] -> [ 4, 5 ]"]);
+    assert_eq!(restored.save_to_toml().unwrap(), toml);
+
+    for (toml, message) in [
+        ("when = 1979-05-27T07:32:00Z", 
+            "when: datetime can't be represented in libconfig"),
+        ("a = [1, nan]", 
+            "a.[1]: float NaN can't be represented in libconfig"),
+        ("\"1st\" = 1", "1st: invalid setting name"),
+        ("[server]\n\"host name\" = \"x\"", 
+            "server.host name: invalid setting name")
+    ] {
+        assert_eq!(restored.load_from_toml(toml), 
+            Err(Errors::TomlError(String::from(message))));
+    }
+    assert!(matches!(restored.load_from_toml("a = "), 
+        Err(Errors::TomlError(_))));
+    assert_eq!(restored.save_to_toml().unwrap(), toml);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_yaml() {
+    let mut cfg = Config::new();
+    assert!(cfg.load_from_string(include_str!("../fixtures/json/settings.cfg"))
+        .is_ok());
+    let yaml = cfg.save_to_yaml().unwrap();
+    assert!(yaml.starts_with("---\nname: \"dashboard \\\"main\\\"\"\n\
+        version: 3\nmask: 255\nsize: 5000000000\n"));
+
+    let mut restored = Config::new();
+    assert!(restored.load_from_yaml(yaml.as_str()).is_ok());
+    let changes : Vec<String> = cfg.diff(&restored).iter()
+        .map(|change| { change.to_string() }).collect();
+    assert_eq!(changes, vec!["~ mixed.[3] = [ 4L, 5L ] -> [ 4, 5 ]"]);
+    assert_eq!(restored.save_to_yaml().unwrap(), yaml);
+
+    assert!(restored.load_from_yaml("base: &base\n  port: 0x50\n\
+        server: *base\n").is_ok());
+    assert_eq!(restored.value("server.port").unwrap().as_int32(), Some(80));
+    assert!(restored.load_from_yaml("").is_ok());
+    assert_eq!(restored.save_to_yaml().unwrap(), "---\n{}\n");
+
+    for (yaml, message) in [
+        ("- 1", "expected mapping"),
+        ("a: 1\n---\nb: 2", "multiple documents not supported"),
+        ("a: [1, ~]", "a.[1]: null can't be represented in libconfig"),
+        ("a: .nan", "a: float can't be represented in libconfig"),
+        ("a:\n  1: x", "a: key Integer(1) is not a string"),
+        ("a:\n  b c: x", "a.b c: invalid setting name")
+    ] {
+        assert_eq!(restored.load_from_yaml(yaml), 
+            Err(Errors::YamlError(String::from(message))));
+    }
+    assert!(matches!(restored.load_from_yaml("a: [1"), 
+        Err(Errors::YamlError(_))));
+}
@@ -0,0 +1,228 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, Result};
+use crate::json::{self, JsonMode};
+use crate::merge::{self, Setting};
+use crate::schema;
+use crate::secret;
+
+use ::toml::{Table, Value};
+use serde_json::{Map, Number};
+use std::ffi::CStr;
+
+impl Config {
+
+    /// Save config tree as TOML string, available with `toml` feature.
+    /// Secret values are redacted unless `Config::set_export_secrets` is set.
+    /// 
+    /// Groups are saved as tables, arrays and lists as TOML arrays, which 
+    /// may mix value types. Both integer types are saved as TOML integers 
+    /// and hex format is dropped. TOML writes groups after other members of 
+    /// their group, so groups come last when TOML is loaded back.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "server = { port = 0x1F90; }; name = \"app\"; ids = ( 1, \"a\" );")
+    ///     .unwrap();
+    /// assert_eq!(cfg.save_to_toml().unwrap(), 
+    ///     "name = \"app\"\nids = [1, \"a\"]\n\n[server]\nport = 8080\n");
+    /// ```
+    pub fn save_to_toml(&self) -> Result<String> {
+        let table = match self.root_setting() {
+            Some(root) => { members(root, self.redacts_exports()) },
+            None => { Table::new() }
+        };
+        ::toml::to_string(&table).map_err(|error| { 
+            Errors::TomlError(error.to_string())
+        })
+    }
+
+    /// Replace config tree with settings of TOML string, available with 
+    /// `toml` feature. Config is left unchanged if TOML can't be converted.
+    /// 
+    /// Tables are loaded as groups. Integers fitting 32bit integer are 
+    /// loaded as integers, other as 64bit integers. TOML array holding 
+    /// scalars of one type is loaded as array, any other TOML array as 
+    /// list. Datetimes, infinite and NaN floats and keys which are not valid 
+    /// setting names can't be represented in libconfig and are reported as 
+    /// `TomlError`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_toml("size = 5000000000\nports = [80, 443]\n\n\
+    ///     [[users]]\nname = \"root\"\n").unwrap();
+    /// assert_eq!(cfg.value("size").unwrap().as_int64(), Some(5000000000));
+    /// assert_eq!(cfg.value("users.[0].name").unwrap().as_string(), 
+    ///     Some(String::from("root")));
+    /// 
+    /// assert_eq!(cfg.load_from_toml("start = 1979-05-27"), 
+    ///     Err(Errors::TomlError(String::from(
+    ///         "start: datetime can't be represented in libconfig"))));
+    /// ```
+    pub fn load_from_toml<S>(&mut self, toml : S) -> Result<()> 
+        where S: Into<String> {
+        
+        let table : Table = ::toml::from_str(&toml.into()).map_err(|error| {
+            Errors::TomlError(error.to_string())
+        })?;
+        let (_, value) = tagged("", &Value::Table(table))?;
+        self.load_from_json_value(&value, JsonMode::Lossless)
+            .map_err(|error| {
+                match error {
+                    Errors::JsonError(message) => { 
+                        Errors::TomlError(message) 
+                    },
+                    error => { error }
+                }
+            })
+    }
+}
+
+// Convert group members to TOML table.
+fn members(group : Setting, redact : bool) -> Table {
+    let mut table = Table::new();
+    for index in 0..merge::length(group) {
+        let member = merge::element(group, index);
+        table.insert(merge::setting_name(member), to_value(member, redact));
+    }
+    table
+}
+
+// Convert setting to TOML value, secret values are replaced if redact is 
+// set.
+fn to_value(setting : Setting, redact : bool) -> Value {
+    unsafe {
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_GROUP => { 
+                Value::Table(members(setting, redact)) 
+            },
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                Value::Array((0..merge::length(setting))
+                    .map(|index| { 
+                        to_value(merge::element(setting, index), redact) 
+                    }).collect())
+            },
+            raw::CONFIG_TYPE_INT => {
+                Value::Integer(raw::config_setting_get_int(setting) as i64)
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                Value::Integer(raw::config_setting_get_int64(setting))
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                Value::Float(raw::config_setting_get_float(setting))
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                Value::Boolean(raw::config_setting_get_bool(setting) != 0)
+            },
+            _ if redact && config::is_secret(setting) => {
+                Value::String(String::from(secret::REDACTED))
+            },
+            _ => {
+                let value = raw::config_setting_get_string(setting);
+                if value.is_null() {
+                    Value::String(String::new())
+                } else {
+                    Value::String(CStr::from_ptr(value).to_string_lossy()
+                        .into_owned())
+                }
+            }
+        }
+    }
+}
+
+// Convert TOML value to JSON value tagged with libconfig type.
+fn tagged(path : &str, value : &Value) -> Result<(i16, serde_json::Value)> {
+    let (kind, value) = match value {
+        Value::String(value) => { 
+            (raw::CONFIG_TYPE_STRING, serde_json::Value::from(value.as_str())) 
+        },
+        Value::Integer(value) => {
+            let kind = json::integer_type(*value);
+            if kind == raw::CONFIG_TYPE_INT64 {
+                (kind, serde_json::Value::from(value.to_string()))
+            } else {
+                (kind, serde_json::Value::from(*value))
+            }
+        },
+        Value::Float(value) => {
+            let number = Number::from_f64(*value).ok_or_else(|| {
+                error(path, &format!("float {} can't be represented in \
+                    libconfig", value))
+            })?;
+            (raw::CONFIG_TYPE_FLOAT, serde_json::Value::Number(number))
+        },
+        Value::Boolean(value) => { 
+            (raw::CONFIG_TYPE_BOOL, serde_json::Value::Bool(*value)) 
+        },
+        Value::Datetime(_) => {
+            return Err(error(path, 
+                "datetime can't be represented in libconfig"));
+        },
+        Value::Array(elements) => {
+            let mut kinds = Vec::new();
+            let mut values = Vec::new();
+            for (index, element) in elements.iter().enumerate() {
+                let (kind, value) = tagged(&json::element_path(path, index), 
+                    element)?;
+                kinds.push(kind);
+                values.push(value);
+            }
+            (json::collection_type(&kinds), serde_json::Value::Array(values))
+        },
+        Value::Table(table) => {
+            let mut members = Map::new();
+            for (name, member) in table {
+                let (_, value) = tagged(&schema::join_path(path, name), 
+                    member)?;
+                members.insert(name.clone(), value);
+            }
+            (raw::CONFIG_TYPE_GROUP, serde_json::Value::Object(members))
+        }
+    };
+    Ok((kind, json::tag(kind, value)))
+}
+
+// Return conversion error at path.
+fn error(path : &str, message : &str) -> Errors {
+    if path.is_empty() {
+        Errors::TomlError(message.to_string())
+    } else {
+        Errors::TomlError(format!("{}: {}", path, message))
+    }
+}
@@ -0,0 +1,321 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, OptionReader, OptionWriter, Result};
+use crate::diff::Change;
+use crate::merge::{self, Merger, MergePolicy, Setting};
+
+use std::ffi::CString;
+
+/// Staged edit of config. Writes go to a copy of config and are committed 
+/// together when transaction succeeds, see `Config::transaction`.
+pub struct Transaction<'a> {
+    config : &'a Config,
+    staged : Config
+}
+
+impl<'a> Transaction<'a> {
+
+    // Constructor.
+    fn new(config : &'a Config) -> Result<Transaction<'a>> {
+        let root = config.root_setting().ok_or(Errors::ElementNotExists)?;
+        let staged = config.empty_copy();
+        let target = staged.root_setting().ok_or(Errors::ElementNotExists)?;
+        Merger::new(MergePolicy::default(), &mut |_, _| {})
+            .merge_group(target, root);
+        Ok(Transaction { config, staged })
+    }
+
+    /// Get writer of staged root group.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.transaction(|tx| {
+    ///     tx.root().write_int32("port", 8080);
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert_eq!(cfg.value("port").unwrap().as_int32(), Some(8080));
+    /// ```
+    pub fn root(&self) -> OptionWriter {
+        self.staged.root()
+    }
+
+    /// Create staged group section by path, see `Config::create_section`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.transaction(|tx| {
+    ///     let server = tx.create_section("server").unwrap();
+    ///     server.write_string("host", "localhost");
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert!(cfg.value("server.host").is_some());
+    /// ```
+    pub fn create_section<S>(&self, path : S) -> Option<OptionWriter>
+        where S: Into<String> {
+        self.staged.create_section(path)
+    }
+
+    /// Get writer of staged setting by path.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("debug = true;").unwrap();
+    /// cfg.transaction(|tx| {
+    ///     tx.writer("debug").unwrap().delete()
+    /// }).unwrap();
+    /// assert!(cfg.value("debug").is_none());
+    /// ```
+    pub fn writer<S>(&self, path : S) -> Option<OptionWriter>
+        where S: Into<String> {
+        self.staged.value(path).map(|reader| { 
+            OptionWriter::new(reader.element) 
+        })
+    }
+
+    /// Read staged value by path, staged writes are visible.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("workers = 4;").unwrap();
+    /// cfg.transaction(|tx| {
+    ///     let workers = tx.value("workers").unwrap().as_int32().unwrap();
+    ///     tx.root().write_int32("threads", workers * 2);
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert_eq!(cfg.value("threads").unwrap().as_int32(), Some(8));
+    /// ```
+    pub fn value<S>(&self, path : S) -> Option<OptionReader>
+        where S: Into<String> {
+        self.staged.value(path)
+    }
+
+    /// Return changes staged so far.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80;").unwrap();
+    /// cfg.transaction(|tx| {
+    ///     tx.writer("port").unwrap().delete()?;
+    ///     tx.root().write_int32("port", 8080);
+    ///     assert_eq!(tx.changes()[0].to_string(), "~ port = 80 -> 8080");
+    ///     Ok(())
+    /// }).unwrap();
+    /// ```
+    pub fn changes(&self) -> Vec<Change> {
+        self.config.diff(&self.staged)
+    }
+}
+
+impl Config {
+
+    /// Run edit on staged copy of config. If edit returns Ok, staged changes
+    /// are committed to config together, otherwise config is left unchanged 
+    /// and edit error is returned. Settings edit doesn't replace keep their 
+    /// source locations, comments and other state.
+    /// 
+    /// Readers and writers of transaction point into the staged copy, which
+    /// is dropped when edit returns. Edit result must be `Send`, readers and
+    /// writers are not, so they can't be returned from edit:
+    /// 
+    /// ```compile_fail
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80;").unwrap();
+    /// let port = cfg.transaction(|tx| { Ok(tx.value("port").unwrap()) });
+    /// ```
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80;").unwrap();
+    /// 
+    /// let result = cfg.transaction(|tx| {
+    ///     tx.root().write_string("host", "localhost");
+    ///     tx.root().write_int32("port", 8080)
+    ///         .ok_or(Errors::ValueError(String::from("port exists")))?;
+    ///     Ok(())
+    /// });
+    /// assert!(result.is_err());
+    /// assert!(cfg.value("host").is_none());
+    /// ```
+    pub fn transaction<F, T>(&mut self, edit : F) -> Result<T>
+        where F: FnOnce(&mut Transaction) -> Result<T>, T: Send {
+        
+        let (result, staged) = {
+            let mut transaction = Transaction::new(self)?;
+            let result = edit(&mut transaction)?;
+            (result, transaction.staged)
+        };
+
+        let root = self.root_setting().ok_or(Errors::ElementNotExists)?;
+        let source = staged.root_setting().ok_or(Errors::ElementNotExists)?;
+        commit(root, source);
+        Ok(result)
+    }
+}
+
+// Make target setting equal to staged source setting of the same type. 
+// Settings are changed in place where possible, so they keep their source
+// locations and state, state of staged settings is merged in.
+fn commit(target : Setting, source : Setting) {
+    config::merge_setting_state(source, target);
+    match merge::setting_type(source) {
+        raw::CONFIG_TYPE_GROUP => { commit_group(target, source); },
+        raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+            commit_collection(target, source);
+        },
+        _ => {
+            if !merge::equal(target, source) {
+                merge::copy_value(target, source);
+            }
+        }
+    }
+}
+
+// Commit staged group. Members missing in staged group are removed, members
+// of other type are replaced at their position and new members are appended.
+// Members following replaced one are moved aside with their state and 
+// appended back, libconfig can only append settings.
+fn commit_group(target : Setting, source : Setting) {
+    for index in (0..merge::length(target)).rev() {
+        let name = merge::setting_name(merge::element(target, index));
+        if member_of(source, &name).is_none() {
+            unsafe { raw::config_setting_remove_elem(target, index); }
+        }
+    }
+
+    let same_type = |setting : Setting| {
+        member_of(source, &merge::setting_name(setting)).is_some_and(
+            |staged| { 
+                merge::setting_type(staged) == merge::setting_type(setting) 
+            })
+    };
+    let first = (0..merge::length(target)).find(|index| {
+        !same_type(merge::element(target, *index))
+    }).unwrap_or(merge::length(target));
+
+    let tail = Config::new();
+    let holder = match tail.root_setting() {
+        Some(holder) => { holder },
+        None => { return; }
+    };
+    let mut observer = |_, _| {};
+    let mut merger = Merger::new(MergePolicy::default(), &mut observer);
+    while merge::length(target) > first {
+        let setting = merge::element(target, first);
+        let name = CString::new(merge::setting_name(setting))
+            .unwrap_or_default();
+        config::move_setting_state(setting, 
+            merger.copy(holder, &name, setting));
+        unsafe { raw::config_setting_remove_elem(target, first); }
+    }
+
+    for index in 0..merge::length(holder) {
+        let setting = merge::element(holder, index);
+        let name = CString::new(merge::setting_name(setting))
+            .unwrap_or_default();
+        if same_type(setting) {
+            config::move_setting_state(setting, 
+                merger.copy(target, &name, setting));
+        } else if let Some(staged) = member_of(source, 
+            &merge::setting_name(setting)) {
+            config::move_setting_state(staged, 
+                merger.copy(target, &name, staged));
+        }
+    }
+
+    for index in 0..merge::length(source) {
+        let staged = merge::element(source, index);
+        let name = merge::setting_name(staged);
+        match member_of(target, &name) {
+            Some(setting) => { commit(setting, staged); },
+            None => {
+                let name = CString::new(name).unwrap_or_default();
+                config::move_setting_state(staged, 
+                    merger.copy(target, &name, staged));
+            }
+        }
+    }
+}
+
+// Commit staged collection. Leading elements of the same type are committed
+// in place, other elements are replaced by staged ones.
+fn commit_collection(target : Setting, source : Setting) {
+    let length = merge::length(target).min(merge::length(source));
+    let same = (0..length).find(|index| {
+        merge::setting_type(merge::element(target, *index)) != 
+            merge::setting_type(merge::element(source, *index))
+    }).unwrap_or(length);
+
+    while merge::length(target) > same {
+        unsafe { raw::config_setting_remove_elem(target, same); }
+    }
+    for index in 0..same {
+        commit(merge::element(target, index), merge::element(source, index));
+    }
+
+    let empty = CString::default();
+    let mut observer = |_, _| {};
+    let mut merger = Merger::new(MergePolicy::default(), &mut observer);
+    for index in same..merge::length(source) {
+        let staged = merge::element(source, index);
+        config::move_setting_state(staged, 
+            merger.copy(target, &empty, staged));
+    }
+}
+
+// Return group member by name.
+fn member_of(group : Setting, name : &str) -> Option<Setting> {
+    let name = CString::new(name).ok()?;
+    let setting = unsafe { 
+        raw::config_setting_get_member(group, name.as_ptr()) 
+    };
+    if setting.is_null() { None } else { Some(setting) }
+}
@@ -0,0 +1,657 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{Errors, OptionReader, OptionWriter, Result};
+use crate::merge::{self, Setting};
+use crate::schema;
+
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::ptr;
+
+#[cfg(feature = "derive")]
+pub use librustconfig_derive::LibConfig;
+
+/// Struct or enum read from and written to config group, usually implemented
+/// by `#[derive(LibConfig)]`.
+/// 
+/// Derive supports structs with named fields and enums. Enum unit variants 
+/// are stored as strings, other variants as group with single member named 
+/// by variant. Field attributes are written as `#[libconfig(...)]`:
+/// 
+/// * `rename = "name"` - setting name, also applies to enum variants.
+/// * `default` or `default = "path"` - value used when setting is missing,
+///   taken from `Default::default()` or function.
+/// * `flatten` - read and write members of field type from the same group.
+/// * `skip` - field is not read and written, `Default::default()` is used.
+/// * `range(min = 1, max = 65535)` - inclusive range of numeric value.
+/// * `hex` - write integers in hexadecimal format.
+/// * `array` or `list` - write collection as array or list, arrays are used
+///   for scalar elements by default.
+/// 
+/// # Example
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// use librustconfig::config::Config;
+/// use librustconfig::typed::LibConfig;
+/// 
+/// #[derive(LibConfig, Debug, PartialEq)]
+/// struct Server {
+///     host : String,
+///     #[libconfig(range(min = 1, max = 65535), default = "default_port")]
+///     port : u16,
+///     #[libconfig(rename = "names", list)]
+///     aliases : Vec<String>
+/// }
+/// 
+/// fn default_port() -> u16 { 80 }
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("server = { host = \"localhost\"; names = (); };")
+///     .unwrap();
+/// let server = Server::from_config(&cfg.value("server").unwrap()).unwrap();
+/// assert_eq!(server.port, 80);
+/// 
+/// let mut out = Config::new();
+/// server.to_config(&out.root()).unwrap();
+/// assert_eq!(out.value("port").unwrap().as_int32(), Some(80));
+/// ```
+pub trait LibConfig : Sized {
+
+    /// Read value from group setting.
+    fn from_config(reader : &OptionReader) -> Result<Self>;
+
+    /// Write value to group setting, settings written before are updated.
+    fn to_config(&self, writer : &OptionWriter) -> Result<()>;
+}
+
+/// Value of single setting, used for fields of `LibConfig` types.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed::{ConfigValue, Format};
+/// 
+/// let cfg = Config::new();
+/// vec![1, 2, 3].write_value(&cfg.root(), "values", Format::default())
+///     .unwrap();
+/// assert_eq!(Vec::<i32>::read_value(&cfg.value("values").unwrap()), 
+///     Ok(vec![1, 2, 3]));
+/// ```
+pub trait ConfigValue : Sized {
+
+    /// Read value of setting.
+    fn read_value(reader : &OptionReader) -> Result<Self>;
+
+    /// Write value as group member with name or as collection element, name
+    /// is ignored for collections.
+    fn write_value(&self, writer : &OptionWriter, name : &str, 
+        format : Format) -> Result<()>;
+
+    /// Return value of missing setting, None if setting is required.
+    fn missing() -> Option<Self> {
+        None
+    }
+
+    /// Return true if value is stored as scalar setting.
+    fn is_scalar() -> bool {
+        false
+    }
+}
+
+/// Format of written setting.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Format {
+    pub hex : bool,
+    pub collection : CollectionFormat
+}
+
+/// Collection type written for sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CollectionFormat {
+    #[default]
+    Auto,
+    Array,
+    List
+}
+
+/// Read group member, missing member is reported as error unless value type 
+/// has missing value.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::{Config, Errors};
+/// use librustconfig::typed;
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("port = \"http\";").unwrap();
+/// let root = cfg.root_reader().unwrap();
+/// assert_eq!(typed::read_member::<Option<String>>(&root, "host"), Ok(None));
+/// assert_eq!(typed::read_member::<i32>(&root, "port"), 
+///     Err(Errors::ValueError(String::from(
+///         "line 1: port: expected integer, found string"))));
+/// ```
+pub fn read_member<T>(reader : &OptionReader, name : &str) -> Result<T>
+    where T: ConfigValue {
+    
+    match member(reader, name)? {
+        Some(member) => { T::read_value(&member) },
+        None => {
+            T::missing().ok_or_else(|| { 
+                member_error(reader, name, "missing setting") 
+            })
+        }
+    }
+}
+
+/// Read group member, return default value if member is missing.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed;
+/// 
+/// let cfg = Config::new();
+/// let root = cfg.root_reader().unwrap();
+/// assert_eq!(typed::read_member_or(&root, "port", || { 80 }), Ok(80));
+/// ```
+pub fn read_member_or<T, F>(reader : &OptionReader, name : &str, default : F)
+    -> Result<T> where T: ConfigValue, F: FnOnce() -> T {
+    
+    match member(reader, name)? {
+        Some(member) => { T::read_value(&member) },
+        None => { Ok(default()) }
+    }
+}
+
+/// Check value of group member is in inclusive range.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::{Config, Errors};
+/// use librustconfig::typed;
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("port = 0;").unwrap();
+/// let root = cfg.root_reader().unwrap();
+/// assert_eq!(typed::check_range(&root, "port", &0, Some(1), Some(65535)),
+///     Err(Errors::ValueError(String::from(
+///         "line 1: port: value 0 is less than 1"))));
+/// ```
+pub fn check_range<T>(reader : &OptionReader, name : &str, value : &T, 
+    min : Option<T>, max : Option<T>) -> Result<()> 
+    where T: PartialOrd + fmt::Display {
+    
+    let message = {
+        match (min, max) {
+            (Some(min), _) if *value < min => {
+                format!("value {} is less than {}", value, min)
+            },
+            (_, Some(max)) if *value > max => {
+                format!("value {} is greater than {}", value, max)
+            },
+            _ => { return Ok(()); }
+        }
+    };
+
+    match member(reader, name)? {
+        Some(member) => { Err(error(&member, &message)) },
+        None => { Err(member_error(reader, name, &message)) }
+    }
+}
+
+/// Check setting is group.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed;
+/// 
+/// let cfg = Config::new();
+/// assert!(typed::expect_group(&cfg.root_reader().unwrap()).is_ok());
+/// ```
+pub fn expect_group(reader : &OptionReader) -> Result<()> {
+    expect(reader, &[raw::CONFIG_TYPE_GROUP]).map(|_| {})
+}
+
+/// Read name and value of enum variant stored as group with single member.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed;
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("mode = { Tcp = { port = 80; }; };").unwrap();
+/// let (name, value) = typed::read_variant(&cfg.value("mode").unwrap())
+///     .unwrap();
+/// assert_eq!(name, "Tcp");
+/// assert_eq!(value.value("port").unwrap().as_int32(), Some(80));
+/// ```
+pub fn read_variant(reader : &OptionReader) -> Result<(String, OptionReader)> {
+    let setting = expect(reader, &[raw::CONFIG_TYPE_GROUP])?;
+    if merge::length(setting) != 1 {
+        return Err(error(reader, &format!(
+            "expected group with single variant member, found {} members", 
+            merge::length(setting))));
+    }
+
+    let variant = merge::element(setting, 0);
+    Ok((merge::setting_name(variant), reader.child(variant)))
+}
+
+/// Return error of unknown enum variant.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::{Config, Errors};
+/// use librustconfig::typed;
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("level = \"trace\";").unwrap();
+/// assert_eq!(typed::unknown_variant(&cfg.value("level").unwrap(), "trace",
+///     &["debug", "info"]), Errors::ValueError(String::from(
+///         "line 1: level: unknown variant \"trace\", expected one of debug, \
+///         info")));
+/// ```
+pub fn unknown_variant(reader : &OptionReader, name : &str, 
+    variants : &[&str]) -> Errors {
+    error(reader, &format!("unknown variant {:?}, expected one of {}", name,
+        variants.join(", ")))
+}
+
+/// Remove members of enum variant group other than variant name, so variant
+/// written to existing group replaces previous one.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed;
+/// 
+/// let mut cfg = Config::new();
+/// cfg.load_from_string("mode = { Tcp = { port = 80; }; };").unwrap();
+/// let mode = typed::create_group(&cfg.root(), "mode").unwrap();
+/// typed::retain_variant(&mode, "Udp");
+/// assert!(cfg.value("mode.Tcp").is_none());
+/// ```
+pub fn retain_variant(writer : &OptionWriter, name : &str) {
+    if let Some(group) = writer.element {
+        for index in (0..merge::length(group)).rev() {
+            if merge::setting_name(merge::element(group, index)) != name {
+                unsafe { raw::config_setting_remove_elem(group, index); }
+            }
+        }
+    }
+}
+
+/// Create group member or collection element group, name is ignored for 
+/// collections. Existing group member is reused, member of other type is 
+/// replaced.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::config::Config;
+/// use librustconfig::typed;
+/// 
+/// let cfg = Config::new();
+/// let server = typed::create_group(&cfg.root(), "server").unwrap();
+/// server.write_int32("port", 80);
+/// assert_eq!(cfg.value("server.port").unwrap().as_int32(), Some(80));
+/// ```
+pub fn create_group(writer : &OptionWriter, name : &str) 
+    -> Result<OptionWriter> {
+    add(writer, name, raw::CONFIG_TYPE_GROUP)
+        .map(|setting| { OptionWriter::new(Some(setting)) })
+}
+
+// Find group member by name.
+fn member(reader : &OptionReader, name : &str) 
+    -> Result<Option<OptionReader>> {
+    
+    let setting = expect(reader, &[raw::CONFIG_TYPE_GROUP])?;
+    let name = CString::new(name).map_err(|_| { 
+        error(reader, &format!("invalid setting name {:?}", name)) 
+    })?;
+    let member = unsafe { 
+        raw::config_setting_get_member(setting, name.as_ptr()) 
+    };
+
+    if member.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(reader.child(member)))
+    }
+}
+
+// Check setting is one of types and return it.
+fn expect(reader : &OptionReader, kinds : &[i16]) -> Result<Setting> {
+    let setting = reader.element.ok_or(Errors::ElementNotExists)?;
+    let kind = merge::setting_type(setting);
+    
+    if kinds.contains(&kind) {
+        Ok(setting)
+    } else {
+        Err(error(reader, &format!("expected {}, found {}", 
+            schema::type_name(kinds[0]), schema::type_name(kind))))
+    }
+}
+
+// Add setting of type to collection or group. Existing group member of the
+// same type is updated in place, member of other type is replaced at its 
+// position keeping its state, collections are emptied.
+fn add(writer : &OptionWriter, name : &str, kind : i16) -> Result<Setting> {
+    let parent = writer.element.ok_or(Errors::ElementNotExists)?;
+    let collection = matches!(merge::setting_type(parent), 
+        raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST);
+    
+    let path = {
+        if collection {
+            format!("{}.[{}]", merge::setting_path(parent), 
+                merge::length(parent))
+        } else {
+            schema::join_path(&merge::setting_path(parent), name)
+        }
+    };
+    let name = CString::new(if collection { "" } else { name })
+        .map_err(|_| { 
+            Errors::ValueError(format!("{}: invalid setting name", path)) 
+        })?;
+
+    let setting = {
+        if collection {
+            unsafe { 
+                raw::config_setting_add(parent, name.as_ptr(), kind as i32) 
+            }
+        } else {
+            merge::upsert(parent, &[name.to_string_lossy().into_owned()], 
+                kind).unwrap_or(ptr::null_mut())
+        }
+    };
+    if setting.is_null() {
+        return Err(Errors::ValueError(format!("{}: can't add {} setting", 
+            path.trim_start_matches('.'), schema::type_name(kind))));
+    }
+    Ok(setting)
+}
+
+// Create error of setting with location.
+fn error(reader : &OptionReader, message : &str) -> Errors {
+    match reader.element {
+        Some(setting) => {
+            Errors::ValueError(format!("{}: {}", 
+                merge::setting_location(setting), message))
+        },
+        None => { Errors::ValueError(message.to_string()) }
+    }
+}
+
+// Create error of group member with location of group.
+fn member_error(reader : &OptionReader, name : &str, message : &str) 
+    -> Errors {
+    
+    let path = schema::join_path(&reader.element.map(merge::setting_path)
+        .unwrap_or_default(), name);
+    match reader.source_line() {
+        Some(line) => {
+            match reader.source_file() {
+                Some(file) => {
+                    Errors::ValueError(format!("{}:{}: {}: {}", file, line, 
+                        path, message))
+                },
+                None => {
+                    Errors::ValueError(format!("line {}: {}: {}", line, path, 
+                        message))
+                }
+            }
+        },
+        None => { Errors::ValueError(format!("{}: {}", path, message)) }
+    }
+}
+
+// Read integer value of setting.
+fn read_integer(reader : &OptionReader) -> Result<i64> {
+    let setting = expect(reader, &[raw::CONFIG_TYPE_INT, 
+        raw::CONFIG_TYPE_INT64])?;
+    Ok(unsafe { raw::config_setting_get_int64(setting) })
+}
+
+// Write integer value as int setting if it fits, as int64 setting otherwise.
+fn write_integer(writer : &OptionWriter, name : &str, value : i64, 
+    format : Format) -> Result<()> {
+    
+    let setting = {
+        match i32::try_from(value) {
+            Ok(value) => {
+                let setting = add(writer, name, raw::CONFIG_TYPE_INT)?;
+                unsafe { raw::config_setting_set_int(setting, value); }
+                setting
+            },
+            Err(_) => {
+                let setting = add(writer, name, raw::CONFIG_TYPE_INT64)?;
+                unsafe { raw::config_setting_set_int64(setting, value); }
+                setting
+            }
+        }
+    };
+
+    if format.hex {
+        unsafe { 
+            raw::config_setting_set_format(setting, 
+                raw::CONFIG_FORMAT_HEX as i16); 
+        }
+    }
+    Ok(())
+}
+
+macro_rules! integer_value {
+    ($($kind:ty),*) => {
+        $(
+            impl ConfigValue for $kind {
+                fn read_value(reader : &OptionReader) -> Result<$kind> {
+                    let value = read_integer(reader)?;
+                    <$kind>::try_from(value).map_err(|_| {
+                        error(reader, &format!("value {} is out of {} range",
+                            value, stringify!($kind)))
+                    })
+                }
+
+                fn write_value(&self, writer : &OptionWriter, name : &str, 
+                    format : Format) -> Result<()> {
+                    let value = i64::try_from(*self).map_err(|_| {
+                        Errors::ValueError(format!(
+                            "{}: value {} is out of 64bit integer range", 
+                            name, self))
+                    })?;
+                    write_integer(writer, name, value, format)
+                }
+
+                fn is_scalar() -> bool {
+                    true
+                }
+            }
+        )*
+    };
+}
+
+integer_value!(i8, i16, i32, i64, u8, u16, u32, u64, isize, usize);
+
+impl ConfigValue for f64 {
+    fn read_value(reader : &OptionReader) -> Result<f64> {
+        let setting = expect(reader, &[raw::CONFIG_TYPE_FLOAT, 
+            raw::CONFIG_TYPE_INT, raw::CONFIG_TYPE_INT64])?;
+        
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_FLOAT => { 
+                Ok(unsafe { raw::config_setting_get_float(setting) }) 
+            },
+            _ => { 
+                Ok(unsafe { raw::config_setting_get_int64(setting) } as f64) 
+            }
+        }
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, _ : Format) 
+        -> Result<()> {
+        let setting = add(writer, name, raw::CONFIG_TYPE_FLOAT)?;
+        unsafe { raw::config_setting_set_float(setting, *self); }
+        Ok(())
+    }
+
+    fn is_scalar() -> bool {
+        true
+    }
+}
+
+impl ConfigValue for f32 {
+    fn read_value(reader : &OptionReader) -> Result<f32> {
+        f64::read_value(reader).map(|value| { value as f32 })
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, 
+        format : Format) -> Result<()> {
+        (*self as f64).write_value(writer, name, format)
+    }
+
+    fn is_scalar() -> bool {
+        true
+    }
+}
+
+impl ConfigValue for bool {
+    fn read_value(reader : &OptionReader) -> Result<bool> {
+        let setting = expect(reader, &[raw::CONFIG_TYPE_BOOL])?;
+        Ok(unsafe { raw::config_setting_get_bool(setting) } != 0)
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, _ : Format) 
+        -> Result<()> {
+        let setting = add(writer, name, raw::CONFIG_TYPE_BOOL)?;
+        unsafe { raw::config_setting_set_bool(setting, *self as i32); }
+        Ok(())
+    }
+
+    fn is_scalar() -> bool {
+        true
+    }
+}
+
+impl ConfigValue for String {
+    fn read_value(reader : &OptionReader) -> Result<String> {
+        let setting = expect(reader, &[raw::CONFIG_TYPE_STRING])?;
+        let value = unsafe { raw::config_setting_get_string(setting) };
+        if value.is_null() {
+            return Ok(String::new());
+        }
+        Ok(unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned())
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, _ : Format) 
+        -> Result<()> {
+        let value = CString::new(self.as_str()).map_err(|_| {
+            Errors::ValueError(format!("{}: string contains nul character", 
+                name))
+        })?;
+        let setting = add(writer, name, raw::CONFIG_TYPE_STRING)?;
+        unsafe { raw::config_setting_set_string(setting, value.as_ptr()); }
+        Ok(())
+    }
+
+    fn is_scalar() -> bool {
+        true
+    }
+}
+
+impl<T> ConfigValue for Vec<T> where T: ConfigValue {
+    fn read_value(reader : &OptionReader) -> Result<Vec<T>> {
+        expect(reader, &[raw::CONFIG_TYPE_ARRAY, raw::CONFIG_TYPE_LIST])?;
+        reader.as_list().map(|element| { T::read_value(&element) }).collect()
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, 
+        format : Format) -> Result<()> {
+        
+        let kind = {
+            match format.collection {
+                CollectionFormat::Array => { raw::CONFIG_TYPE_ARRAY },
+                CollectionFormat::List => { raw::CONFIG_TYPE_LIST },
+                CollectionFormat::Auto if T::is_scalar() => { 
+                    raw::CONFIG_TYPE_ARRAY 
+                },
+                CollectionFormat::Auto => { raw::CONFIG_TYPE_LIST }
+            }
+        };
+
+        let collection = OptionWriter::new(Some(add(writer, name, kind)?));
+        let format = Format { collection : CollectionFormat::Auto, ..format };
+        for value in self {
+            value.write_value(&collection, "", format)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> ConfigValue for Option<T> where T: ConfigValue {
+    fn read_value(reader : &OptionReader) -> Result<Option<T>> {
+        T::read_value(reader).map(Some)
+    }
+
+    fn write_value(&self, writer : &OptionWriter, name : &str, 
+        format : Format) -> Result<()> {
+        match self {
+            Some(value) => { value.write_value(writer, name, format) },
+            None => {
+                // Member written before is removed.
+                let name = CString::new(name).unwrap_or_default();
+                if let Some(parent) = writer.element {
+                    let member = unsafe { 
+                        raw::config_setting_get_member(parent, name.as_ptr()) 
+                    };
+                    if !member.is_null() {
+                        unsafe { 
+                            raw::config_setting_remove_elem(parent, 
+                                raw::config_setting_index(member) as u32); 
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn missing() -> Option<Option<T>> {
+        Some(None)
+    }
+
+    fn is_scalar() -> bool {
+        T::is_scalar()
+    }
+}
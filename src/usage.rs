@@ -0,0 +1,142 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::OptionReader;
+use crate::merge::{self, Setting};
+
+use std::fmt;
+
+/// Setting present in config but never read.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnusedSetting {
+    path : String,
+    file : Option<String>,
+    line : Option<u32>
+}
+
+impl UnusedSetting {
+
+    // Constructor.
+    fn new(setting : Setting) -> UnusedSetting {
+        let reader = OptionReader::new(Some(setting));
+        UnusedSetting {
+            path : merge::setting_path(setting),
+            file : reader.source_file(),
+            line : reader.source_line()
+        }
+    }
+
+    /// Return path of setting.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("server = { sever_port = 80; };").unwrap();
+    /// cfg.value("server");
+    /// assert_eq!(cfg.unused_settings()[0].path(), "server.sever_port");
+    /// ```
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return name of file setting was read from.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("port = 80;").unwrap();
+    /// assert_eq!(cfg.unused_settings()[0].file(), None);
+    /// ```
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Return line setting was read from.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("\nport = 80;").unwrap();
+    /// assert_eq!(cfg.unused_settings()[0].line(), Some(2));
+    /// ```
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+}
+
+/// Unused setting is shown as `file:line: path`.
+impl fmt::Display for UnusedSetting {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => { write!(f, "{}:{}: ", file, line)?; },
+            (Some(file), None) => { write!(f, "{}: ", file)?; },
+            (None, Some(line)) => { write!(f, "line {}: ", line)?; },
+            (None, None) => {}
+        }
+        write!(f, "{}", self.path)
+    }
+}
+
+// Collect unused settings of group or collection which was read, accessed 
+// tells if setting was read.
+pub(crate) fn unused(setting : Setting, accessed : &dyn Fn(Setting) -> bool) 
+    -> Vec<UnusedSetting> {
+    
+    let mut settings = Vec::new();
+    collect(setting, accessed, &mut settings);
+    settings
+}
+
+// Collect unused members of setting, recurse into members which were read.
+fn collect(setting : Setting, accessed : &dyn Fn(Setting) -> bool, 
+    settings : &mut Vec<UnusedSetting>) {
+    
+    let kind = merge::setting_type(setting);
+    if kind != raw::CONFIG_TYPE_GROUP && kind != raw::CONFIG_TYPE_ARRAY && 
+        kind != raw::CONFIG_TYPE_LIST {
+        return;
+    }
+
+    for index in 0..merge::length(setting) {
+        let member = merge::element(setting, index);
+        if accessed(member) {
+            collect(member, accessed, settings);
+        } else if kind == raw::CONFIG_TYPE_GROUP {
+            settings.push(UnusedSetting::new(member));
+        }
+    }
+}
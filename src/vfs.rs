@@ -0,0 +1,461 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Filesystem used by `Config` to read configuration files and includes and
+/// to save configuration.
+pub trait ConfigFs {
+
+    /// Read file contents.
+    fn read(&self, path : &Path) -> io::Result<String>;
+
+    /// Replace file contents, file is created if not exists.
+    fn write(&self, path : &Path, contents : &str) -> io::Result<()>;
+
+    /// Return true if file or directory exists.
+    fn exists(&self, path : &Path) -> bool;
+
+    /// Return paths of directory entries.
+    fn list(&self, path : &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Return true if path is directory.
+    fn is_dir(&self, path : &Path) -> bool {
+        self.list(path).is_ok()
+    }
+
+    /// Return path used to identify file, e.g. to detect include cycles.
+    fn canonicalize(&self, path : &Path) -> PathBuf {
+        normalize(path)
+    }
+
+    /// Return path of file libconfig can read directly from disk, files 
+    /// without local path are read by `read` and passed to libconfig through
+    /// temporary file.
+    fn local_path(&self, _path : &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Rename file, replacing destination file if it exists. `replace_file` 
+    /// is as atomic as rename.
+    fn rename(&self, from : &Path, to : &Path) -> io::Result<()>;
+
+    /// Remove file.
+    fn remove(&self, path : &Path) -> io::Result<()>;
+
+    /// Create new file, fail if it exists. File gets permissions of template 
+    /// file from the start, so contents are never readable by others when 
+    /// template isn't.
+    fn create(&self, path : &Path, contents : &str, template : Option<&Path>) 
+        -> io::Result<()> {
+        
+        if self.exists(path) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, 
+                format!("{}: file exists", path.display())));
+        }
+        self.write(path, contents)?;
+        match template {
+            Some(template) => { self.copy_permissions(template, path) },
+            None => { Ok(()) }
+        }
+    }
+
+    /// Flush file contents and its directory entry to storage.
+    fn sync(&self, _path : &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Copy permissions and, where possible, owner of file to another file.
+    fn copy_permissions(&self, _from : &Path, _to : &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Filesystem on disk.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskFs;
+
+/// Filesystem held in memory. Clones share the same files.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFs {
+    files : Arc<Mutex<BTreeMap<PathBuf, String>>>
+}
+
+impl ConfigFs for DiskFs {
+    fn read(&self, path : &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path : &Path, contents : &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn exists(&self, path : &Path) -> bool {
+        path.exists()
+    }
+
+    fn list(&self, path : &Path) -> io::Result<Vec<PathBuf>> {
+        let mut result = Vec::new();
+        for entry in fs::read_dir(path)? {
+            result.push(entry?.path());
+        }
+        Ok(result)
+    }
+
+    fn is_dir(&self, path : &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn canonicalize(&self, path : &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| normalize(path))
+    }
+
+    fn local_path(&self, path : &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+
+    fn rename(&self, from : &Path, to : &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path : &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create(&self, path : &Path, contents : &str, template : Option<&Path>) 
+        -> io::Result<()> {
+        
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            let mode = match template {
+                Some(template) => { 
+                    fs::metadata(template)?.permissions().mode() & 0o7777 
+                },
+                None => { 0o666 }
+            };
+            options.mode(mode);
+        }
+
+        let mut file = options.open(path)?;
+        if let Err(error) = file.write_all(contents.as_bytes()) {
+            let _ = fs::remove_file(path);
+            return Err(error);
+        }
+        match template {
+            Some(template) => { self.copy_permissions(template, path) },
+            None => { Ok(()) }
+        }
+    }
+
+    fn sync(&self, path : &Path) -> io::Result<()> {
+        fs::File::open(path)?.sync_all()?;
+
+        if cfg!(unix) {
+            fs::File::open(parent_dir(path))?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn copy_permissions(&self, from : &Path, to : &Path) -> io::Result<()> {
+        let metadata = fs::metadata(from)?;
+        fs::set_permissions(to, metadata.permissions())?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            // Changing owner requires privileges, file stays owned by current
+            // user when it is not permitted.
+            let _ = std::os::unix::fs::chown(to, Some(metadata.uid()), 
+                Some(metadata.gid()));
+        }
+        Ok(())
+    }
+}
+
+impl MemoryFs {
+
+    /// Constructor.
+    /// Create new empty MemoryFs.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// ```
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    /// Add file or replace its contents.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("/etc/app/app.cfg", "port = 8080;");
+    /// ```
+    pub fn insert<P, S>(&self, path : P, contents : S) 
+        where P: AsRef<Path>, S: Into<String> {
+        self.files.lock().unwrap().insert(normalize(path.as_ref()), 
+            contents.into());
+    }
+
+    /// Return file contents.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 8080;");
+    /// assert_eq!(fs.get("app.cfg").unwrap(), "port = 8080;");
+    /// ```
+    pub fn get<P>(&self, path : P) -> Option<String> where P: AsRef<Path> {
+        self.files.lock().unwrap().get(&normalize(path.as_ref())).cloned()
+    }
+
+    /// Remove file, return its contents.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 8080;");
+    /// assert_eq!(fs.take("app.cfg").unwrap(), "port = 8080;");
+    /// ```
+    pub fn take<P>(&self, path : P) -> Option<String> where P: AsRef<Path> {
+        self.files.lock().unwrap().remove(&normalize(path.as_ref()))
+    }
+
+    // Remove file, return error if it does not exist.
+    fn remove_file(&self, path : &Path) -> io::Result<String> {
+        self.take(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, 
+                format!("{}: file not found", path.display()))
+        })
+    }
+}
+
+impl ConfigFs for MemoryFs {
+    fn read(&self, path : &Path) -> io::Result<String> {
+        self.get(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, 
+                format!("{}: file not found", path.display()))
+        })
+    }
+
+    fn write(&self, path : &Path, contents : &str) -> io::Result<()> {
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn exists(&self, path : &Path) -> bool {
+        self.get(path).is_some() || self.is_dir(path)
+    }
+
+    fn rename(&self, from : &Path, to : &Path) -> io::Result<()> {
+        let contents = self.remove_file(from)?;
+        self.insert(to, contents);
+        Ok(())
+    }
+
+    fn remove(&self, path : &Path) -> io::Result<()> {
+        self.remove_file(path).map(|_| ())
+    }
+
+    fn list(&self, path : &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = normalize(path);
+        let mut result = Vec::<PathBuf>::new();
+
+        for file in self.files.lock().unwrap().keys() {
+            let relative = {
+                match file.strip_prefix(&dir) {
+                    Ok(relative) => { relative },
+                    Err(_) => { continue; }
+                }
+            };
+
+            if let Some(Component::Normal(name)) = relative.components()
+                .next() {
+                let entry = dir.join(name);
+                if !result.contains(&entry) {
+                    result.push(entry);
+                }
+            }
+        }
+
+        if result.is_empty() {
+            Err(io::Error::new(io::ErrorKind::NotFound, 
+                format!("{}: directory not found", path.display())))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+/// Normalize path lexically, remove `.` components and resolve `..` 
+/// components.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::vfs::normalize;
+/// use std::path::{Path, PathBuf};
+/// 
+/// assert_eq!(normalize(Path::new("./conf/../app.cfg")), 
+///     PathBuf::from("app.cfg"));
+/// ```
+pub fn normalize(path : &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                match result.components().next_back() {
+                    Some(Component::Normal(_)) => { result.pop(); },
+                    Some(Component::RootDir) => {},
+                    _ => { result.push(component.as_os_str()); }
+                }
+            },
+            component => { result.push(component.as_os_str()); }
+        }
+    }
+    result
+}
+
+/// Counter used to create unique temporary file names.
+static TEMP_FILE_COUNTER : AtomicUsize = AtomicUsize::new(0);
+
+/// Replace file contents. Contents are written to temporary file in the 
+/// same directory, flushed to storage and renamed over the file. With 
+/// `DiskFs` replacement is atomic, the file holds either previous or new 
+/// contents even if writing fails midway, other filesystems are as atomic as
+/// their `rename`. New file gets permissions and owner of the previous one. 
+/// Previous contents are kept in `<file>.bak` if backup is true.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::vfs::{replace_file, MemoryFs};
+/// use std::path::Path;
+/// 
+/// let fs = MemoryFs::new();
+/// fs.insert("app.cfg", "port = 8080;");
+/// replace_file(&fs, Path::new("app.cfg"), "port = 9090;", true).unwrap();
+/// assert_eq!(fs.get("app.cfg.bak").unwrap(), "port = 8080;");
+/// ```
+pub fn replace_file(fs : &dyn ConfigFs, path : &Path, contents : &str, 
+    backup : bool) -> io::Result<()> {
+    
+    let temp = temp_path(path);
+    let mut created = Vec::new();
+    let mut replace = || -> io::Result<()> {
+        write_temp_file(fs, path, &temp, contents, &mut created)?;
+        if backup && fs.exists(path) {
+            let backup = backup_path(path);
+            let backup_temp = temp_path(&backup);
+            write_temp_file(fs, path, &backup_temp, &fs.read(path)?, 
+                &mut created)?;
+            fs.rename(&backup_temp, &backup)?;
+            fs.sync(&backup)?;
+        }
+
+        fs.rename(&temp, path)?;
+        fs.sync(path)
+    };
+    let result = replace();
+
+    if result.is_err() {
+        for temp in created {
+            if fs.exists(&temp) {
+                let _ = fs.remove(&temp);
+            }
+        }
+    }
+    result
+}
+
+/// Return path of backup file kept by `replace_file`.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::vfs::backup_path;
+/// use std::path::{Path, PathBuf};
+/// 
+/// assert_eq!(backup_path(Path::new("/etc/app.cfg")), 
+///     PathBuf::from("/etc/app.cfg.bak"));
+/// ```
+pub fn backup_path(path : &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+// Create temporary file with contents and permissions of file at path and 
+// flush it to storage, created file is added to list.
+fn write_temp_file(fs : &dyn ConfigFs, path : &Path, temp : &Path, 
+    contents : &str, created : &mut Vec<PathBuf>) -> io::Result<()> {
+    
+    let template = if fs.exists(path) { Some(path) } else { None };
+    fs.create(temp, contents, template)?;
+    created.push(temp.to_path_buf());
+    fs.sync(temp)
+}
+
+// Return temporary file path in the same directory as path.
+fn temp_path(path : &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    
+    parent_dir(path).join(format!(".{}.tmp-{}-{}", name, process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::SeqCst)))
+}
+
+// Return directory containing path.
+fn parent_dir(path : &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => { parent },
+        _ => { Path::new(".") }
+    }
+}
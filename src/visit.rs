@@ -0,0 +1,570 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{Config, Errors, OptionReader, OptionWriter, Result};
+use crate::merge::{self, Setting};
+use crate::schema;
+
+use std::ffi::CString;
+
+/// Visitor of config tree, see `Config::walk`. Callbacks receive setting 
+/// path, collection elements are addressed by index and root group has 
+/// empty path. Enter callbacks return false to skip children of setting.
+#[allow(unused_variables)]
+pub trait ConfigVisitor {
+
+    /// Called before group members are visited.
+    fn enter_group(&mut self, path : &str, group : &OptionReader) -> bool {
+        true
+    }
+
+    /// Called after group members are visited.
+    fn leave_group(&mut self, path : &str, group : &OptionReader) {}
+
+    /// Called before array elements are visited.
+    fn enter_array(&mut self, path : &str, array : &OptionReader) -> bool {
+        true
+    }
+
+    /// Called after array elements are visited.
+    fn leave_array(&mut self, path : &str, array : &OptionReader) {}
+
+    /// Called before list elements are visited.
+    fn enter_list(&mut self, path : &str, list : &OptionReader) -> bool {
+        true
+    }
+
+    /// Called after list elements are visited.
+    fn leave_list(&mut self, path : &str, list : &OptionReader) {}
+
+    /// Called for integer, float, boolean and string settings.
+    fn visit_scalar(&mut self, path : &str, value : &OptionReader) {}
+}
+
+/// Visitor changing config tree in place, see `Config::walk_mut`. Settings
+/// removed by editor are not visited further, settings added to groups and
+/// collections in enter callbacks are visited.
+#[allow(unused_variables)]
+pub trait ConfigVisitorMut {
+
+    /// Called before group members are visited.
+    fn enter_group(&mut self, path : &str, group : &mut OptionEditor) 
+        -> bool {
+        true
+    }
+
+    /// Called after group members are visited.
+    fn leave_group(&mut self, path : &str, group : &mut OptionEditor) {}
+
+    /// Called before array elements are visited.
+    fn enter_array(&mut self, path : &str, array : &mut OptionEditor) 
+        -> bool {
+        true
+    }
+
+    /// Called after array elements are visited.
+    fn leave_array(&mut self, path : &str, array : &mut OptionEditor) {}
+
+    /// Called before list elements are visited.
+    fn enter_list(&mut self, path : &str, list : &mut OptionEditor) -> bool {
+        true
+    }
+
+    /// Called after list elements are visited.
+    fn leave_list(&mut self, path : &str, list : &mut OptionEditor) {}
+
+    /// Called for integer, float, boolean and string settings.
+    fn visit_scalar(&mut self, path : &str, value : &mut OptionEditor) {}
+}
+
+/// Editor of setting visited by `ConfigVisitorMut`.
+pub struct OptionEditor {
+    element : Setting,
+    removed : bool
+}
+
+impl OptionEditor {
+
+    // Constructor.
+    pub(crate) fn new(element : Setting) -> OptionEditor {
+        OptionEditor { element, removed : false }
+    }
+
+    /// Return reader of setting, None if setting was removed.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Doubler;
+    /// impl ConfigVisitorMut for Doubler {
+    ///     fn visit_scalar(&mut self, _path : &str, 
+    ///         value : &mut OptionEditor) {
+    ///         let reader = value.reader().unwrap();
+    ///         if let Some(number) = reader.as_int32() {
+    ///             value.set_int32(number * 2).unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("a = 1; b = [ 2, 3 ];").unwrap();
+    /// cfg.walk_mut(&mut Doubler);
+    /// assert_eq!(cfg.value("b.[1]").unwrap().as_int32(), Some(6));
+    /// ```
+    pub fn reader(&self) -> Option<OptionReader> {
+        if self.removed {
+            return None;
+        }
+        Some(OptionReader::new(Some(self.element)))
+    }
+
+    /// Return writer adding settings to group or collection, None if setting 
+    /// was removed.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Versioner;
+    /// impl ConfigVisitorMut for Versioner {
+    ///     fn enter_group(&mut self, path : &str, group : &mut OptionEditor) 
+    ///         -> bool {
+    ///         if path.is_empty() {
+    ///             group.writer().unwrap().write_int32("version", 2);
+    ///         }
+    ///         false
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.walk_mut(&mut Versioner);
+    /// assert_eq!(cfg.value("version").unwrap().as_int32(), Some(2));
+    /// ```
+    pub fn writer(&self) -> Option<OptionWriter> {
+        if self.removed {
+            return None;
+        }
+        Some(OptionWriter::new(Some(self.element)))
+    }
+
+    /// Set integer value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Zero;
+    /// impl ConfigVisitorMut for Zero {
+    ///     fn visit_scalar(&mut self, _path : &str, 
+    ///         value : &mut OptionEditor) {
+    ///         assert!(value.set_int32(0).is_err());
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("name = \"app\";").unwrap();
+    /// cfg.walk_mut(&mut Zero);
+    /// ```
+    pub fn set_int32(&mut self, value : i32) -> Result<()> {
+        let element = self.element()?;
+        let result = unsafe { 
+            raw::config_setting_set_int(element, value) 
+        };
+        self.check(element, result, raw::CONFIG_TYPE_INT)
+    }
+
+    /// Set 64bit integer value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Limit;
+    /// impl ConfigVisitorMut for Limit {
+    ///     fn visit_scalar(&mut self, _path : &str, 
+    ///         value : &mut OptionEditor) {
+    ///         value.set_int64(1 << 40).unwrap();
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("size = 1L;").unwrap();
+    /// cfg.walk_mut(&mut Limit);
+    /// assert_eq!(cfg.value("size").unwrap().as_int64(), Some(1 << 40));
+    /// ```
+    pub fn set_int64(&mut self, value : i64) -> Result<()> {
+        let element = self.element()?;
+        let result = unsafe { 
+            raw::config_setting_set_int64(element, value) 
+        };
+        self.check(element, result, raw::CONFIG_TYPE_INT64)
+    }
+
+    /// Set float value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Round;
+    /// impl ConfigVisitorMut for Round {
+    ///     fn visit_scalar(&mut self, _path : &str, 
+    ///         value : &mut OptionEditor) {
+    ///         let number = value.reader().unwrap().as_float64().unwrap();
+    ///         value.set_float64(number.round()).unwrap();
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("ratio = 2.7;").unwrap();
+    /// cfg.walk_mut(&mut Round);
+    /// assert_eq!(cfg.value("ratio").unwrap().as_float64(), Some(3.0));
+    /// ```
+    pub fn set_float64(&mut self, value : f64) -> Result<()> {
+        let element = self.element()?;
+        let result = unsafe { 
+            raw::config_setting_set_float(element, value) 
+        };
+        self.check(element, result, raw::CONFIG_TYPE_FLOAT)
+    }
+
+    /// Set boolean value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Disable;
+    /// impl ConfigVisitorMut for Disable {
+    ///     fn visit_scalar(&mut self, path : &str, value : &mut OptionEditor) {
+    ///         if path.ends_with("enabled") {
+    ///             value.set_bool(false).unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("tls = { enabled = true; };").unwrap();
+    /// cfg.walk_mut(&mut Disable);
+    /// assert_eq!(cfg.value("tls.enabled").unwrap().as_bool(), Some(false));
+    /// ```
+    pub fn set_bool(&mut self, value : bool) -> Result<()> {
+        let element = self.element()?;
+        let result = unsafe { 
+            raw::config_setting_set_bool(element, value as i32) 
+        };
+        self.check(element, result, raw::CONFIG_TYPE_BOOL)
+    }
+
+    /// Set string value.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Redact;
+    /// impl ConfigVisitorMut for Redact {
+    ///     fn visit_scalar(&mut self, path : &str, value : &mut OptionEditor) {
+    ///         if path.ends_with("password") {
+    ///             value.set_string("***").unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("db = { password = \"secret\"; };").unwrap();
+    /// cfg.walk_mut(&mut Redact);
+    /// assert_eq!(cfg.value("db.password").unwrap().as_string(), 
+    ///     Some(String::from("***")));
+    /// ```
+    pub fn set_string<S>(&mut self, value : S) -> Result<()> 
+        where S: Into<String> {
+        
+        let element = self.element()?;
+        let value = CString::new(value.into()).map_err(|_| { 
+            Errors::ValueError(format!("{}: invalid string value", 
+                merge::setting_path(element)))
+        })?;
+        let result = unsafe { 
+            raw::config_setting_set_string(element, value.as_ptr()) 
+        };
+        self.check(element, result, raw::CONFIG_TYPE_STRING)
+    }
+
+    /// Remove setting from config.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct Prune;
+    /// impl ConfigVisitorMut for Prune {
+    ///     fn enter_group(&mut self, path : &str, group : &mut OptionEditor) 
+    ///         -> bool {
+    ///         if path.ends_with("debug") {
+    ///             group.remove().unwrap();
+    ///         }
+    ///         true
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "debug = { level = 3; }; list = ( { debug = {}; } );").unwrap();
+    /// cfg.walk_mut(&mut Prune);
+    /// assert!(cfg.value("debug").is_none());
+    /// assert!(cfg.value("list.[0].debug").is_none());
+    /// ```
+    pub fn remove(&mut self) -> Result<()> {
+        let element = self.element().map_err(|_| { Errors::DeleteError })?;
+        let parent = raw::config_setting_parent(element);
+        if parent.is_null() {
+            return Err(Errors::DeleteError);
+        }
+
+        let index = unsafe { raw::config_setting_index(element) };
+        let result = unsafe { 
+            raw::config_setting_remove_elem(parent, index as u32) 
+        };
+        if result == raw::CONFIG_TRUE {
+            self.removed = true;
+            Ok(())
+        } else {
+            Err(Errors::DeleteError)
+        }
+    }
+
+    // Return edited setting, removed setting is freed and can't be used.
+    fn element(&self) -> Result<Setting> {
+        if self.removed {
+            Err(Errors::ElementNotExists)
+        } else {
+            Ok(self.element)
+        }
+    }
+
+    // Check result of libconfig setter.
+    fn check(&self, element : Setting, result : i32, kind : i16) -> Result<()> {
+        if result == raw::CONFIG_TRUE {
+            Ok(())
+        } else {
+            Err(Errors::ValueError(format!("{}: can't set {} value of {}", 
+                merge::setting_path(element), schema::type_name(kind),
+                schema::type_name(merge::setting_type(element)))))
+        }
+    }
+}
+
+impl Config {
+
+    /// Walk config tree depth first in config order. Walk doesn't mark 
+    /// settings as accessed, see `Config::unused_settings`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, OptionReader};
+    /// use librustconfig::visit::ConfigVisitor;
+    /// 
+    /// #[derive(Default)]
+    /// struct Paths(Vec<String>);
+    /// impl ConfigVisitor for Paths {
+    ///     fn enter_list(&mut self, path : &str, _list : &OptionReader) 
+    ///         -> bool {
+    ///         self.0.push(format!("{} (list)", path));
+    ///         true
+    ///     }
+    ///     fn visit_scalar(&mut self, path : &str, _value : &OptionReader) {
+    ///         self.0.push(String::from(path));
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("a = 1; b = { c = ( 2, { d = 3; } ); };").unwrap();
+    /// let mut paths = Paths::default();
+    /// cfg.walk(&mut paths);
+    /// assert_eq!(paths.0, vec!["a", "b.c (list)", "b.c.[0]", "b.c.[1].d"]);
+    /// ```
+    pub fn walk<V>(&self, visitor : &mut V) where V: ConfigVisitor + ?Sized {
+        if let Some(root) = self.root_setting() {
+            walk(visitor, root, "");
+        }
+    }
+
+    /// Walk config tree depth first in config order and let visitor change 
+    /// settings in place.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// use librustconfig::visit::{ConfigVisitorMut, OptionEditor};
+    /// 
+    /// struct DropEmpty;
+    /// impl ConfigVisitorMut for DropEmpty {
+    ///     fn visit_scalar(&mut self, _path : &str, 
+    ///         value : &mut OptionEditor) {
+    ///         let text = value.reader().unwrap().as_string();
+    ///         if text.is_some_and(|s| { s.is_empty() }) {
+    ///             value.remove().unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("a = \"\"; b = \"x\"; c = ( \"\", \"\", \"y\" );")
+    ///     .unwrap();
+    /// cfg.walk_mut(&mut DropEmpty);
+    /// assert_eq!(cfg.save_to_string().unwrap(), 
+    ///     "b = \"x\";\nc = ( \"y\" );\n");
+    /// ```
+    pub fn walk_mut<V>(&mut self, visitor : &mut V) 
+        where V: ConfigVisitorMut + ?Sized {
+        
+        if let Some(root) = self.root_setting() {
+            walk_mut(visitor, root, "");
+        }
+    }
+}
+
+impl OptionReader {
+
+    /// Walk setting subtree depth first, see `Config::walk`. Paths are full 
+    /// config paths.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, OptionReader};
+    /// use librustconfig::visit::ConfigVisitor;
+    /// 
+    /// struct Count(usize);
+    /// impl ConfigVisitor for Count {
+    ///     fn visit_scalar(&mut self, _path : &str, _value : &OptionReader) {
+    ///         self.0 += 1;
+    ///     }
+    /// }
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string("a = [ 1, 2 ]; b = { c = 3; };").unwrap();
+    /// let mut count = Count(0);
+    /// cfg.value("a").unwrap().walk(&mut count);
+    /// assert_eq!(count.0, 2);
+    /// ```
+    pub fn walk<V>(&self, visitor : &mut V) where V: ConfigVisitor + ?Sized {
+        if let Some(element) = self.element {
+            walk(visitor, element, &merge::setting_path(element));
+        }
+    }
+}
+
+// Visit setting and its children.
+fn walk<V>(visitor : &mut V, setting : Setting, path : &str) 
+    where V: ConfigVisitor + ?Sized {
+    
+    let reader = OptionReader::new(Some(setting));
+    let kind = merge::setting_type(setting);
+    let enter = match kind {
+        raw::CONFIG_TYPE_GROUP => { visitor.enter_group(path, &reader) },
+        raw::CONFIG_TYPE_ARRAY => { visitor.enter_array(path, &reader) },
+        raw::CONFIG_TYPE_LIST => { visitor.enter_list(path, &reader) },
+        _ => {
+            visitor.visit_scalar(path, &reader);
+            return;
+        }
+    };
+
+    if enter {
+        for index in 0..merge::length(setting) {
+            let child = merge::element(setting, index);
+            walk(visitor, child, &child_path(path, child, index));
+        }
+    }
+
+    match kind {
+        raw::CONFIG_TYPE_GROUP => { visitor.leave_group(path, &reader); },
+        raw::CONFIG_TYPE_ARRAY => { visitor.leave_array(path, &reader); },
+        _ => { visitor.leave_list(path, &reader); }
+    }
+}
+
+// Visit setting and its children, return true if setting was removed.
+fn walk_mut<V>(visitor : &mut V, setting : Setting, path : &str) -> bool
+    where V: ConfigVisitorMut + ?Sized {
+    
+    let mut editor = OptionEditor::new(setting);
+    let kind = merge::setting_type(setting);
+    let enter = match kind {
+        raw::CONFIG_TYPE_GROUP => { visitor.enter_group(path, &mut editor) },
+        raw::CONFIG_TYPE_ARRAY => { visitor.enter_array(path, &mut editor) },
+        raw::CONFIG_TYPE_LIST => { visitor.enter_list(path, &mut editor) },
+        _ => {
+            visitor.visit_scalar(path, &mut editor);
+            return editor.removed;
+        }
+    };
+    if editor.removed {
+        return true;
+    }
+
+    if enter {
+        let mut index = 0;
+        while index < merge::length(setting) {
+            let child = merge::element(setting, index);
+            if !walk_mut(visitor, child, &child_path(path, child, index)) {
+                index += 1;
+            }
+        }
+    }
+
+    match kind {
+        raw::CONFIG_TYPE_GROUP => { visitor.leave_group(path, &mut editor); },
+        raw::CONFIG_TYPE_ARRAY => { visitor.leave_array(path, &mut editor); },
+        _ => { visitor.leave_list(path, &mut editor); }
+    }
+    editor.removed
+}
+
+// Return path of child setting.
+fn child_path(path : &str, child : Setting, index : u32) -> String {
+    let name = merge::setting_name(child);
+    if name.is_empty() {
+        schema::join_path(path, &format!("[{}]", index))
+    } else {
+        schema::join_path(path, &name)
+    }
+}
@@ -0,0 +1,703 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use crate::config::{Config, Errors, Result};
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Function preparing fresh config before it is loaded.
+type Setup = dyn Fn(&mut Config);
+
+/// Watches main config file and all included files and reloads config when
+/// they change. Config is reparsed into fresh `Config` and replaces active
+/// config only if parsing succeeds, otherwise previous config stays active
+/// and parse error is reported.
+/// 
+/// On Linux changes are detected by inotify watches of directories holding
+/// config files, files are also polled with poll interval, which is the only
+/// detection method on other systems, for files of non-local file systems 
+/// or if inotify is not available. Config is not `Send`, so watcher is used
+/// from thread owning the config.
+/// 
+/// # Example
+/// ```
+/// use librustconfig::vfs::MemoryFs;
+/// use librustconfig::watch::{ConfigWatcher, Reload};
+/// 
+/// let fs = MemoryFs::new();
+/// fs.insert("/etc/app.cfg", "port = 80;");
+/// 
+/// let setup_fs = fs.clone();
+/// let mut watcher = ConfigWatcher::with_setup("/etc/app.cfg", move |cfg| {
+///     cfg.set_fs(setup_fs.clone());
+/// }).unwrap();
+/// assert!(watcher.check().is_none());
+/// 
+/// fs.insert("/etc/app.cfg", "port = 8080;");
+/// assert_eq!(watcher.check(), Some(Reload::Reloaded));
+/// assert_eq!(watcher.config().value("port").unwrap().as_int32(), Some(8080));
+/// 
+/// fs.insert("/etc/app.cfg", "port = ;");
+/// match watcher.check() {
+///     Some(Reload::Failed(error)) => { 
+///         assert_eq!(error.line(), Some(1));
+///     },
+///     _ => { panic!("parse error expected"); }
+/// }
+/// assert_eq!(watcher.config().value("port").unwrap().as_int32(), Some(8080));
+/// ```
+pub struct ConfigWatcher {
+    path : PathBuf,
+    setup : Box<Setup>,
+    config : Config,
+    files : Vec<PathBuf>,
+    dirs : Vec<PathBuf>,
+    snapshot : Snapshot,
+    poll_interval : Duration,
+    polling : bool,
+    #[cfg(target_os = "linux")]
+    inotify : Option<inotify::Inotify>
+}
+
+/// Outcome of config reload.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Reload {
+    Reloaded,
+    Failed(ReloadError)
+}
+
+/// Error of config reload with location of parse error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReloadError {
+    file : Option<String>,
+    line : Option<u32>,
+    message : String
+}
+
+/// State of watched files and directories used to detect changes, files are
+/// stored with hash of their contents, directories with their entries.
+#[derive(Debug, PartialEq, Default)]
+struct Snapshot {
+    files : Vec<(PathBuf, Option<u64>)>,
+    dirs : Vec<(PathBuf, Vec<PathBuf>)>
+}
+
+impl ConfigWatcher {
+
+    /// Constructor.
+    /// Load config file and start watching it.
+    /// 
+    /// # Example
+    /// ```no_run
+    /// use librustconfig::watch::ConfigWatcher;
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     watcher.run(|reload, cfg| {
+    ///         /* ... */
+    ///         true
+    ///     });
+    /// }
+    /// ```
+    pub fn new<P>(path : P) -> Result<ConfigWatcher> where P: AsRef<Path> {
+        ConfigWatcher::with_setup(path, |_| {})
+    }
+
+    /// Load config file and start watching it, setup function prepares each
+    /// fresh config before it is loaded, e.g. sets file system, include 
+    /// directory and options.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::ConfigWatcher;
+    /// use std::path::Path;
+    /// 
+    /// let watcher = ConfigWatcher::with_setup("/etc/app/app.cfg", |cfg| {
+    ///     cfg.include_dir(Path::new("/etc/app"));
+    /// });
+    /// ```
+    pub fn with_setup<P, F>(path : P, setup : F) -> Result<ConfigWatcher> 
+        where P: AsRef<Path>, F: Fn(&mut Config) + 'static {
+        
+        let path = path.as_ref().to_path_buf();
+        let mut config = Config::new();
+        setup(&mut config);
+        config.load_from_file(&path)?;
+
+        let mut watcher = ConfigWatcher {
+            path,
+            setup : Box::new(setup),
+            config : Config::new(),
+            files : Vec::new(),
+            dirs : Vec::new(),
+            snapshot : Snapshot::default(),
+            poll_interval : Duration::from_secs(1),
+            polling : false,
+            #[cfg(target_os = "linux")]
+            inotify : None
+        };
+        watcher.activate(config);
+        Ok(watcher)
+    }
+
+    /// Set interval of polling watched files, default is one second.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::ConfigWatcher;
+    /// use std::time::Duration;
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     watcher.set_poll_interval(Duration::from_millis(200));
+    /// }
+    /// ```
+    pub fn set_poll_interval(&mut self, interval : Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Detect changes by polling only, without inotify.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::ConfigWatcher;
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     watcher.set_polling(true);
+    ///     assert!(watcher.is_polling());
+    /// }
+    /// ```
+    pub fn set_polling(&mut self, polling : bool) {
+        self.polling = polling;
+        self.update_watches();
+    }
+
+    /// Return true if changes are detected by polling only.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// use librustconfig::watch::ConfigWatcher;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 80;");
+    /// let watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+    ///     cfg.set_fs(fs.clone());
+    /// }).unwrap();
+    /// assert!(watcher.is_polling());
+    /// ```
+    pub fn is_polling(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            self.inotify.is_none()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            true
+        }
+    }
+
+    /// Return active config.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::ConfigWatcher;
+    /// 
+    /// if let Ok(watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     let port = watcher.config().value("port");
+    /// }
+    /// ```
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Return watched files: main file and included files of active config
+    /// and of config which failed to load.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// use librustconfig::watch::ConfigWatcher;
+    /// use std::path::PathBuf;
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 80;");
+    /// let watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+    ///     cfg.set_fs(fs.clone());
+    /// }).unwrap();
+    /// assert_eq!(watcher.files(), &[PathBuf::from("app.cfg")]);
+    /// ```
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Check watched files without blocking and reload config if they 
+    /// changed. Return None if nothing changed.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::{ConfigWatcher, Reload};
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     match watcher.check() {
+    ///         Some(Reload::Reloaded) => { /* ... */ },
+    ///         Some(Reload::Failed(error)) => { eprintln!("{}", error); },
+    ///         None => { /* ... */ }
+    ///     }
+    /// }
+    /// ```
+    pub fn check(&mut self) -> Option<Reload> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(inotify) = &self.inotify {
+                inotify.wait(Duration::from_secs(0));
+            }
+        }
+
+        if self.take_snapshot(None) == self.snapshot {
+            None
+        } else {
+            Some(self.reload())
+        }
+    }
+
+    /// Wait until watched files change or timeout expires and reload 
+    /// config. Return None if nothing changed before timeout.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::watch::ConfigWatcher;
+    /// use std::time::Duration;
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     let reload = watcher.wait(Duration::from_secs(5));
+    /// }
+    /// ```
+    pub fn wait(&mut self, timeout : Duration) -> Option<Reload> {
+        let deadline = Instant::now() + timeout;
+        
+        loop {
+            if let Some(reload) = self.check() {
+                return Some(reload);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            self.sleep((deadline - now).min(self.poll_interval));
+        }
+    }
+
+    /// Watch files until callback returns false. Callback receives outcome 
+    /// of each reload and active config.
+    /// 
+    /// # Example
+    /// ```no_run
+    /// use librustconfig::watch::{ConfigWatcher, Reload};
+    /// 
+    /// if let Ok(mut watcher) = ConfigWatcher::new("/etc/app.cfg") {
+    ///     watcher.run(|reload, cfg| {
+    ///         if let Reload::Failed(error) = reload {
+    ///             eprintln!("config is not reloaded: {}", error);
+    ///         }
+    ///         true
+    ///     });
+    /// }
+    /// ```
+    pub fn run<F>(&mut self, mut callback : F) 
+        where F: FnMut(&Reload, &Config) -> bool {
+        
+        loop {
+            if let Some(reload) = self.wait(self.poll_interval) {
+                if !callback(&reload, &self.config) {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Parse config into fresh config and activate it on success. Files of 
+    // loaded config are watched from contents they were parsed from, state
+    // of files of failed config is taken before parsing, so files changed 
+    // while config was parsed are reloaded again.
+    fn reload(&mut self) -> Reload {
+        let before = self.take_snapshot(None);
+        let mut config = Config::new();
+        (self.setup)(&mut config);
+
+        match config.load_from_file(&self.path) {
+            Ok(()) => {
+                self.activate(config);
+                Reload::Reloaded
+            },
+            Err(error) => {
+                let error = ReloadError::new(&config, &self.path, error);
+                for file in config.source_files() {
+                    if !self.files.contains(&file) {
+                        self.files.push(file);
+                    }
+                }
+                for dir in config.include_dirs() {
+                    if !self.dirs.contains(&dir) {
+                        self.dirs.push(dir);
+                    }
+                }
+                self.watch(Some(&before));
+                Reload::Failed(error)
+            }
+        }
+    }
+
+    // Make config active and watch its files starting from contents they 
+    // were parsed from.
+    fn activate(&mut self, config : Config) {
+        self.files = config.source_files();
+        self.dirs = config.include_dirs();
+        self.config = config;
+
+        let loaded = Snapshot {
+            files : self.files.iter().filter_map(|file| {
+                self.config.source_text(file).map(|contents| {
+                    (file.clone(), Some(hash(contents)))
+                })
+            }).collect(),
+            dirs : Vec::new()
+        };
+        self.watch(Some(&loaded));
+    }
+
+    // Take snapshot of watched files and watch their directories.
+    fn watch(&mut self, before : Option<&Snapshot>) {
+        self.snapshot = self.take_snapshot(before);
+        self.update_watches();
+    }
+
+    // Read state of watched files and directories, state found in previous 
+    // snapshot is reused.
+    fn take_snapshot(&self, previous : Option<&Snapshot>) -> Snapshot {
+        let fs = self.config.fs();
+        let file = |file : &PathBuf| -> (PathBuf, Option<u64>) {
+            let known = previous.and_then(|snapshot| { 
+                snapshot.files.iter().find(|(path, _)| { path == file }) 
+            });
+            match known {
+                Some(known) => { known.clone() },
+                None => {
+                    (file.clone(), fs.read(file).ok().map(|contents| {
+                        hash(&contents)
+                    }))
+                }
+            }
+        };
+        let dir = |dir : &PathBuf| -> (PathBuf, Vec<PathBuf>) {
+            let known = previous.and_then(|snapshot| { 
+                snapshot.dirs.iter().find(|(path, _)| { path == dir }) 
+            });
+            match known {
+                Some(known) => { known.clone() },
+                None => {
+                    let mut entries = fs.list(dir).unwrap_or_default();
+                    entries.sort();
+                    (dir.clone(), entries)
+                }
+            }
+        };
+
+        Snapshot {
+            files : self.files.iter().map(file).collect(),
+            dirs : self.dirs.iter().map(dir).collect()
+        }
+    }
+
+    // Watch directories of watched files by inotify if possible.
+    fn update_watches(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            let fs = self.config.fs();
+            let local : Option<Vec<PathBuf>> = self.files.iter().filter_map(
+                |file| { file.parent().map(Path::to_path_buf) })
+                .chain(self.dirs.iter().cloned())
+                .map(|dir| {
+                    if dir.as_os_str().is_empty() {
+                        fs.local_path(Path::new("."))
+                    } else {
+                        fs.local_path(&dir)
+                    }
+                }).collect();
+            
+            self.inotify = match local {
+                Some(dirs) if !self.polling => {
+                    let mut inotify = self.inotify.take()
+                        .or_else(|| { inotify::Inotify::new().ok() });
+                    if let Some(inotify) = &mut inotify {
+                        inotify.watch(&dirs);
+                    }
+                    inotify
+                },
+                _ => { None }
+            };
+        }
+    }
+
+    // Sleep until timeout expires or inotify reports change.
+    fn sleep(&self, timeout : Duration) {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(inotify) = &self.inotify {
+                inotify.wait(timeout);
+                return;
+            }
+        }
+        thread::sleep(timeout);
+    }
+}
+
+impl ReloadError {
+
+    // Constructor.
+    fn new(config : &Config, path : &Path, error : Errors) -> ReloadError {
+        let (file, line, message) = {
+            match error {
+                Errors::ParseError => {
+                    let (file, line) = config.error_location();
+                    let message = config.error_text()
+                        .unwrap_or_else(|| { String::from("parse error") });
+                    (file.or_else(|| { Some(path.display().to_string()) }), 
+                        line, message)
+                },
+                Errors::FileNotExists => {
+                    (Some(path.display().to_string()), None, 
+                        String::from("file not exists"))
+                },
+                Errors::IncludeError(message) => {
+                    (Some(path.display().to_string()), None, message)
+                },
+                error => { 
+                    (Some(path.display().to_string()), None, 
+                        format!("{:?}", error)) 
+                }
+            }
+        };
+
+        ReloadError { file, line, message }
+    }
+
+    /// Return file containing error.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// use librustconfig::watch::{ConfigWatcher, Reload};
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 80;");
+    /// let setup_fs = fs.clone();
+    /// let mut watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+    ///     cfg.set_fs(setup_fs.clone());
+    /// }).unwrap();
+    /// 
+    /// fs.insert("app.cfg", "port = 80");
+    /// if let Some(Reload::Failed(error)) = watcher.check() {
+    ///     assert_eq!(error.file(), Some("app.cfg"));
+    /// }
+    /// ```
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Return line of parse error.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// use librustconfig::watch::{ConfigWatcher, Reload};
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 80;");
+    /// let setup_fs = fs.clone();
+    /// let mut watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+    ///     cfg.set_fs(setup_fs.clone());
+    /// }).unwrap();
+    /// 
+    /// fs.insert("app.cfg", "\nport = ;");
+    /// if let Some(Reload::Failed(error)) = watcher.check() {
+    ///     assert_eq!(error.line(), Some(2));
+    /// }
+    /// ```
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Return error description.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::vfs::MemoryFs;
+    /// use librustconfig::watch::{ConfigWatcher, Reload};
+    /// 
+    /// let fs = MemoryFs::new();
+    /// fs.insert("app.cfg", "port = 80;");
+    /// let setup_fs = fs.clone();
+    /// let mut watcher = ConfigWatcher::with_setup("app.cfg", move |cfg| {
+    ///     cfg.set_fs(setup_fs.clone());
+    /// }).unwrap();
+    /// 
+    /// fs.take("app.cfg");
+    /// if let Some(Reload::Failed(error)) = watcher.check() {
+    ///     assert_eq!(error.message(), "file not exists");
+    /// }
+    /// ```
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Reload error is shown as `file:line: message`.
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => { write!(f, "{}:{}: ", file, line)?; },
+            (Some(file), None) => { write!(f, "{}: ", file)?; },
+            (None, Some(line)) => { write!(f, "line {}: ", line)?; },
+            (None, None) => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+// Return hash of file contents.
+fn hash(contents : &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Events of directory entries which may change config files.
+    const WATCH_MASK : u32 = libc::IN_CLOSE_WRITE | libc::IN_MODIFY | 
+        libc::IN_ATTRIB | libc::IN_CREATE | libc::IN_DELETE | 
+        libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+
+    /// Inotify instance watching directories.
+    pub(super) struct Inotify {
+        fd : libc::c_int,
+        watches : Vec<(PathBuf, libc::c_int)>
+    }
+
+    impl Inotify {
+
+        // Constructor.
+        pub(super) fn new() -> io::Result<Inotify> {
+            let fd = unsafe { 
+                libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) 
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Inotify { fd, watches : Vec::new() })
+        }
+
+        // Watch directories, watches of other directories are removed. 
+        // Directories which can't be watched are left to polling.
+        pub(super) fn watch(&mut self, dirs : &[PathBuf]) {
+            let fd = self.fd;
+            self.watches.retain(|(dir, watch)| {
+                let keep = dirs.contains(dir);
+                if !keep {
+                    unsafe { libc::inotify_rm_watch(fd, *watch); }
+                }
+                keep
+            });
+
+            for dir in dirs {
+                if self.watches.iter().any(|(watched, _)| { watched == dir }) {
+                    continue;
+                }
+                let path = {
+                    match CString::new(dir.as_os_str().as_bytes()) {
+                        Ok(path) => { path },
+                        Err(_) => { continue; }
+                    }
+                };
+                let watch = unsafe { 
+                    libc::inotify_add_watch(fd, path.as_ptr(), WATCH_MASK) 
+                };
+                if watch >= 0 {
+                    self.watches.push((dir.clone(), watch));
+                }
+            }
+        }
+
+        // Wait for events until timeout expires and discard them, return 
+        // true if there were events.
+        pub(super) fn wait(&self, timeout : Duration) -> bool {
+            let mut poll = libc::pollfd { 
+                fd : self.fd, 
+                events : libc::POLLIN, 
+                revents : 0 
+            };
+            let timeout = timeout.as_millis().min(libc::c_int::MAX as u128);
+            let ready = unsafe { 
+                libc::poll(&mut poll, 1, timeout as libc::c_int) 
+            };
+            if ready <= 0 {
+                return false;
+            }
+
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = unsafe {
+                    libc::read(self.fd, 
+                        buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+                };
+                if read <= 0 {
+                    return true;
+                }
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd); }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+/******************************************************************************/
+/*                               libRustConfig                                */
+/*                   rust wrapper around libconfig library                    */
+/*                  https://github.com/hyperrealm/libconfig                   */
+/*                                                                            */
+/* Copyright (c) 2020                                       Ivan Semenkov     */
+/* https://github.com/isemenkov/librustconfig               ivan@semenkov.pro */
+/*                                                          Ukraine           */
+/******************************************************************************/
+/*                                                                            */
+/* Permission is hereby granted,  free of charge,  to any person obtaining a  */
+/* copy of this software and associated documentation files (the "Software"), */
+/* to deal in the Software without restriction, including without limitation  */
+/* the rights to use, copy,  modify, merge, publish, distribute,  sublicense, */
+/* and/or  sell copies  of the Software,  and to permit persons  to whom  the */
+/* Software  is furnished to  do  so,  subject to  the following  conditions: */
+/*                                                                            */
+/* The above copyright notice and this permission notice shall be included in */
+/* all copies or substantial portions of the Software.                        */
+/*                                                                            */
+/* THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR */
+/* IMPLIED,  INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF  MERCHANTABILITY, */
+/* FITNESS  FOR A PARTICULAR PURPOSE  AND NONINFRINGEMENT. IN  NO EVENT SHALL */
+/* THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER */
+/* LIABILITY,  WHETHER IN AN ACTION  OF CONTRACT,  TORT OR OTHERWISE, ARISING */
+/* FROM,  OUT OF  OR IN  CONNECTION WITH  THE SOFTWARE  OR THE  USE OR  OTHER */
+/* DEALINGS IN THE SOFTWARE.                                                  */
+/*                                                                            */
+/******************************************************************************/
+
+use libconfig_sys as raw;
+use crate::config::{self, Config, Errors, Result};
+use crate::json::{self, JsonMode};
+use crate::merge::{self, Setting};
+use crate::schema;
+use crate::secret;
+
+use serde_json::{Map, Number, Value};
+use std::ffi::CStr;
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+use yaml_rust2::yaml::{Array, Hash};
+
+impl Config {
+
+    /// Save config tree as YAML document, available with `yaml` feature.
+    /// Secret values are redacted unless `Config::set_export_secrets` is set.
+    /// 
+    /// Groups are saved as mappings in config order, arrays and lists as 
+    /// sequences. Both integer types are saved as YAML integers and hex 
+    /// format is dropped. Infinite and NaN floats are saved as `.inf`, 
+    /// `-.inf` and `.nan`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::Config;
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_string(
+    ///     "name = \"app\"; server = { port = 0x1F90; }; ids = ( 1, \"a\" );")
+    ///     .unwrap();
+    /// assert_eq!(cfg.save_to_yaml().unwrap(), 
+    ///     "---\nname: app\nserver:\n  port: 8080\nids:\n  - 1\n  - a\n");
+    /// ```
+    pub fn save_to_yaml(&self) -> Result<String> {
+        let hash = match self.root_setting() {
+            Some(root) => { members(root, self.redacts_exports()) },
+            None => { Hash::new() }
+        };
+
+        let mut yaml = String::new();
+        YamlEmitter::new(&mut yaml).dump(&Yaml::Hash(hash))
+            .map_err(|error| { Errors::YamlError(error.to_string()) })?;
+        yaml.push('\n');
+        Ok(yaml)
+    }
+
+    /// Replace config tree with settings of YAML document, available with 
+    /// `yaml` feature. Config is left unchanged if YAML can't be converted.
+    /// 
+    /// Mappings are loaded as groups and aliases are expanded. Integers 
+    /// fitting 32bit integer are loaded as integers, other as 64bit 
+    /// integers, hexadecimal and octal integers are loaded as decimal. 
+    /// Sequence holding scalars of one type is loaded as array, any other 
+    /// sequence as list. Nulls, infinite and NaN floats, mapping keys which 
+    /// are not valid setting names and streams of several documents can't 
+    /// be represented in libconfig and are reported as `YamlError`.
+    /// 
+    /// # Example
+    /// ```
+    /// use librustconfig::config::{Config, Errors};
+    /// 
+    /// let mut cfg = Config::new();
+    /// cfg.load_from_yaml("size: 5000000000\nusers:\n  - name: root\n")
+    ///     .unwrap();
+    /// assert_eq!(cfg.value("size").unwrap().as_int64(), Some(5000000000));
+    /// assert_eq!(cfg.value("users.[0].name").unwrap().as_string(), 
+    ///     Some(String::from("root")));
+    /// 
+    /// assert_eq!(cfg.load_from_yaml("server:\n  host: ~\n"), 
+    ///     Err(Errors::YamlError(String::from(
+    ///         "server.host: null can't be represented in libconfig"))));
+    /// ```
+    pub fn load_from_yaml<S>(&mut self, yaml : S) -> Result<()> 
+        where S: Into<String> {
+        
+        let documents = YamlLoader::load_from_str(&yaml.into())
+            .map_err(|error| { Errors::YamlError(error.to_string()) })?;
+        let root = match documents.as_slice() {
+            [] | [Yaml::Null] => { Yaml::Hash(Hash::new()) },
+            [document @ Yaml::Hash(_)] => { document.clone() },
+            [_] => { return Err(error("", "expected mapping")); },
+            _ => { return Err(error("", "multiple documents not supported")); }
+        };
+
+        let (_, value) = tagged("", &root)?;
+        self.load_from_json_value(&value, JsonMode::Lossless)
+            .map_err(|error| {
+                match error {
+                    Errors::JsonError(message) => { 
+                        Errors::YamlError(message) 
+                    },
+                    error => { error }
+                }
+            })
+    }
+}
+
+// Convert group members to YAML mapping.
+fn members(group : Setting, redact : bool) -> Hash {
+    let mut hash = Hash::new();
+    for index in 0..merge::length(group) {
+        let member = merge::element(group, index);
+        hash.insert(Yaml::String(merge::setting_name(member)), 
+            to_value(member, redact));
+    }
+    hash
+}
+
+// Convert setting to YAML value, secret values are replaced if redact is 
+// set.
+fn to_value(setting : Setting, redact : bool) -> Yaml {
+    unsafe {
+        match merge::setting_type(setting) {
+            raw::CONFIG_TYPE_GROUP => { 
+                Yaml::Hash(members(setting, redact)) 
+            },
+            raw::CONFIG_TYPE_ARRAY | raw::CONFIG_TYPE_LIST => {
+                Yaml::Array((0..merge::length(setting))
+                    .map(|index| { 
+                        to_value(merge::element(setting, index), redact) 
+                    }).collect::<Array>())
+            },
+            raw::CONFIG_TYPE_INT => {
+                Yaml::Integer(raw::config_setting_get_int(setting) as i64)
+            },
+            raw::CONFIG_TYPE_INT64 => {
+                Yaml::Integer(raw::config_setting_get_int64(setting))
+            },
+            raw::CONFIG_TYPE_FLOAT => {
+                let value = raw::config_setting_get_float(setting);
+                Yaml::Real(match value {
+                    value if value.is_nan() => { String::from(".nan") },
+                    value if value.is_infinite() && value > 0.0 => { 
+                        String::from(".inf") 
+                    },
+                    value if value.is_infinite() => { String::from("-.inf") },
+                    value => { format!("{:?}", value) }
+                })
+            },
+            raw::CONFIG_TYPE_BOOL => {
+                Yaml::Boolean(raw::config_setting_get_bool(setting) != 0)
+            },
+            _ if redact && config::is_secret(setting) => {
+                Yaml::String(String::from(secret::REDACTED))
+            },
+            _ => {
+                let value = raw::config_setting_get_string(setting);
+                if value.is_null() {
+                    Yaml::String(String::new())
+                } else {
+                    Yaml::String(CStr::from_ptr(value).to_string_lossy()
+                        .into_owned())
+                }
+            }
+        }
+    }
+}
+
+// Convert YAML value to JSON value tagged with libconfig type.
+fn tagged(path : &str, value : &Yaml) -> Result<(i16, Value)> {
+    let (kind, value) = match value {
+        Yaml::String(value) => { 
+            (raw::CONFIG_TYPE_STRING, Value::from(value.as_str())) 
+        },
+        Yaml::Integer(value) => {
+            let kind = json::integer_type(*value);
+            if kind == raw::CONFIG_TYPE_INT64 {
+                (kind, Value::from(value.to_string()))
+            } else {
+                (kind, Value::from(*value))
+            }
+        },
+        Yaml::Real(_) => {
+            let number = value.as_f64().and_then(Number::from_f64)
+                .ok_or_else(|| {
+                    error(path, "float can't be represented in libconfig")
+                })?;
+            (raw::CONFIG_TYPE_FLOAT, Value::Number(number))
+        },
+        Yaml::Boolean(value) => { 
+            (raw::CONFIG_TYPE_BOOL, Value::Bool(*value)) 
+        },
+        Yaml::Array(elements) => {
+            let mut kinds = Vec::new();
+            let mut values = Vec::new();
+            for (index, element) in elements.iter().enumerate() {
+                let (kind, value) = tagged(&json::element_path(path, index), 
+                    element)?;
+                kinds.push(kind);
+                values.push(value);
+            }
+            (json::collection_type(&kinds), Value::Array(values))
+        },
+        Yaml::Hash(hash) => {
+            let mut members = Map::new();
+            for (key, member) in hash {
+                let name = key.as_str().ok_or_else(|| {
+                    error(path, &format!("key {:?} is not a string", key))
+                })?;
+                let (_, value) = tagged(&schema::join_path(path, name), 
+                    member)?;
+                members.insert(name.to_string(), value);
+            }
+            (raw::CONFIG_TYPE_GROUP, Value::Object(members))
+        },
+        Yaml::Null => {
+            return Err(error(path, "null can't be represented in libconfig"));
+        },
+        _ => { return Err(error(path, "invalid value")); }
+    };
+    Ok((kind, json::tag(kind, value)))
+}
+
+// Return conversion error at path.
+fn error(path : &str, message : &str) -> Errors {
+    if path.is_empty() {
+        Errors::YamlError(message.to_string())
+    } else {
+        Errors::YamlError(format!("{}: {}", path, message))
+    }
+}